@@ -0,0 +1,66 @@
+// Compares the buffered and memory-mapped read paths on random primary key lookups, with a buffer pool big enough
+// to hold the whole file and with one that's mostly missing. Both paths copy the page bytes they read, so the
+// difference is the seek and read calls the map skips. Run with `cargo bench --bench read_path`.
+use std::{env, fs, time::{Duration, Instant}};
+
+use pocket_db::{ColumnType, DataType, OpenOptions, PocketDB, ReadMode, PAGE_SIZE};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const ROWS: u32 = 1_000;
+const LOOKUPS: usize = 20_000;
+
+fn build(path: &str) {
+    let _ = fs::remove_file(path);
+
+    let db = PocketDB::open(path).unwrap();
+    db.add_indexed_column("name", ColumnType::Text)
+        .and_then(|db| db.add_column("age", ColumnType::Integer))
+        .expect("Failed to add columns");
+
+    for key in 1..=ROWS {
+        let row = PocketDB::row(key, vec![
+            PocketDB::cell(DataType::Text(format!("name-{}", key % 97))),
+            PocketDB::cell(DataType::Integer(key)),
+        ]);
+        db.insert(key, row).expect("Failed to insert row");
+    }
+}
+
+fn lookups(path: &str, buffer_pool_size: usize, read_mode: ReadMode) -> Duration {
+    let mut options = OpenOptions::new();
+    options.set_buffer_pool_size(buffer_pool_size).set_read_mode(read_mode);
+    let db = PocketDB::open_with_options(path, &options).unwrap();
+
+    let mut rng = StdRng::seed_from_u64(7);
+    let keys: Vec<u32> = (0..LOOKUPS).map(|_| rng.gen_range(1..=ROWS)).collect();
+
+    let start = Instant::now();
+    for key in keys {
+        let rows = db.search_by_primary_index(DataType::Integer(key));
+        assert!(rows.is_some(), "Missing key {}", key);
+    }
+
+    start.elapsed()
+}
+
+fn main() {
+    let path = env::temp_dir().join("pocket_db_read_path.db");
+    let path = path.to_str().expect("Temp path is not UTF-8");
+    build(path);
+
+    println!("{} lookups over {} rows ({} bytes)", LOOKUPS, ROWS, fs::metadata(path).map(|m| m.len()).unwrap_or(0));
+    for (label, buffer_pool_size) in [("warm pool", OpenOptions::new().buffer_pool_size()), ("4 page pool", 4 * PAGE_SIZE)] {
+        for read_mode in [ReadMode::Buffered, ReadMode::Mmap] {
+            let elapsed = lookups(path, buffer_pool_size, read_mode);
+            println!(
+                "{:<12} {:<10} {:>10.2?} {:>8} ns/lookup",
+                label,
+                format!("{:?}", read_mode),
+                elapsed,
+                elapsed.as_nanos() / LOOKUPS as u128
+            );
+        }
+    }
+
+    let _ = fs::remove_file(path);
+}
@@ -0,0 +1,79 @@
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Field, Fields};
+
+// `#[derive(PocketRecord)]` implements `pocket_db::PocketRecord` for a struct with named fields. One field, a `u32`,
+// is marked `#[key]`; the others become columns in the order they're declared, indexed if marked `#[index]`. Field
+// types are mapped to column types through `pocket_db::ColumnValue`, so a field of any other type doesn't compile.
+#[proc_macro_derive(PocketRecord, attributes(key, index))]
+pub fn derive_pocket_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match pocket_record(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn pocket_record(input: &DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect::<Vec<&Field>>(),
+            _ => return Err(Error::new(Span::call_site(), "PocketRecord needs a struct with named fields")),
+        },
+        _ => return Err(Error::new(Span::call_site(), "PocketRecord can only be derived for structs")),
+    };
+
+    let keys: Vec<&Field> = fields.iter().copied().filter(|field| has_attribute(field, "key")).collect();
+    let key = match keys.as_slice() {
+        [key] => *key,
+        [] => return Err(Error::new(Span::call_site(), "PocketRecord needs a field marked #[key]")),
+        [_, second, ..] => return Err(Error::new_spanned(second, "PocketRecord can only have one #[key] field")),
+    };
+    if has_attribute(key, "index") {
+        return Err(Error::new_spanned(key, "The #[key] field is already indexed by the primary index"));
+    }
+    let key_name = &key.ident;
+    let key_type = &key.ty;
+
+    let columns: Vec<&Field> = fields.iter().copied().filter(|field| !has_attribute(field, "key")).collect();
+    let column_names: Vec<_> = columns.iter().map(|field| &field.ident).collect();
+    let column_types: Vec<_> = columns.iter().map(|field| &field.ty).collect();
+    let column_strings: Vec<String> = column_names.iter().map(|name| name.as_ref().map(|name| name.to_string()).unwrap_or_default()).collect();
+    let indexed: Vec<bool> = columns.iter().map(|field| has_attribute(field, "index")).collect();
+    let positions = 1..=columns.len();
+
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::pocket_db::PocketRecord for #name #type_generics #where_clause {
+            fn columns() -> ::std::vec::Vec<(&'static str, ::pocket_db::ColumnType, bool)> {
+                vec![#((#column_strings, <#column_types as ::pocket_db::ColumnValue>::COLUMN_TYPE, #indexed)),*]
+            }
+
+            fn key(&self) -> u32 {
+                let key: &u32 = &self.#key_name;
+                *key
+            }
+
+            fn into_row(self) -> ::pocket_db::Row {
+                ::pocket_db::PocketDB::row(self.#key_name, vec![
+                    #(::pocket_db::PocketDB::cell(::pocket_db::ColumnValue::into_data_type(self.#column_names))),*
+                ])
+            }
+
+            fn from_values(values: &[::pocket_db::DataType]) -> ::std::option::Option<Self> {
+                Some(#name {
+                    #key_name: <#key_type as ::pocket_db::ColumnValue>::from_data_type(values.first()?)?,
+                    #(#column_names: <#column_types as ::pocket_db::ColumnValue>::from_data_type(values.get(#positions)?)?),*
+                })
+            }
+        }
+    })
+}
+
+fn has_attribute(field: &Field, name: &str) -> bool {
+    field.attrs.iter().any(|attribute| attribute.path().is_ident(name))
+}
@@ -0,0 +1,55 @@
+use std::{fs::File, io, process};
+
+use pocket_db::{CsvOptions, PocketDB};
+
+// `pocketdb <database> import <file.csv>` loads a CSV file and `pocketdb <database> export <file.csv>` writes one,
+// with `-` for stdin or stdout. Returns false if the arguments aren't a command.
+pub fn run(args: &[String]) -> bool {
+    let (path, command, file) = match args {
+        [_, path, command, file] if command == "import" || command == "export" => (path, command, file),
+        _ => return false,
+    };
+
+    let db = PocketDB::open(path).unwrap_or_else(|e| fail(format!("Failed to open {}: {}", path, e)));
+    if command == "import" {
+        import(&db, file);
+    } else {
+        export(&db, file);
+    }
+
+    true
+}
+
+fn import(db: &PocketDB, file: &str) {
+    let options = CsvOptions::new();
+    let import = if file == "-" {
+        db.import_csv(io::stdin().lock(), &options)
+    } else {
+        let reader = File::open(file).unwrap_or_else(|e| fail(format!("Failed to open {}: {}", file, e)));
+        db.import_csv(reader, &options)
+    };
+    let import = import.unwrap_or_else(|e| fail(format!("Failed to import {}: {}", file, e)));
+
+    for e in &import.errors {
+        eprintln!("Skipped {}", e);
+    }
+    println!("Imported {} rows, skipped {}", import.imported, import.errors.len());
+}
+
+// Row counts go to stderr so they stay out of CSV written to stdout
+fn export(db: &PocketDB, file: &str) {
+    let exported = if file == "-" {
+        db.export_csv(io::stdout().lock())
+    } else {
+        let writer = File::create(file).unwrap_or_else(|e| fail(format!("Failed to create {}: {}", file, e)));
+        db.export_csv(writer)
+    };
+    let exported = exported.unwrap_or_else(|e| fail(format!("Failed to export {}: {}", file, e)));
+
+    eprintln!("Exported {} rows", exported);
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
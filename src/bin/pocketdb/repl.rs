@@ -0,0 +1,433 @@
+use std::{env, fs::File, io::BufRead};
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+
+use pocket_db::{ColumnType, CsvOptions, DataType, PocketDB, TABLE_NAME};
+
+const HELP: &str = "\
+.open PATH                      Open a database file, or memory with no PATH
+.schema                         Show the columns, their types and indexes
+.tables                         List the tables
+.stats                          Show free space and buffer pool stats
+.import PATH / .export PATH     Load or write CSV
+.help                           Show this help
+.quit                           Exit
+add COLUMN TYPE [indexed]       Add a column of type integer, text or bool
+insert KEY VALUE...             Insert a row, one value per column after the key
+get KEY                         Look up a row by key
+find COLUMN VALUE               Look up rows by an indexed column
+delete KEY                      Delete a row by key
+delete COLUMN VALUE             Delete rows by an indexed column
+Values with spaces go in double quotes.
+SQL statements end with ; and the table is called main, for example SELECT * FROM main WHERE key = 1;";
+
+// Commands run against one open database at a time, starting in memory. Each command's output comes back as text,
+// so interactive and scripted runs print the same thing.
+pub struct Repl {
+    db: PocketDB,
+    path: Option<String>,
+    pending_sql: String,
+}
+
+impl Repl {
+    pub fn new(path: Option<&str>) -> Result<Repl, String> {
+        let mut repl = Repl { db: PocketDB::in_memory(), path: None, pending_sql: String::new() };
+        if let Some(path) = path {
+            repl.open(Some(path))?;
+        }
+
+        Ok(repl)
+    }
+
+    // Line editing and history, which is kept in ~/.pocketdb_history between sessions
+    pub fn run_interactive(&mut self) {
+        let mut editor = match DefaultEditor::new() {
+            Ok(editor) => editor,
+            Err(e) => {
+                println!("Failed to start line editor: {}", e);
+                return;
+            },
+        };
+        let history_path = env::var("HOME").ok().map(|home| format!("{}/.pocketdb_history", home));
+        if let Some(history_path) = &history_path {
+            let _ = editor.load_history(history_path);
+        }
+
+        println!("PocketDB. Enter .help for commands.");
+        loop {
+            let prompt = if self.pending_sql.is_empty() {
+                format!("{}> ", self.path.as_deref().unwrap_or(":memory:"))
+            } else {
+                String::from("...> ")
+            };
+            let line = match editor.readline(&prompt) {
+                Ok(line) => line,
+                Err(ReadlineError::Interrupted) => continue,
+                Err(ReadlineError::Eof) => break,
+                Err(e) => {
+                    println!("Failed to read line: {}", e);
+                    break;
+                },
+            };
+            if !line.trim().is_empty() {
+                let _ = editor.add_history_entry(line.as_str());
+            }
+
+            match self.execute(&line) {
+                Ok(Some(output)) => println!("{}", output),
+                Ok(None) => (),
+                Err(e) if e == ".quit" => break,
+                Err(e) => println!("Error: {}", e),
+            }
+        }
+
+        if let Some(history_path) = &history_path {
+            let _ = editor.save_history(history_path);
+        }
+    }
+
+    // Runs one command per line with no prompts. Errors go to stderr with their line number and the run carries on.
+    // Returns false if any command failed.
+    pub fn run_script(&mut self, reader: impl BufRead) -> bool {
+        let mut succeeded = true;
+
+        for (i, line) in reader.lines().enumerate() {
+            let line = match line {
+                Ok(line) => line,
+                Err(e) => {
+                    eprintln!("Failed to read line {}: {}", i + 1, e);
+                    return false;
+                },
+            };
+
+            match self.execute(&line) {
+                Ok(Some(output)) => println!("{}", output),
+                Ok(None) => (),
+                Err(e) if e == ".quit" => break,
+                Err(e) => {
+                    eprintln!("Error on line {}: {}", i + 1, e);
+                    succeeded = false;
+                },
+            }
+        }
+
+        if !self.pending_sql.is_empty() {
+            eprintln!("Error: SQL statement has no closing ;");
+            succeeded = false;
+        }
+
+        succeeded
+    }
+
+    // `.quit` comes back as an error so callers stop reading. SQL can run over several lines and is held back until
+    // a line ends in `;`.
+    pub fn execute(&mut self, line: &str) -> Result<Option<String>, String> {
+        if !self.pending_sql.is_empty() || Repl::is_sql(line) {
+            self.pending_sql.push_str(line);
+            self.pending_sql.push('\n');
+            if !line.trim_end().ends_with(';') {
+                return Ok(None);
+            }
+
+            let sql = std::mem::take(&mut self.pending_sql);
+            return self.sql(&sql);
+        }
+
+        let words = Repl::split(line)?;
+        let args: Vec<&str> = words.iter().skip(1).map(|word| word.as_str()).collect();
+        let command = match words.first() {
+            Some(command) => command.as_str(),
+            None => return Ok(None),
+        };
+
+        match (command, args.as_slice()) {
+            (".help", []) => Ok(Some(String::from(HELP))),
+            (".quit" | ".exit", []) => Err(String::from(".quit")),
+            (".open", []) => self.open(None).map(|_| None),
+            (".open", [path]) => self.open(Some(path)).map(|_| None),
+            (".schema", []) => Ok(Some(self.schema())),
+            (".tables", []) => Ok(Some(String::from(TABLE_NAME))),
+            (".stats", []) => Ok(Some(self.stats())),
+            (".import", [path]) => self.import(path).map(Some),
+            (".export", [path]) => self.export(path).map(Some),
+            ("add", [column, column_type]) => self.add(column, column_type, false).map(|_| None),
+            ("add", [column, column_type, "indexed"]) => self.add(column, column_type, true).map(|_| None),
+            ("insert", [key, values @ ..]) => self.insert(key, values).map(|_| None),
+            ("get", [key]) => {
+                let rows = self.db.search_by_primary_index(DataType::Integer(Repl::key(key)?));
+                Ok(Some(self.render(rows.unwrap_or_default())))
+            },
+            ("find", [column, value]) => {
+                let rows = self.db.search_by_secondary_index(self.indexed_value(column, value)?, column);
+                Ok(Some(self.render(rows.unwrap_or_default())))
+            },
+            ("delete", [key]) => {
+                self.db.delete_by_primary_index(DataType::Integer(Repl::key(key)?)).map(|_| None)
+            },
+            ("delete", [column, value]) => {
+                self.db.delete_by_secondary_index(self.indexed_value(column, value)?, column).map(|_| None)
+            },
+            _ => Err(format!("Unknown command or wrong arguments: {}. Enter .help for commands.", line.trim())),
+        }
+    }
+
+    fn open(&mut self, path: Option<&str>) -> Result<(), String> {
+        self.db = match path {
+            Some(path) => PocketDB::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?,
+            None => PocketDB::in_memory(),
+        };
+        self.path = path.map(|path| path.to_string());
+
+        Ok(())
+    }
+
+    fn schema(&self) -> String {
+        let indexed_columns = self.db.indexed_columns();
+        let rows = self.db.columns()
+            .into_iter()
+            .map(|(name, column_type)| {
+                let indexed = if name == "key" { "primary" } else if indexed_columns.contains(&name) { "secondary" } else { "" };
+                vec![name, format!("{:?}", column_type).to_lowercase(), String::from(indexed)]
+            })
+            .collect();
+
+        Repl::table(&[String::from("column"), String::from("type"), String::from("index")], rows)
+    }
+
+    fn stats(&self) -> String {
+        let free_space = self.db.free_space_stats();
+        let buffer_pool = self.db.buffer_pool_stats();
+        let statistics = self.db.statistics();
+        let mut rows = vec![vec![String::from("rows"), statistics.rows.to_string()]];
+        for index in [&statistics.primary].into_iter().chain(&statistics.indexes) {
+            let shape = format!(
+                "height {}, nodes {}, {:.0}% full, distinct keys {}",
+                index.height,
+                index.nodes,
+                index.average_fill() * 100.0,
+                index.distinct_keys,
+            );
+            rows.push(vec![format!("index on {}", index.column), shape]);
+        }
+        rows.extend([
+            vec![String::from("free bytes"), free_space.free_bytes.to_string()],
+            vec![String::from("free extents"), free_space.free_extents.to_string()],
+            vec![String::from("largest extent"), free_space.largest_extent.to_string()],
+            vec![String::from("fragmentation"), format!("{:.2}", free_space.fragmentation())],
+            vec![String::from("cached pages"), format!("{} ({} of {} bytes)", buffer_pool.pages, buffer_pool.bytes, buffer_pool.budget)],
+            vec![String::from("cache hits"), buffer_pool.hits.to_string()],
+            vec![String::from("cache misses"), buffer_pool.misses.to_string()],
+        ]);
+
+        Repl::table(&[String::from("stat"), String::from("value")], rows)
+    }
+
+    fn import(&self, path: &str) -> Result<String, String> {
+        let reader = File::open(path).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        let import = self.db.import_csv(reader, &CsvOptions::new())?;
+
+        let mut output: Vec<String> = import.errors.iter().map(|e| format!("Skipped {}", e)).collect();
+        output.push(format!("Imported {} rows, skipped {}", import.imported, import.errors.len()));
+
+        Ok(output.join("\n"))
+    }
+
+    fn export(&self, path: &str) -> Result<String, String> {
+        let writer = File::create(path).map_err(|e| format!("Failed to create {}: {}", path, e))?;
+
+        self.db.export_csv(writer).map(|exported| format!("Exported {} rows", exported))
+    }
+
+    fn add(&self, column: &str, column_type: &str, indexed: bool) -> Result<(), String> {
+        let column_type = match column_type.to_lowercase().as_str() {
+            "integer" | "int" => ColumnType::Integer,
+            "text" => ColumnType::Text,
+            "bool" => ColumnType::Bool,
+            _ => return Err(format!("Unknown column type {}", column_type)),
+        };
+        if self.db.columns().iter().any(|(name, _)| name == column) {
+            return Err(format!("Column {} already exists", column));
+        }
+
+        if indexed {
+            self.db.add_indexed_column(column, column_type)?;
+        } else {
+            self.db.add_column(column, column_type)?;
+        }
+
+        Ok(())
+    }
+
+    fn insert(&self, key: &str, values: &[&str]) -> Result<(), String> {
+        let key = Repl::key(key)?;
+        let columns = self.db.columns();
+        if values.len() != columns.len() - 1 {
+            return Err(format!("Expected {} values but found {}", columns.len() - 1, values.len()));
+        }
+
+        let cells = columns
+            .iter()
+            .skip(1)
+            .zip(values)
+            .map(|((name, column_type), value)| {
+                column_type.parse(value)
+                    .map(PocketDB::cell)
+                    .map_err(|e| format!("{} for column {}", e, name))
+            })
+            .collect::<Result<_, String>>()?;
+
+        self.db.insert(key, PocketDB::row(key, cells))
+    }
+
+    fn key(key: &str) -> Result<u32, String> {
+        key.parse().map_err(|_| format!("Invalid key {:?}", key))
+    }
+
+    fn indexed_value(&self, column: &str, value: &str) -> Result<DataType, String> {
+        if !self.db.indexed_columns().iter().any(|name| name == column) {
+            return Err(format!("Column {} has no index", column));
+        }
+
+        let (_, column_type) = self.db.columns().into_iter().find(|(name, _)| name == column).ok_or(format!("No column named {}", column))?;
+
+        column_type.parse(value).map_err(|e| format!("{} for column {}", e, column))
+    }
+
+    // Rows come back with the row head first and padding last, which aren't shown
+    fn render(&self, rows: Vec<Vec<DataType>>) -> String {
+        let columns = self.db.columns();
+        let rows = rows.into_iter().map(|row| row.into_iter().skip(1).take(columns.len()).collect()).collect();
+
+        Repl::render_values(&columns, rows)
+    }
+
+    fn render_values(columns: &[(String, ColumnType)], rows: Vec<Vec<DataType>>) -> String {
+        let headers: Vec<String> = columns.iter().map(|(name, _)| name.clone()).collect();
+        let num_rows = rows.len();
+
+        let rows = rows
+            .into_iter()
+            .map(|row| row
+                .into_iter()
+                .map(|data| match data {
+                    DataType::Integer(value) => value.to_string(),
+                    DataType::Text(value) => value,
+                    DataType::Bool(value) => value.to_string(),
+                    DataType::Meta => String::new(),
+                })
+                .collect())
+            .collect();
+
+        format!("{}\n({} row{})", Repl::table(&headers, rows), num_rows, if num_rows == 1 { "" } else { "s" })
+    }
+
+    // The REPL's own `insert` and `delete` commands take a key, so SQL is told apart by the word after them
+    fn is_sql(line: &str) -> bool {
+        let mut words = line.split_whitespace().map(|word| word.to_ascii_lowercase());
+        matches!(
+            (words.next().as_deref(), words.next().as_deref()),
+            (Some("select" | "create" | "update" | "explain"), _) | (Some("insert"), Some("into")) | (Some("delete"), Some("from"))
+        )
+    }
+
+    fn sql(&self, sql: &str) -> Result<Option<String>, String> {
+        let result = self.db.execute(sql).map_err(|e| e.to_string())?;
+        if !result.columns().is_empty() {
+            return Ok(Some(Repl::render_values(result.columns(), result.rows().to_vec())));
+        }
+        if result.rows_affected() > 0 {
+            return Ok(Some(format!("({} row{} affected)", result.rows_affected(), if result.rows_affected() == 1 { "" } else { "s" })));
+        }
+
+        Ok(None)
+    }
+
+    fn table(headers: &[String], rows: Vec<Vec<String>>) -> String {
+        let mut widths: Vec<usize> = headers.iter().map(|header| header.chars().count()).collect();
+        for row in &rows {
+            for (width, value) in widths.iter_mut().zip(row) {
+                *width = (*width).max(value.chars().count());
+            }
+        }
+
+        let border = widths.iter().fold(String::from("+"), |border, width| format!("{}{}+", border, "-".repeat(width + 2)));
+        let line = |values: &[String]| {
+            values.iter().zip(&widths).fold(String::from("|"), |line, (value, width)| {
+                format!("{} {}{} |", line, value, " ".repeat(width - value.chars().count()))
+            })
+        };
+
+        let mut lines = vec![border.clone(), line(headers), border.clone()];
+        lines.extend(rows.iter().map(|row| line(row)));
+        if !rows.is_empty() {
+            lines.push(border);
+        }
+
+        lines.join("\n")
+    }
+
+    // Splits on whitespace, keeping double-quoted words together. `\"` and `\\` escape inside quotes.
+    fn split(line: &str) -> Result<Vec<String>, String> {
+        let mut words = vec![];
+        let mut chars = line.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+
+            let mut word = String::new();
+            if c == '"' {
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => word.push(chars.next().ok_or("Unterminated quote")?),
+                        Some(c) => word.push(c),
+                        None => return Err(String::from("Unterminated quote")),
+                    }
+                }
+            } else {
+                word.push(c);
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    word.push(c);
+                }
+            }
+            words.push(word);
+        }
+
+        Ok(words)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Repl;
+
+    #[test]
+    fn scripts_build_and_query_a_table() {
+        let mut repl = Repl::new(None).unwrap();
+        for line in ["add name text indexed", "add age int", "insert 1 \"Alex Smith\" 30", "insert 2 Sam 41", "", "delete 2"] {
+            assert_eq!(repl.execute(line), Ok(None), "{}", line);
+        }
+
+        assert_eq!(repl.execute("get 1").unwrap().unwrap(), "\
++-----+------------+-----+
+| key | name       | age |
++-----+------------+-----+
+| 1   | Alex Smith | 30  |
++-----+------------+-----+
+(1 row)");
+        assert_eq!(repl.execute("find name Sam").unwrap().unwrap(), "\
++-----+------+-----+
+| key | name | age |
++-----+------+-----+
+(0 rows)");
+        assert!(repl.execute(".schema").unwrap().unwrap().contains("| name   | text    | secondary |"));
+
+        assert_eq!(repl.execute("insert 3 Jo old"), Err(String::from("Invalid integer \"old\" for column age")));
+        assert!(repl.execute("find age 30").is_err());
+        assert!(repl.execute("add name text").is_err());
+        assert!(repl.execute("insert 4 \"open").is_err());
+        assert_eq!(repl.execute(".quit"), Err(String::from(".quit")));
+    }
+}
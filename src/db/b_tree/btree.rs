@@ -1,9 +1,28 @@
-use std::cmp::{max, min};
+use std::{cmp::{max, min}, ops::{Bound, RangeBounds}};
 
-use crate::db::{b_tree::tree_node::TreeNode, file_stystem::pager::Pager, shared::{constants::{node::NODE_SIZE, node_child::{NODE_MAX_CHILDREN, NODE_MIN_CHILDREN}, node_key::{NODE_MAX_KEYS, NODE_MIN_KEYS}}, enums::DataType}, table::{disk_storage::row::Row, table::Table, disk_storage}};
+use crate::db::{b_tree::tree_node::TreeNode, query::statistics::IndexStatistics, file_stystem::pager::Pager, meta::vacuum::BlockSource, shared::{constants::{node::NODE_SIZE, node_child::{NODE_MAX_CHILDREN, NODE_MIN_CHILDREN}, node_key::{NODE_MAX_KEYS, NODE_MIN_KEYS}, statistics::OVERFLOW_BUCKETS}, enums::DataType}, table::{disk_storage::row::Row, table::Table, disk_storage}};
 
 use super::disk_storage::{node::{Node, NodeIndexType, NodeType}, node_overflow::NodeOverflow};
 
+// What deleting a key did to the node it was under
+enum Deletion {
+    NotFound,
+    // The key's rows, and whether the node was left without children
+    Deleted(Vec<Row>, bool),
+}
+
+// The rows taken from the end of a node, and the key before them unless the node was left without children
+type LastRows = (Vec<Row>, Option<(u32, usize)>);
+
+// What taking one row out of a key's rows did to the node it was under
+enum RowRemoval {
+    NotFound,
+    // The row was the key's only one, so the key has to go as well
+    LastOfKey,
+    // The row is gone and the key's rows start at this one
+    Removed((u32, usize)),
+}
+
 #[derive(Debug)]
 pub struct BTree {
     root: Option<TreeNode>,
@@ -329,417 +348,291 @@ impl BTree {
         }
     }
 
-    pub fn search<'a>(&'a mut self, key: Vec<u8>, pager: &mut Pager, table: &'a mut Table) -> Option<Vec<&'a Row>> {
-        if let Some(root) = &mut self.root {
-            BTree::search_node(root, key, pager, table)
-        } else {
-            None
-        }
-    }
-
-    fn search_node<'a>(node: &'a mut TreeNode, key: Vec<u8>, pager: &mut Pager, table: &'a mut Table) -> Option<Vec<&'a Row>> {
-        let (i, _) = BTree::key_index_from_node(&node, &key, pager, table);
-
-        match node.node_type() {
-            NodeType::Internal => BTree::search_internal(node, key, i, pager, table),
-            NodeType::Leaf => BTree::search_leaf(node, key, i, pager, table)
+    // Offsets and sizes of the rows whose key is `key`, under the node at `offset`. Nodes and rows are read from the
+    // source a block at a time, so a snapshot only holds the pager for one block at once.
+    pub(crate) fn search(source: &mut dyn BlockSource, offset: u32, size: usize, key: &[u8]) -> Option<Vec<(u32, usize)>> {
+        let node = Node::from_bytes(&source.read_block(offset, size)?);
+        let i = BTree::key_index(source, &node, key);
+        if i >= node.num_children() {
+            return None;
         }
-    }
-
-    fn search_internal<'a>(node: &'a mut TreeNode, key: Vec<u8>, i: usize, pager: &mut Pager, table: &'a mut Table) -> Option<Vec<&'a Row>> {
-        if let None = node.cached_tree_node_child(i) {
-            if let Some((child_offset, child_size, _)) = node.child(i) {
-                if let Some(bytes) = pager.read_from_file(child_offset as usize, child_size) {
-                    node.cache_tree_node_child(TreeNode::from_bytes(bytes), i);
-                }
-            }
-        }
-
-        if let Some(child_node) = node.chached_mut_tree_node_child(i) {
-            if let Some(row) = BTree::search_node(child_node, key, pager, table) {
-                return Some(row);
-            } 
-        }
-
-        None
-    }
-
-    fn search_leaf<'a>(node: &'a mut TreeNode, key: Vec<u8>, i: usize, pager: &mut Pager, table: &'a mut Table) -> Option<Vec<&'a Row>> {
-        if let Some((child_offset, child_size, is_overflowing)) = node.child(i) {
-            if !is_overflowing {
-                let row_meta_data = (child_offset, child_size);
-                if key.cmp(&BTree::key_value_from_table(row_meta_data, node.node_indexed_column(), pager, table)).is_eq() {
-                    if let Some(row) = table.row(child_offset) {
-                        return Some(vec![row]);
-                    }                
-                }
-            } else {
-                if let None = node.cached_node_overflow_child(i) {
-                    if let Some(bytes) = pager.read_from_file(child_offset as usize, child_size) {
-                        node.cache_node_overflow_child(NodeOverflow::from_bytes(bytes), i);
-                    }
-                }
-
-                if let Some(overflow_child) = node.overflow_children(i) {
-                    for (overflow_child_offset, overflow_child_size) in overflow_child.items() {
-                        let row_meta_data = (overflow_child_offset, overflow_child_size);
-                        if !key.cmp(&BTree::key_value_from_table(row_meta_data, node.node_indexed_column(), pager, table)).is_eq() {
-                            return None;
-                        }
-                    }
-                }
+        let (child_offset, child_size) = (node.child_offset_as_u32(i), node.child_size_as_usize(i));
 
-                if let Some(overflow_child) = node.overflow_children(i) {
-                    let rows: Vec<&Row> = overflow_child.items()
-                        .iter()
-                        .filter_map(|(overflow_child_offset, _)| table.row(*overflow_child_offset))
-                        .collect();
+        match (node.node_type(), node.child_is_overflowing(i)) {
+            (NodeType::Internal, _) => BTree::search(source, child_offset, child_size, key),
+            (NodeType::Leaf, false) => (BTree::row_key(source, &node, (child_offset, child_size)) == key).then(|| vec![(child_offset, child_size)]),
+            (NodeType::Leaf, true) => {
+                // Rows in an overflow list share a key, so the first one stands for all of them
+                let items = NodeOverflow::from_bytes(source.read_block(child_offset, child_size)?).items();
 
-                    return Some(rows);
-                }
-            }
+                (BTree::row_key(source, &node, *items.first()?) == key).then_some(items)
+            },
         }
-
-        None
     }
 
+    // Deleting never rebalances: nodes can be left with fewer keys than a split gives them, and ones left without
+    // children are dropped. Each key stays in exactly one node, with its rows at that key's child in a leaf, or for a
+    // key in an internal node, at the end of the rightmost leaf left of it. Nodes are read back through the pager,
+    // which also has the blocks written since the last flush, so nothing is cached while children shift.
     pub fn delete(&mut self, key: Vec<u8>, pager: &mut Pager, table: &mut Table) -> Option<Vec<Row>> {
-        if let Some(root) = self.root.take() {
-            let (root, deleted_rows) = BTree::delete_node(root, key, pager, table); 
-            self.root = Some(root);
+        let mut root = self.root.take()?;
+        root.release_cached_children();
+        let deletion = BTree::delete_from_node(&mut root, &key, pager, table);
 
-            return deleted_rows;
+        // * A root left without children starts over as an empty leaf *
+        if root.children_len() == 0 {
+            root.set_node_type(NodeType::Leaf);
         }
+        self.root = Some(root);
 
-        None
-    }
-
-    fn delete_node(mut node: TreeNode, key: Vec<u8>, pager: &mut Pager, table: &mut Table) -> (TreeNode, Option<Vec<Row>>) {
-        let (i, _) = BTree::key_index_from_node(&node, &key, pager, table);
-        let i = if i < node.children_len() || i <= 0 { i } else { node.children_len() - 1 };
-
-        match node.node_type() {
-            NodeType::Internal => BTree::delete_internal(node, key, i, pager, table),
-            NodeType::Leaf => BTree::delete_leaf(node, key, i, pager, table)
+        match deletion {
+            Deletion::Deleted(rows, _) => Some(rows),
+            Deletion::NotFound => None,
         }
     }
 
-    fn delete_internal(mut node: TreeNode, key: Vec<u8>, i: usize, pager: &mut Pager, table: &mut Table) -> (TreeNode, Option<Vec<Row>>) {
-        // * Load child in if not in cache *
-        if let None = node.cached_tree_node_child(i) {
-            if let Some((child_offset, child_size, _)) = node.child(i) {
-                if let Some(bytes) = pager.read_from_file(child_offset as usize, child_size) {
-                    node.cache_tree_node_child(TreeNode::from_bytes(bytes), i);
-                }
-            }
-        }
-
-        // * If key match found... *
-        let node_key_value = BTree::key_value_from_node(&node, i, pager, table);
-        let deleted_rows = if i < NODE_MAX_KEYS && key.cmp(&node_key_value).is_eq() {
-            // * Traverse down to find row to remove at before removing key *
-            let deleted_rows = if let Some(child) = node.take_cached_tree_node_child(i) {
-                let (child, deleted_rows) = BTree::delete_node(child, key, pager, table);
-                node.cache_tree_node_child(child, i);
+    fn delete_from_node(node: &mut TreeNode, key: &Vec<u8>, pager: &mut Pager, table: &mut Table) -> Deletion {
+        let (i, found) = BTree::key_index_from_node(node, key, pager, table);
 
-                deleted_rows
-            } else {
-                None
-            };
-            
-            // * Remove key if rows removed and balance *
-            if let Some(_) = deleted_rows {
+        match (node.node_type(), found) {
+            (NodeType::Leaf, false) => Deletion::NotFound,
+            (NodeType::Leaf, true) => {
+                let rows = BTree::take_leaf_rows(node, i, pager, table);
                 node.remove_key(i);
-                BTree::delete_balance(&mut node, i, pager, table);
-            }
-
-            deleted_rows
-        } else {
-            // * Traverse down to find row to remove at *
-            if let Some(child) = node.take_cached_tree_node_child(i) {
-                let (child, deleted_rows) = BTree::delete_node(child, key, pager, table);
-                node.cache_tree_node_child(child, i);
 
-                deleted_rows
-            } else {
-                None
-            }
-        };
+                Deletion::Deleted(rows, node.children_len() == 0)
+            },
+            (NodeType::Internal, true) => {
+                let Some(mut child) = BTree::read_child(node, i, pager) else {
+                    return Deletion::NotFound;
+                };
+                let Some((rows, previous_key)) = BTree::delete_last(&mut child, key, pager, table) else {
+                    return Deletion::NotFound;
+                };
 
-        if let Some(_) = deleted_rows {
-            // * Balance tree if intenral node falls beneath children threshold *
-            if let Some(child) = node.cached_tree_node_child(i) {
-                if child.keys_len() < NODE_MIN_KEYS {
-                    BTree::delete_balance(&mut node, i, pager, table);
+                // * The key before the deleted one takes its place, unless there was nothing else left of it *
+                node.remove_key(i);
+                if let Some((previous_key, remote_key_size)) = previous_key {
+                    BTree::write_child(node, &child, i, pager);
+                    node.splice_key(previous_key, remote_key_size, i);
+                } else {
+                    BTree::remove_child(node, i, pager);
                 }
-            }
-        }
-
-        // * Add modified node to file and replace pointer * 
-        if let Some(child) = node.cached_tree_node_child(i) {
-            let offset = pager.add_to_write_buffer(child.data(), node.child_offset_child_size(i));
-            node.replace_node_child(offset, child.size(), false, i);
-        }
 
-        (node, deleted_rows)
-    }
+                Deletion::Deleted(rows, false)
+            },
+            (NodeType::Internal, false) => {
+                let i = i.min(node.children_len().saturating_sub(1));
+                let Some(mut child) = BTree::read_child(node, i, pager) else {
+                    return Deletion::NotFound;
+                };
 
-    fn delete_leaf(mut node: TreeNode, key: Vec<u8>, i: usize, pager: &mut Pager, table: &mut Table) -> (TreeNode, Option<Vec<Row>>) {
-        let deleted_rows = if !node.child_is_overflowing(i) {
-            if let Some(row) = node.child_offset_child_size(i) {
-                let (row_offset, row_size) = row;
-                if key.cmp(&BTree::key_value_from_table(row, node.node_indexed_column(), pager, table)).is_eq() {
-                    if let Some(row) = table.delete_row(row_offset) {
-                        pager.mark_free(row_size, row_offset);
-                        Some(vec![row])
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            } else {
-                None
-            }
-        } else {
-            if let Some(overflow_children) = node.overflow_children(i) {
-                let deleted_rows: Vec<Row> = overflow_children
-                    .items()
-                    .iter()
-                    .filter_map(|row| {
-                        if key.cmp(&BTree::key_value_from_table(*row, node.node_indexed_column(), pager, table)).is_eq() {
-                            let (row_offset, row_size) = row;
-                            if let Some(row) = table.delete_row(*row_offset) {
-                                pager.mark_free(*row_size, *row_offset);
-                                Some(row)
-                            } else {
-                                None
-                            }
-                        } else {
-                            None
-                        }
-                    })
-                    .collect();
-                
-                if let Some((overflow_child_offset, overflow_child_size, _)) = node.child(i) {
-                    node.remove_node_overflow_child(i);
-                    pager.mark_free(overflow_child_size, overflow_child_offset);
+                let Deletion::Deleted(rows, emptied) = BTree::delete_from_node(&mut child, key, pager, table) else {
+                    return Deletion::NotFound;
+                };
+                if !emptied {
+                    BTree::write_child(node, &child, i, pager);
+                    return Deletion::Deleted(rows, false);
                 }
 
-                Some(deleted_rows)
-            } else {
-                None
-            }
-        };
+                BTree::remove_child(node, i, pager);
+                if i < node.keys_len() {
+                    node.remove_key(i);
+                } else if i > 0 {
+                    // * Only the last child on the tree's right edge can be emptied from its own keys, and with it gone
+                    // there's no node above for the key before it, which moves down into the rightmost leaf *
+                    let (last_key, remote_key_size) = node.take_key(i - 1);
+                    BTree::append_to_last_leaf(node, i - 1, (last_key, remote_key_size), pager);
+                }
 
-        // * If rows deleted remove child pointer and key if it exists on node  *
-        if let Some(_) = deleted_rows {
-            node.remove_node_child(i);
-            let node_key_value = BTree::key_value_from_node(&node, i, pager, table);
-            if i < NODE_MAX_KEYS && key.cmp(&node_key_value).is_eq() {
-                node.remove_key(i);
-            }
+                Deletion::Deleted(rows, node.children_len() == 0)
+            },
         }
-
-        (node, deleted_rows)
     }
 
-    fn delete_balance(parent_node: &mut TreeNode, i: usize, pager: &mut Pager, table: &mut Table) -> Option<()> {
-        let (mut left_child, mut right_child) = BTree::node_take_left_right_children(parent_node, i, pager)?;
-        let (left_index, right_index) = parent_node.left_right_children_of_index(i);
-        let mut left_keys = left_child.keys_len();
-        let mut right_keys = right_child.keys_len();
+    // Takes the rows at the end of the rightmost leaf under the node, which belong to `key` in a node above. The key
+    // before them comes out of its node to take its place, or there's none when the node is left without children.
+    fn delete_last(node: &mut TreeNode, key: &Vec<u8>, pager: &mut Pager, table: &mut Table) -> Option<LastRows> {
+        let last = node.children_len().checked_sub(1)?;
 
-        if left_keys >= NODE_MIN_KEYS && right_keys >= NODE_MIN_KEYS {
-            if left_keys >= right_keys {
-                let (promote_key, remote_key_size) = left_child.key(left_keys - 1);
-                parent_node.splice_key(promote_key, remote_key_size, left_index);
-
-                let delete_key = BTree::key_value_from_node(&parent_node, left_index, pager, table);
-                match left_child.node_type() {
-                    NodeType::Internal => {
-                        let (child, _) = BTree::delete_node(left_child, delete_key, pager, table);
-                        left_child = child;
-                    },                    
-                    NodeType::Leaf => left_child.remove_key(left_keys - 1)
+        match node.node_type() {
+            NodeType::Leaf => {
+                if node.children_len() != node.keys_len() + 1 || BTree::leaf_child_key(node, last, pager, table)? != *key {
+                    return None;
                 }
-                
-                left_keys = left_child.keys_len();
-            } else {
-                let (promote_key, remote_key_size) = right_child.key(0);
-                parent_node.splice_key(promote_key, remote_key_size, right_index - 1);
-
-                let delete_key = BTree::key_value_from_node(&parent_node, left_index, pager, table);
-                match right_child.node_type() {
-                    NodeType::Internal => {
-                        let (child, _) = BTree::delete_node(right_child, delete_key, pager, table);
-                        right_child = child;
-                    },
-                    NodeType::Leaf => {
-                        left_child.remove_key(0)
-                        // let (row_offset, row_size) = right_child.take_node_child(0);
-                        // left_child.add_node_child(row_offset, row_size, false);
-                    }
+                let rows = BTree::take_leaf_rows(node, last, pager, table);
+                let previous_key = node.keys_len().checked_sub(1).map(|i| node.take_key(i));
+
+                Some((rows, previous_key))
+            },
+            NodeType::Internal => {
+                let mut child = BTree::read_child(node, last, pager)?;
+                let (rows, previous_key) = BTree::delete_last(&mut child, key, pager, table)?;
+                if previous_key.is_some() {
+                    BTree::write_child(node, &child, last, pager);
+                    return Some((rows, previous_key));
                 }
 
-                right_keys = right_child.keys_len();
-            }
-        }
+                BTree::remove_child(node, last, pager);
+                let previous_key = node.keys_len().checked_sub(1).map(|i| node.take_key(i));
 
-        if parent_node.is_root() && parent_node.keys_len() < NODE_MIN_KEYS && (
-            (left_keys < NODE_MIN_KEYS && right_keys <= NODE_MIN_KEYS) || (left_keys <= NODE_MIN_KEYS && right_keys < NODE_MIN_KEYS)
-        ) {
-            BTree::merge_left_right_child_to_parent(parent_node, left_child, right_child, pager, i);
-        } else if left_keys < NODE_MIN_KEYS && right_keys > NODE_MIN_KEYS {
-            BTree::demote_key_move_left_child_to_right(parent_node, left_child, right_child, pager, i);
-        } else if left_keys > NODE_MIN_KEYS && right_keys < NODE_MIN_KEYS {
-            BTree::demote_key_move_right_child_to_left(parent_node, left_child, right_child, pager, i);
-        } else if (left_keys >= NODE_MIN_KEYS && right_keys < NODE_MIN_KEYS) || (left_keys < NODE_MIN_KEYS && right_keys >= NODE_MIN_KEYS) {
-            BTree::demote_key_merge_left_to_right(parent_node, left_child, right_child, pager, i);
-        } else {
-            parent_node.cache_tree_node_child(left_child, left_index);
-            parent_node.cache_tree_node_child(right_child, right_index);
+                Some((rows, previous_key))
+            },
         }
-        
-        None
     }
 
-    fn merge_left_right_child_to_parent(parent_node: &mut TreeNode, mut left_child: TreeNode, mut right_child: TreeNode, pager: &mut Pager, i: usize) {
-        let (left_index, right_index) = parent_node.left_right_children_of_index(i);
+    fn append_to_last_leaf(node: &mut TreeNode, i: usize, key: (u32, usize), pager: &mut Pager) {
+        let Some(mut child) = BTree::read_child(node, i, pager) else {
+            return;
+        };
 
-        if let Some(left_offset) = parent_node.child_offset(left_index) {
-            pager.mark_free(left_child.data().len(), left_offset)
+        match child.node_type() {
+            NodeType::Internal => {
+                let last = child.children_len().saturating_sub(1);
+                BTree::append_to_last_leaf(&mut child, last, key, pager);
+            },
+            NodeType::Leaf => child.append_key(key.0, key.1),
         }
-        if let Some(right_offset) = parent_node.child_offset(right_index) {
-            pager.mark_free(right_child.data().len(), right_offset);
-        }  
-        
-        parent_node.clear_children();
-        parent_node.prepend_merge(&mut left_child);
-        parent_node.append_merge(&mut right_child);
 
-        match left_child.node_type() {
-            NodeType::Leaf if parent_node.is_root() => parent_node.set_node_type(NodeType::Leaf),
-            NodeType::Internal => (),
-            NodeType::Leaf => ()
-        }
+        BTree::write_child(node, &child, i, pager);
     }
 
-    fn demote_key_move_left_child_to_right(parent_node: &mut TreeNode, mut left_child: TreeNode, mut right_child: TreeNode, pager: &mut Pager, i: usize) -> Option<()> {
-        let (left_index, right_index) = parent_node.left_right_children_of_index(i);
+    // Takes child `i` out of a leaf and returns its rows. Every tree points at each row, but only the primary tree
+    // frees it.
+    fn take_leaf_rows(node: &mut TreeNode, i: usize, pager: &mut Pager, table: &mut Table) -> Vec<Row> {
+        let Some((child_offset, child_size, is_overflowing)) = node.child(i) else {
+            return vec![];
+        };
+        let items = if is_overflowing {
+            let items = pager.read_from_file(child_offset as usize, child_size)
+                .map(|bytes| NodeOverflow::from_bytes(bytes).items())
+                .unwrap_or_default();
+            pager.mark_free(child_size, child_offset);
 
-        let (demote_key, remote_key_size) = parent_node.take_key(i); //left_index
-        left_child.append_key(demote_key, remote_key_size);
+            items
+        } else {
+            vec![(child_offset, child_size)]
+        };
+        node.remove_node_child(i);
+
+        items.into_iter()
+            .filter_map(|(row_offset, row_size)| {
+                let row = table.delete_row(row_offset)
+                    .or_else(|| pager.read_from_file(row_offset as usize, row_size).map(Row::from_bytes))?;
+                if let NodeIndexType::Primary = node.node_index_type() {
+                    pager.mark_free(row_size, row_offset);
+                }
 
-        let (promote_key, remote_key_size) = right_child.take_key(0);
-        parent_node.splice_key(promote_key, remote_key_size, left_index); // right index
+                Some(row)
+            })
+            .collect()
+    }
 
-        if right_child.has_children() {
-            let move_child_index = 0;
+    // The key of a leaf's child: its row's, or the first row's of an overflow list
+    fn leaf_child_key(node: &TreeNode, i: usize, pager: &mut Pager, table: &mut Table) -> Option<Vec<u8>> {
+        let (child_offset, child_size, is_overflowing) = node.child(i)?;
+        let row = if is_overflowing {
+            NodeOverflow::from_bytes(pager.read_from_file(child_offset as usize, child_size)?).items().first().copied()?
+        } else {
+            (child_offset, child_size)
+        };
 
-            match right_child.node_type() {
-                NodeType::Internal => {
-                    let move_child = BTree::node_take_child(&mut right_child, move_child_index, pager)?;
-                    let move_child_offset = right_child.child_offset(move_child_index)?;
-            
-                    left_child.add_tree_node_child(move_child, move_child_offset, false);
-                    right_child.remove_tree_node_child(move_child_index);
-                },
-                NodeType::Leaf => {
-                    let (move_child_offset, move_child_size) = right_child.take_node_child(move_child_index);
-                    left_child.add_node_child(move_child_offset, move_child_size, false);
+        Some(BTree::key_value_from_table(row, node.node_indexed_column(), pager, table))
+    }
 
-                    if let Some(overflow_child) = right_child.take_cached_node_overflow_child(i) {
-                        // left_child.cache_node_overflow_child(overflow_child, left_child.overflow_children_len());
-                        left_child.cache_node_overflow_child(overflow_child, left_child.keys_len() - 1);
-                    }
-                }
-            }
-        }
+    // Takes the row at `row_offset` out of the rows under `key`, leaving any other rows that share the key in place.
+    // The row itself isn't freed; that's left to the primary tree.
+    pub fn remove_row(&mut self, key: Vec<u8>, row_offset: u32, pager: &mut Pager, table: &mut Table) {
+        let Some(mut root) = self.root.take() else {
+            return;
+        };
+        root.release_cached_children();
+        let removal = BTree::remove_row_from_node(&mut root, &key, row_offset, pager, table);
+        self.root = Some(root);
 
-        if let Some((left_offset, left_size)) = parent_node.child_offset_child_size(left_index) {
-            let offset = pager.add_to_write_buffer(left_child.data(), Some((left_offset, left_size)));
-            parent_node.replace_node_child(offset, left_child.size(), false, left_index);
+        if let RowRemoval::LastOfKey = removal {
+            self.delete(key, pager, table);
         }
-        if let Some((right_offset, right_size)) = parent_node.child_offset_child_size(right_index) {
-            let offset = pager.add_to_write_buffer(right_child.data(), Some((right_offset, right_size)));
-            parent_node.replace_node_child(offset, right_child.size(), false, right_index);
-        }
-        
-        parent_node.cache_tree_node_child(left_child, left_index);
-        parent_node.cache_tree_node_child(right_child, right_index);
-        None
     }
 
-    fn demote_key_move_right_child_to_left(parent_node: &mut TreeNode, mut left_child: TreeNode, mut right_child: TreeNode, pager: &mut Pager, i: usize) -> Option<()> {
-        let (left_index, right_index) = parent_node.left_right_children_of_index(i);
-
-        let (demote_key, remote_key_size) = parent_node.take_key(i); //right_index
-        right_child.prepend_key(demote_key, remote_key_size);
+    fn remove_row_from_node(node: &mut TreeNode, key: &Vec<u8>, row_offset: u32, pager: &mut Pager, table: &mut Table) -> RowRemoval {
+        let (i, _) = BTree::key_index_from_node(node, key, pager, table);
+        let i = i.min(node.children_len().saturating_sub(1));
 
-        let (promote_key, remote_key_size) = left_child.take_key(left_child.keys_len() - 1);
-        parent_node.splice_key(promote_key, remote_key_size, right_index); //left_index
+        let removal = match node.node_type() {
+            NodeType::Internal => {
+                let Some(mut child) = BTree::read_child(node, i, pager) else {
+                    return RowRemoval::NotFound;
+                };
+                let removal = BTree::remove_row_from_node(&mut child, key, row_offset, pager, table);
+                if let RowRemoval::Removed(_) = removal {
+                    BTree::write_child(node, &child, i, pager);
+                }
 
-        if left_child.has_children() {
-            let move_child_index = left_child.children_len() - 1;
+                removal
+            },
+            NodeType::Leaf => {
+                let Some((child_offset, child_size, is_overflowing)) = node.child(i) else {
+                    return RowRemoval::NotFound;
+                };
+                if !is_overflowing {
+                    return if child_offset == row_offset { RowRemoval::LastOfKey } else { RowRemoval::NotFound };
+                }
 
-            match left_child.node_type() {
-                NodeType::Internal => {
-                    let move_child = BTree::node_take_child(&mut left_child, move_child_index, pager)?;
-                    let move_child_offset = left_child.child_offset(move_child_index)?;
-        
-                    right_child.splice_tree_node_child(move_child, move_child_offset, false, 0);
-                    left_child.remove_tree_node_child(move_child_index);
-                },
-                NodeType::Leaf => {
-                    let (move_child_offset, move_child_size) = left_child.take_node_child(move_child_index);
-                    right_child.add_node_child(move_child_offset, move_child_size, false);
+                let Some(bytes) = pager.read_from_file(child_offset as usize, child_size) else {
+                    return RowRemoval::NotFound;
+                };
+                let items = NodeOverflow::from_bytes(bytes).items();
+                let remaining: Vec<(u32, usize)> = items.iter().copied().filter(|(offset, _)| *offset != row_offset).collect();
+                if remaining.len() == items.len() {
+                    return RowRemoval::NotFound;
+                }
+                let Some((first_offset, first_size)) = remaining.first().copied() else {
+                    return RowRemoval::LastOfKey;
+                };
 
-                    if let Some(overflow_child) = left_child.take_cached_node_overflow_child(i) {
-                        right_child.cache_node_overflow_child(overflow_child, right_child.overflow_children_len());
-                    }
+                let mut overflow_child = NodeOverflow::new(first_offset, first_size);
+                for (offset, size) in remaining.iter().skip(1) {
+                    overflow_child.add_item(*offset, *size);
                 }
-            }
+                let offset = pager.add_to_write_buffer(overflow_child.data(), Some((child_offset, child_size)));
+                node.replace_node_child(offset, overflow_child.data().len(), true, i);
 
-            let move_child = BTree::node_take_child(&mut left_child, move_child_index, pager)?;
-            let move_child_offset = left_child.child_offset(move_child_index)?;
+                RowRemoval::Removed((first_offset, first_size))
+            },
+        };
 
-            right_child.splice_tree_node_child(move_child, move_child_offset, false, 0);
-            left_child.remove_tree_node_child(move_child_index);
-        }
-        
-        if let Some((left_offset, left_size)) = parent_node.child_offset_child_size(left_index) {
-            let offset = pager.add_to_write_buffer(left_child.data(), Some((left_offset, left_size)));
-            parent_node.replace_node_child(offset, left_child.size(), false, left_index);
-        }
-        if let Some((right_offset, right_size)) = parent_node.child_offset_child_size(right_index) {
-            let offset = pager.add_to_write_buffer(right_child.data(), Some((right_offset, right_size)));
-            parent_node.replace_node_child(offset, right_child.size(), false, right_index);
+        // * Keys are row pointers too, so any that pointed at the removed row now point at one still under the key *
+        if let RowRemoval::Removed((replacement_offset, replacement_size)) = removal {
+            for j in 0..node.keys_len() {
+                if node.key(j).0 == row_offset {
+                    node.remove_key(j);
+                    node.splice_key(replacement_offset, replacement_size, j);
+                }
+            }
         }
 
-        parent_node.cache_tree_node_child(left_child, left_index);
-        parent_node.cache_tree_node_child(right_child, right_index);
-
-        None
+        removal
     }
 
-    fn demote_key_merge_left_to_right(parent_node: &mut TreeNode, mut left_child: TreeNode, mut right_child: TreeNode, pager: &mut Pager, i: usize) {
-        let (left_index, right_index) = parent_node.left_right_children_of_index(i);
+    fn read_child(node: &TreeNode, i: usize, pager: &mut Pager) -> Option<TreeNode> {
+        let (child_offset, child_size, _) = node.child(i)?;
+
+        pager.read_from_file(child_offset as usize, child_size).map(TreeNode::from_bytes)
+    }
 
-        let (demote_key, remote_key_size) = parent_node.take_key(left_index);
-        left_child.append_key(demote_key, remote_key_size);
-        left_child.append_merge(&mut right_child);
+    // Writes out a child that changed and points the node at the new copy
+    fn write_child(node: &mut TreeNode, child: &TreeNode, i: usize, pager: &mut Pager) {
+        let offset = pager.add_to_write_buffer(child.data(), node.child_offset_child_size(i));
+        node.replace_node_child(offset, child.size(), false, i);
+    }
 
-        if let Some((left_offset, left_size)) = parent_node.child_offset_child_size(left_index) {
-            let offset = pager.add_to_write_buffer(left_child.data(), Some((left_offset, left_size)));
-            parent_node.replace_node_child(offset, left_child.size(), false, left_index);
+    // Drops a child node that has nothing left under it
+    fn remove_child(node: &mut TreeNode, i: usize, pager: &mut Pager) {
+        if let Some((child_offset, child_size, _)) = node.child(i) {
+            pager.mark_free(child_size, child_offset);
         }
-        if let Some((right_offset, right_size)) = parent_node.child_offset_child_size(right_index) {
-            pager.mark_free(right_size, right_offset);
-            parent_node.remove_tree_node_child(right_index);
-        }
-
-        parent_node.cache_tree_node_child(left_child, left_index);
+        node.remove_node_child(i);
     }
 
     fn key_index_from_node(node: &TreeNode, key_value: &Vec<u8>, pager: &mut Pager, table: &mut Table) -> (usize, bool) {
@@ -785,63 +678,191 @@ impl BTree {
         vec![]
     }
 
-    fn node_take_child(parent_node: &mut TreeNode, i: usize, pager: &mut Pager) -> Option<TreeNode> {
-        if let Some(child) = parent_node.take_cached_tree_node_child(i) {
-            Some(child)
-        } else {
-            let (child_offset, child_size, _) = parent_node.child(i).unwrap();
-            if let Some(bytes) = pager.read_from_file(child_offset as usize, child_size) {
-                Some(TreeNode::from_bytes(bytes))
-            } else {
-                None
-            }
+    pub fn release_cache(&mut self) {
+        if let Some(root) = &mut self.root {
+            root.release_cached_children();
         }
     }
 
-    fn node_take_left_right_children(parent_node: &mut TreeNode, i: usize, pager: &mut Pager) -> Option<(TreeNode, TreeNode)> {
-        let (left_index, right_index) = parent_node.left_right_children_of_index(i);
+    pub fn root(&self) -> Option<&TreeNode> {
+        if let Some(root) = &self.root {
+            return Some(root)
+        }
+        None
+    }
 
-        let (left_child, right_child) = if let Some((left_child, right_child)) = parent_node.take_cached_left_right_tree_node_child(left_index) {
-            let left = if let Some(left) = left_child {
-                left
-            } else {
-                let (child_offset, child_size, _) = parent_node.child(left_index)?;
-                let bytes = pager.read_from_file(child_offset as usize, child_size)?;
+    pub fn indexed_column(&self) -> Option<usize> {
+        self.indexed_column
+    }
 
-                TreeNode::from_bytes(bytes)
-            };
+    // Rows whose key falls within the bounds, where keys compare as bytes the way the tree orders them. That's value
+    // order for text and bools but not for integers, whose little-endian bytes sort differently.
+    pub(crate) fn range_search(source: &mut dyn BlockSource, offset: u32, size: usize, lower: Bound<&[u8]>, upper: Bound<&[u8]>, rows: &mut Vec<(u32, usize)>) {
+        let node = if let Some(bytes) = source.read_block(offset, size) {
+            Node::from_bytes(&bytes)
+        } else {
+            return;
+        };
+        let num_children = node.num_children();
+        if num_children == 0 {
+            return;
+        }
 
-            let right = if let Some(right) = right_child {
-                right
-            } else {
-                let (child_offset, child_size, _) = parent_node.child(right_index)?;
-                let bytes = pager.read_from_file(child_offset as usize, child_size)?;
+        match node.node_type() {
+            NodeType::Internal => {
+                // One child past the upper bound's is visited too, in case the bound equals a key; the leaves check
+                // every row against the bounds anyway
+                let first = match lower {
+                    Bound::Included(key) | Bound::Excluded(key) => BTree::key_index(source, &node, key),
+                    Bound::Unbounded => 0,
+                };
+                let last = match upper {
+                    Bound::Included(key) | Bound::Excluded(key) => BTree::key_index(source, &node, key) + 1,
+                    Bound::Unbounded => num_children - 1,
+                };
 
-                TreeNode::from_bytes(bytes)
-            };
+                for i in first.min(num_children - 1)..=last.min(num_children - 1) {
+                    BTree::range_search(source, node.child_offset_as_u32(i), node.child_size_as_usize(i), lower, upper, rows);
+                }
+            },
+            NodeType::Leaf => {
+                for i in 0..num_children {
+                    let (child_offset, child_size) = (node.child_offset_as_u32(i), node.child_size_as_usize(i));
+                    // Rows in an overflow list share a key, so the first one stands for all of them
+                    let items = if node.child_is_overflowing(i) {
+                        source.read_block(child_offset, child_size)
+                            .map(|bytes| NodeOverflow::from_bytes(bytes).items())
+                            .unwrap_or_default()
+                    } else {
+                        vec![(child_offset, child_size)]
+                    };
 
-            (left, right)
+                    if let Some(first) = items.first() {
+                        let key = BTree::row_key(source, &node, *first);
+                        if (lower, upper).contains(key.as_slice()) {
+                            rows.extend(items);
+                        }
+                    }
+                }
+            },
+        }
+    }
+
+    // The first key in the node that's at least `key`, the way `key_index_from_node` finds it
+    fn key_index(source: &mut dyn BlockSource, node: &Node, key: &[u8]) -> usize {
+        (0..node.num_keys())
+            .find(|i| key <= BTree::node_key(source, node, *i).as_slice())
+            .unwrap_or(node.num_keys())
+    }
+
+    fn node_key(source: &mut dyn BlockSource, node: &Node, i: usize) -> Vec<u8> {
+        match node.node_index_type() {
+            NodeIndexType::Primary => node.key_value_as_u32(i).to_le_bytes().to_vec(),
+            NodeIndexType::Secondary => BTree::row_key(source, node, (node.key_value_as_u32(i), node.key_remote_item_size(i))),
+        }
+    }
+
+    // The row's key in the node's tree: its primary key, or the indexed column's value
+    fn row_key(source: &mut dyn BlockSource, node: &Node, row: (u32, usize)) -> Vec<u8> {
+        let (row_offset, row_size) = row;
+        let Some(bytes) = source.read_block(row_offset, row_size) else { return vec![] };
+        let row = Row::from_bytes(bytes);
+
+        match node.node_index_type() {
+            NodeIndexType::Primary => row.primary_key_bytes().to_vec(),
+            NodeIndexType::Secondary => row.cell_data(node.node_indexed_column()).to_vec(),
+        }
+    }
+
+    // Adds the node at `offset` and everything under it to `statistics`, reading nodes and overflow lists but no
+    // rows. A leaf's children are its distinct keys, each a row or an overflow list of rows that share a key.
+    pub(crate) fn statistics(source: &mut dyn BlockSource, offset: u32, size: usize, depth: usize, statistics: &mut IndexStatistics) {
+        let node = if let Some(bytes) = source.read_block(offset, size) {
+            Node::from_bytes(&bytes)
         } else {
-            let (left_offset, left_size, _) = parent_node.child(left_index)?;
-            let (right_offset, right_size, _) = parent_node.child(right_index)?;
+            return;
+        };
 
-            let left_bytes = pager.read_from_file(left_offset as usize, left_size)?;
-            let right_bytes = pager.read_from_file(right_offset as usize, right_size)?;
+        statistics.nodes += 1;
+        statistics.keys += node.num_keys();
+        statistics.height = max(statistics.height, depth + 1);
 
-            (TreeNode::from_bytes(left_bytes), TreeNode::from_bytes(right_bytes))
+        for i in 0..node.num_children() {
+            let child_offset = node.child_offset_as_u32(i);
+            let child_size = node.child_size_as_usize(i);
+
+            let rows = match (node.node_type(), node.child_is_overflowing(i)) {
+                (NodeType::Internal, _) => {
+                    BTree::statistics(source, child_offset, child_size, depth + 1, statistics);
+                    continue;
+                },
+                (NodeType::Leaf, false) => 1,
+                (NodeType::Leaf, true) => source.read_block(child_offset, child_size).map(|bytes| NodeOverflow::from_bytes(bytes).num_items()).unwrap_or(0),
+            };
+
+            statistics.distinct_keys += 1;
+            statistics.rows += rows;
+            if rows > 0 {
+                let bucket = min(rows.ilog2() as usize, OVERFLOW_BUCKETS - 1);
+                statistics.overflow_lengths.resize(max(statistics.overflow_lengths.len(), OVERFLOW_BUCKETS), 0);
+                statistics.overflow_lengths[bucket] += 1;
+            }
+        }
+    }
+
+    // Every key of every node under the node at `offset`, as its value and remote item size. A key moves up into its
+    // parent when a node splits rather than being copied, so each key is in exactly one node. Primary keys are the
+    // key itself; secondary keys point at the first row with their value.
+    pub(crate) fn keys(source: &mut dyn BlockSource, offset: u32, size: usize, keys: &mut Vec<(u32, usize)>) {
+        let node = if let Some(bytes) = source.read_block(offset, size) {
+            Node::from_bytes(&bytes)
+        } else {
+            return;
         };
 
-        Some((left_child, right_child))
+        keys.extend((0..node.num_keys()).map(|i| (node.key_value_as_u32(i), node.key_remote_item_size(i))));
+        if let NodeType::Internal = node.node_type() {
+            for i in 0..node.num_children() {
+                BTree::keys(source, node.child_offset_as_u32(i), node.child_size_as_usize(i), keys);
+            }
+        }
     }
 
-    pub fn root(&self) -> Option<&TreeNode> {
-        if let Some(root) = &self.root {
-            return Some(root)
+    // The offset and size of the first row under the node at `offset` in tree order, or the last one with `last`
+    pub(crate) fn edge_row(source: &mut dyn BlockSource, offset: u32, size: usize, last: bool) -> Option<(u32, usize)> {
+        let node = Node::from_bytes(&source.read_block(offset, size)?);
+        let i = if last { node.num_children().checked_sub(1)? } else { 0 };
+        let (child_offset, child_size) = (node.child_offset_as_u32(i), node.child_size_as_usize(i));
+
+        match (node.node_type(), node.child_is_overflowing(i)) {
+            (NodeType::Internal, _) => BTree::edge_row(source, child_offset, child_size, last),
+            (NodeType::Leaf, false) => Some((child_offset, child_size)),
+            (NodeType::Leaf, true) => NodeOverflow::from_bytes(source.read_block(child_offset, child_size)?).items().first().copied(),
         }
-        None
     }
 
-    pub fn indexed_column(&self) -> Option<usize> {
-        self.indexed_column
+    // Offsets and sizes of every row under the node at `offset`, in tree order. A leaf's children are rows, or
+    // overflow lists of rows that share a key.
+    pub(crate) fn row_pointers(source: &mut dyn BlockSource, offset: u32, size: usize, row_pointers: &mut Vec<(u32, usize)>) {
+        let node = if let Some(bytes) = source.read_block(offset, size) {
+            Node::from_bytes(&bytes)
+        } else {
+            return;
+        };
+
+        for i in 0..node.num_children() {
+            let child_offset = node.child_offset_as_u32(i);
+            let child_size = node.child_size_as_usize(i);
+
+            match (node.node_type(), node.child_is_overflowing(i)) {
+                (NodeType::Internal, _) => BTree::row_pointers(source, child_offset, child_size, row_pointers),
+                (NodeType::Leaf, false) => row_pointers.push((child_offset, child_size)),
+                (NodeType::Leaf, true) => {
+                    if let Some(bytes) = source.read_block(child_offset, child_size) {
+                        row_pointers.extend(NodeOverflow::from_bytes(bytes).items());
+                    }
+                },
+            }
+        }
     }
 }
\ No newline at end of file
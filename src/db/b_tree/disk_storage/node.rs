@@ -143,54 +143,6 @@ impl Node {
     }
 
 
-    pub fn append_merge(merge_to: &mut Node, merge_from: &mut Node) {
-        for (i, key) in merge_from.keys().chunks(NODE_KEY_SIZE).enumerate() {
-            if i >= merge_from.num_keys() { break; }
-
-            merge_to.append_key_bytes(key.to_vec());
-        }
-
-        for (i, child) in merge_from.children().chunks(NODE_CHILD_SIZE).enumerate() {
-            if i >= merge_from.num_children() { break; }
-
-            merge_to.append_child_bytes(child.to_vec());
-        }
-    }
-
-    pub fn prepend_merge(merge_to: &mut Node, merge_from: &mut Node) {
-        let mut reverse_keys = merge_from
-            .keys()
-            .chunks(NODE_KEY_SIZE)
-            .fold(vec![], |mut keys, key| {
-                keys.push(key.to_vec());
-                keys
-            })
-            .split_at(merge_from.num_keys())
-            .0
-            .to_vec();
-        reverse_keys.reverse();
-        for key in reverse_keys {
-            merge_to.prepend_key_bytes(key);
-        }
-
-        if merge_from.num_children() > 0 {
-            let mut reverse_children = merge_from
-                .children()
-                .chunks(NODE_CHILD_SIZE)
-                .fold(vec![], |mut children, child| {
-                    children.push(child.to_vec());
-                    children
-                })
-                .split_at(merge_from.num_children())
-                .0
-                .to_vec();
-            reverse_children.reverse();
-            for child in reverse_children {
-                merge_to.prepend_child_bytes(child);
-            }
-        }
-    }
-
     pub fn append_key(&mut self, key: u32, remote_key_item_size: usize) {
         let keys_offset = self.num_keys() * NODE_KEY_SIZE;
         let (start, _) = Node::keys_range();
@@ -207,42 +159,18 @@ impl Node {
         self.increment_key();
     }
 
-    fn append_key_bytes(&mut self, key: Vec<u8>) {
-        let keys_offset = self.num_keys() * NODE_KEY_SIZE;
-        let (start, _) = Node::keys_range();
-
-        let start = start + keys_offset;
-        let end = start + NODE_KEY_SIZE;
-
-        (*self).splice(start..end, key);
-        self.increment_key();
-    }
+    pub fn replace_key(&mut self, key: u32, remote_key_item_size: usize, i: usize) {
+        let key_offset = i * NODE_KEY_SIZE;
 
-    pub fn prepend_key(&mut self, key: u32, remote_key_item_size: usize) {
-        let keys_offset = self.num_keys() * NODE_KEY_SIZE;
         let (start, _) = Node::keys_range();
-        let end = start + keys_offset;
-
-        let mut keys: Vec<u8> = (remote_key_item_size as u32).to_le_bytes()
+        let start = start + key_offset;
+        let end = start + NODE_KEY_SIZE;
+        let key: Vec<u8> = (remote_key_item_size as u32).to_le_bytes()
             .into_iter()
             .chain(key.to_le_bytes())
             .collect();
-        keys.extend(&(*self)[start..end]);
-
-        (*self).splice(start..end + NODE_KEY_SIZE, keys);
-        self.increment_key();
-    }
-
-    fn prepend_key_bytes(&mut self, mut key: Vec<u8>) {
-        let keys_offset = self.num_keys() * NODE_KEY_SIZE;
 
-        let (start, _) = Node::keys_range();
-        let end = start + keys_offset;
-
-        key.extend(&(*self)[start..end]);
-
-        (*self).splice(start..end + NODE_KEY_SIZE, key);
-        self.increment_key();
+        (*self).splice(start..end, key);
     }
 
     pub fn splice_key(&mut self, key: u32, remote_key_item_size: usize, i: usize) {
@@ -280,17 +208,6 @@ impl Node {
         self.increment_children();
     }
 
-     fn append_child_bytes(&mut self, child: Vec<u8>) {
-        let children_offset = self.num_children() * NODE_CHILD_SIZE;
-        let (start, _) = Node::children_range();
-
-        let start = start + children_offset;
-        let end = start + NODE_CHILD_SIZE;
-
-        (*self).splice(start..end, child);
-        self.increment_children();
-    }
-
     pub fn prepend_child(&mut self, child_offset: u32, size: usize, is_overflowing: bool) {
         let children_offset = self.num_children() * NODE_CHILD_SIZE;
 
@@ -344,26 +261,6 @@ impl Node {
         self.increment_children();
     }
 
-    fn prepend_child_bytes(&mut self, mut child: Vec<u8>) {
-        let children_offset = self.num_children() * NODE_CHILD_SIZE;
-
-        let (start, _) = Node::children_range();
-        let end = start + children_offset;
-
-        child.extend(&(*self)[start..end]);
-
-        (*self).splice(start..end + NODE_CHILD_SIZE, child);
-        self.increment_children();
-    }
-
-    pub fn clear_children(&mut self) {
-        let (start, end) = Node::children_range();
-        let children = vec![0; NODE_CHILD_SIZE * NODE_MAX_CHILDREN];
-
-        (*self).splice(start..end, children);
-        self.set_num_children(0);
-    }
-
     pub fn shift_keys(&mut self, i: usize, num_keys: usize) -> Vec<u8> {
         let key_offset_start = i * NODE_KEY_SIZE;
         let (start, end) = Node::keys_range();
@@ -508,12 +405,6 @@ impl Node {
         bytes_to_u32(&(*self)[start..end]) as usize
     }
 
-    pub fn keys(&self) -> &[u8] {
-        let (start, end) = Node::keys_range();
-
-        &(*self)[start..end]
-    }
-
     pub fn key_value_as_u32(&self, i: usize) -> u32 {
         let (start, _) = Node::keys_range();
         let key_offset = NODE_KEY_SIZE * i;
@@ -56,6 +56,13 @@ impl NodeOverflow {
             .collect()
     }
 
+    pub fn set_item_offset(&mut self, offset: u32, i: usize) {
+        let start = NODE_OVERFLOW_META_DATA_SIZE + (NODE_OVERFLOW_ITEM_SIZE * i) + NODE_OVERFLOW_ITEM_OFFSET_OFFSET;
+        let end = start + NODE_OVERFLOW_ITEM_OFFSET_SIZE;
+
+        (*self).splice(start..end, offset.to_le_bytes());
+    }
+
     fn increment_num_items(&mut self) {
         let (start, end) = NodeOverflow::num_items_range();
 
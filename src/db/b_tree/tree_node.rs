@@ -28,6 +28,11 @@ impl TreeNode {
         }
     }
 
+    pub fn release_cached_children(&mut self) {
+        self.cached_children = None;
+        self.cached_overflow_children = None;
+    }
+
     pub fn cache_tree_node_child(&mut self, tree_child: TreeNode, i: usize) {
         if let Some(children) = &mut self.cached_children {
             if children.len() < NODE_MAX_CHILDREN {
@@ -71,25 +76,6 @@ impl TreeNode {
         None
     }
 
-    pub fn take_cached_left_right_tree_node_child(&mut self, i: usize) -> Option<(Option<TreeNode>, Option<TreeNode>)> {
-        if let Some(children) = &mut self.cached_children {
-            let left = if let Some(left) = children.remove(i) {
-                Some(*left)
-            } else {
-                None
-            };
-
-            let right = if let Some(right) = children.remove(i) {
-                Some(*right)
-            } else {
-                None
-            };
-
-            return Some((left, right));
-        }
-        None
-    }
-
     pub fn add_tree_node_child(&mut self, tree_child: TreeNode, offset: u32, is_overflowing: bool) {
         self.disk_node.append_child(offset, tree_child.data().len(), is_overflowing);
 
@@ -119,23 +105,6 @@ impl TreeNode {
         }
     }
 
-    pub fn remove_tree_node_child(&mut self, remove_at: usize) {
-        self.disk_node.shift_children(remove_at, 1);
-
-        let disk_children_len = self.children_len();
-        let cached_children_len = if let Some(cached_children) = &self.cached_children {
-            cached_children.len()
-        } else {
-            0
-        };
-
-        if disk_children_len < cached_children_len {
-            if let Some(children) = &mut self.cached_children {
-                children.remove(remove_at);
-            }
-        }
-    }
-
     pub fn cache_node_overflow_child(&mut self, overflow_child: NodeOverflow, i: usize) {
         if let Some(overflow_children) = &mut self.cached_overflow_children {
             if overflow_children.len() < NODE_MAX_CHILDREN {
@@ -172,63 +141,6 @@ impl TreeNode {
         None
     }
 
-    pub fn remove_node_overflow_child(&mut self, i: usize) {
-        if let Some(overflow_children) = &mut self.cached_overflow_children {
-            overflow_children.remove(i);
-            overflow_children.insert(i, None);
-        }
-    }
-
-    pub fn prepend_merge(&mut self, merge_with_node: &mut TreeNode) {
-        Node::prepend_merge(&mut self.disk_node, &mut merge_with_node.disk_node);
-
-        if let Some(merge_with_children) = &mut merge_with_node.cached_children {
-            for (i, child) in merge_with_children.drain(..).enumerate() {
-                if let Some(child) = child {
-                    self.cache_tree_node_child(*child, i);
-                }
-            }
-        }
-
-        if let Some(overflow_children) = &mut merge_with_node.cached_overflow_children {
-            for (i, overflow_child) in overflow_children.drain(..).enumerate() {
-                if let Some(overflow_child) = overflow_child {
-                    self.cache_node_overflow_child(overflow_child, i);
-                }
-            }
-        }
-    }
-
-    pub fn append_merge(&mut self, merge_with_node: &mut TreeNode) {
-        Node::append_merge(&mut self.disk_node, &mut merge_with_node.disk_node);
-
-        let merge_to_len = if let Some(cached_children) = &self.cached_children {
-            cached_children.len()
-        } else {
-            0
-        };
-
-        if let Some(merge_with_children) = &mut merge_with_node.cached_children {
-            let mut j = merge_to_len;
-            for child in merge_with_children.drain(..) {
-                if let Some(child) = child {
-                    self.cache_tree_node_child(*child, j);
-                    j += 1;
-                }
-            }
-        }
-
-        if let Some(overflow_children) = &mut merge_with_node.cached_overflow_children {
-            let mut j = merge_to_len;
-            for overflow_child in overflow_children.drain(..) {
-                if let Some(overflow_child) = overflow_child {
-                    self.cache_node_overflow_child(overflow_child, j);
-                    j += 1;
-                }
-            }
-        }
-    }
-
     pub fn take_node_and_split(mut self, split_keys_at: usize, split_children_at: usize) -> (TreeNode, TreeNode) {
         let (left_data, right_data) = Node::from_split_new(self.disk_node, split_keys_at, split_children_at);
 
@@ -371,10 +283,6 @@ impl TreeNode {
         self.disk_node.append_key(key, remote_key_size);
     }
 
-    pub fn prepend_key(&mut self, key: u32, remote_key_size: usize) {
-        self.disk_node.prepend_key(key, remote_key_size);
-    }
-
     pub fn splice_key(&mut self, key: u32, remote_key_size: usize, i: usize) {
         self.disk_node.splice_key(key, remote_key_size, i);
     }
@@ -422,10 +330,6 @@ impl TreeNode {
         self.disk_node.take_child(i)
     }
 
-    pub fn clear_children(&mut self) {
-        self.disk_node.clear_children();
-    }
-
     pub fn child(&self, i: usize) -> Option<(u32, usize, bool)> {
         let child_offset = self.disk_node.child_offset_as_u32(i);
         let child_size = self.disk_node.child_size_as_usize(i);
@@ -449,16 +353,6 @@ impl TreeNode {
         }
     }
 
-    pub fn child_offset(&self, i: usize) -> Option<u32> {
-        let child_offset = self.disk_node.child_offset_as_u32(i);
-
-        if child_offset > 0 {
-            Some(child_offset)
-        } else {
-            None
-        }
-    }
-
     pub fn child_size(&self, i: usize) -> Option<usize> {
         let child_size = self.disk_node.child_size_as_usize(i);
 
@@ -473,10 +367,6 @@ impl TreeNode {
     //     self.disk_node
     // }
 
-    pub fn child_is_overflowing(&self, i: usize) -> bool {
-        self.disk_node.child_is_overflowing(i)
-    }
-
     // pub fn children_len(&self) -> usize {
     //     // TODO: why am I checking the length of the cached children?
     //     if let Some(children) = &self.cached_children {
@@ -545,31 +435,10 @@ impl TreeNode {
         0
     }
 
-    pub fn overflow_children_len(&self) -> usize {
-        if let Some(overflow_children) = &self.cached_overflow_children {
-            if let Some(i) = overflow_children.into_iter().position(|c| c.is_none()) {
-                return i; 
-            }
-        }
-        0
-    }
-
-    pub fn left_right_children_of_index(&self, i: usize) -> (usize, usize) {
-        if i < self.disk_node.num_children() - 1  {
-            (i, i + 1)
-        } else {
-            (i - 1, i)
-        }
-    }
-
     pub fn set_node_type(&mut self, node_type: NodeType) {
         self.disk_node.set_node_type(node_type);
     }
 
-    pub fn has_children(&self) -> bool {
-        self.disk_node.num_children() > 0
-    }
-
     pub fn is_root(&self) -> bool {
         self.disk_node.is_root()
     }
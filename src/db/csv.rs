@@ -0,0 +1,254 @@
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+
+use super::{db::PocketDB, import::{ImportReport, RowError}, options::CsvOptions, shared::enums::{ColumnType, DataType}, table::disk_storage::row::Row};
+
+// Maps CSV headers to the table's columns by name. Every column, the key included, needs a header and every header
+// needs a column; a mismatch fails the whole import, while a bad row is skipped and reported. The header is line 1.
+#[derive(Debug)]
+pub struct Csv;
+
+impl Csv {
+    pub fn import(db: &PocketDB, reader: impl Read, options: &CsvOptions) -> Result<ImportReport, String> {
+        let columns = db.columns();
+        let mut records = CsvRecords::new(BufReader::new(reader), options.delimiter());
+
+        let headers = match records.next() {
+            Some(Ok((_, headers))) => headers,
+            Some(Err(e)) => return Err(e.to_string()),
+            None => return Err(String::from("CSV has no header row")),
+        };
+        if let Some(header) = headers.iter().find(|header| !columns.iter().any(|(name, _)| name == header.trim())) {
+            return Err(format!("No column named {}", header.trim()));
+        }
+        let positions = columns
+            .iter()
+            .map(|(name, _)| headers.iter().position(|header| header.trim() == name).ok_or(format!("CSV has no {} column", name)))
+            .collect::<Result<Vec<usize>, String>>()?;
+
+        let mut import = ImportReport::default();
+        let mut batch = vec![];
+        for record in records {
+            let row = record.and_then(|(line, fields)| {
+                if fields.len() != headers.len() {
+                    let message = format!("Expected {} fields but found {}", headers.len(), fields.len());
+                    return Err(RowError { line, message });
+                }
+
+                Csv::row(&fields, &columns, &positions)
+                    .map(|(key, row)| (line, key, row))
+                    .map_err(|message| RowError { line, message })
+            });
+
+            match row {
+                Ok(row) => batch.push(row),
+                Err(e) => import.errors.push(e),
+            }
+
+            if batch.len() >= options.batch_size() {
+                import.insert_batch(db, &mut batch);
+            }
+        }
+        import.insert_batch(db, &mut batch);
+
+        Ok(import)
+    }
+
+    // Writes a header row and then every row in primary-key order, all from one snapshot. Returns the number of rows.
+    pub fn export(db: &PocketDB, writer: impl Write, options: &CsvOptions) -> Result<usize, String> {
+        let snapshot = db.snapshot();
+        let columns = snapshot.columns();
+        let mut writer = BufWriter::new(writer);
+
+        let headers: Vec<String> = columns.iter().map(|(name, _)| name.clone()).collect();
+        Csv::write_record(&mut writer, &headers, options.delimiter()).map_err(|e| format!("Failed to write CSV: {}", e))?;
+
+        let mut exported = 0;
+        for row in snapshot.rows() {
+            // Cell 0 is the row head, so the key is cell 1 like its column
+            let fields: Vec<String> = row
+                .iter()
+                .skip(1)
+                .take(columns.len())
+                .map(|data| match data {
+                    DataType::Integer(value) => value.to_string(),
+                    DataType::Text(value) => value.clone(),
+                    DataType::Bool(value) => value.to_string(),
+                    DataType::Meta => String::new(),
+                })
+                .collect();
+
+            Csv::write_record(&mut writer, &fields, options.delimiter()).map_err(|e| format!("Failed to write CSV: {}", e))?;
+            exported += 1;
+        }
+        writer.flush().map_err(|e| format!("Failed to write CSV: {}", e))?;
+
+        Ok(exported)
+    }
+
+    fn row(fields: &[String], columns: &[(String, ColumnType)], positions: &[usize]) -> Result<(u32, Row), String> {
+        let mut cells = vec![];
+        for ((name, column_type), position) in columns.iter().zip(positions) {
+            cells.push(column_type.parse(&fields[*position]).map_err(|e| format!("{} for column {}", e, name))?);
+        }
+
+        let key = match cells.remove(0) {
+            DataType::Integer(key) => key,
+            _ => return Err(String::from("Key column isn't an integer")),
+        };
+
+        Ok((key, PocketDB::row(key, cells.into_iter().map(PocketDB::cell).collect())))
+    }
+
+    // Fields holding the delimiter, a quote or a line break are quoted, with quotes doubled
+    fn write_record(writer: &mut impl Write, fields: &[String], delimiter: u8) -> Result<(), std::io::Error> {
+        let delimiter = delimiter as char;
+        let record: Vec<String> = fields
+            .iter()
+            .map(|field| {
+                if field.contains([delimiter, '"', '\n', '\r']) {
+                    format!("\"{}\"", field.replace('"', "\"\""))
+                } else {
+                    field.clone()
+                }
+            })
+            .collect();
+
+        writeln!(writer, "{}", record.join(&delimiter.to_string()))
+    }
+}
+
+// Splits CSV into records of fields. Quoted fields can hold delimiters, doubled quotes and line breaks, so a record
+// can span lines; each comes with the line it started on.
+struct CsvRecords<R: BufRead> {
+    reader: R,
+    delimiter: char,
+    line: usize,
+    done: bool,
+}
+
+impl<R: BufRead> CsvRecords<R> {
+    fn new(reader: R, delimiter: u8) -> CsvRecords<R> {
+        CsvRecords { reader, delimiter: delimiter as char, line: 0, done: false }
+    }
+
+    // Reads a line without its line break. None at the end of the input.
+    fn read_line(&mut self) -> Result<Option<String>, RowError> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Ok(None),
+            Ok(_) => {
+                self.line += 1;
+                let len = line.trim_end_matches(['\n', '\r']).len();
+                line.truncate(len);
+
+                Ok(Some(line))
+            },
+            Err(e) => Err(RowError { line: self.line + 1, message: format!("Failed to read CSV: {}", e) }),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for CsvRecords<R> {
+    type Item = Result<(usize, Vec<String>), RowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        // Blank lines between records are skipped
+        let mut line = loop {
+            match self.read_line() {
+                Ok(Some(line)) if line.is_empty() => continue,
+                Ok(Some(line)) => break line,
+                Ok(None) => return None,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+            }
+        };
+        let start_line = self.line;
+
+        let mut fields = vec![];
+        let mut field = String::new();
+        let mut in_quotes = false;
+        loop {
+            let mut chars = line.chars().peekable();
+            while let Some(c) = chars.next() {
+                match (in_quotes, c) {
+                    (true, '"') if chars.peek() == Some(&'"') => {
+                        field.push('"');
+                        chars.next();
+                    },
+                    (true, '"') => in_quotes = false,
+                    (false, '"') if field.is_empty() => in_quotes = true,
+                    (false, c) if c == self.delimiter => fields.push(std::mem::take(&mut field)),
+                    (_, c) => field.push(c),
+                }
+            }
+
+            if !in_quotes {
+                break;
+            }
+
+            match self.read_line() {
+                Ok(Some(next_line)) => {
+                    field.push('\n');
+                    line = next_line;
+                },
+                Ok(None) => {
+                    self.done = true;
+                    return Some(Err(RowError { line: start_line, message: String::from("Unterminated quoted field") }));
+                },
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+            }
+        }
+        fields.push(field);
+
+        Some(Ok((start_line, fields)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{db::PocketDB, options::CsvOptions, shared::enums::{ColumnType, DataType}};
+
+    #[test]
+    fn import_reports_bad_rows_and_export_round_trips() {
+        let db = PocketDB::in_memory();
+        db.add_indexed_column("name", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap().add_column("active", ColumnType::Bool).unwrap();
+
+        let csv = "age,key,name,active\n\
+            30,2,\"Smith, Alex\",true\n\
+            41,1,\"Line\nbreak \"\"quoted\"\"\",0\n\
+            \n\
+            old,3,Sam,false\n\
+            25,4,Jo\n\
+            25,10,Jo,FALSE\n";
+        let mut options = CsvOptions::new();
+        options.set_batch_size(2);
+        let import = db.import_csv(csv.as_bytes(), &options).unwrap();
+
+        assert_eq!(import.imported, 3);
+        let errors: Vec<String> = import.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors, vec![
+            "line 6: Invalid integer \"old\" for column age",
+            "line 7: Expected 4 fields but found 3",
+        ]);
+        assert_eq!(db.search_by_primary_index(DataType::Integer(2)).unwrap()[0][2].as_bytes(), b"Smith, Alex");
+
+        let mut exported = vec![];
+        assert_eq!(db.export_csv(&mut exported).unwrap(), 3);
+        assert_eq!(String::from_utf8(exported).unwrap(), "key,name,age,active\n\
+            1,\"Line\nbreak \"\"quoted\"\"\",41,false\n\
+            2,\"Smith, Alex\",30,true\n\
+            10,Jo,25,false\n");
+
+        assert!(db.import_csv("key,name,age,active,extra\n".as_bytes(), &options).is_err());
+        assert!(db.import_csv("key,name\n".as_bytes(), &options).is_err());
+    }
+}
@@ -1,171 +1,641 @@
-use super::{b_tree::{btree::BTree, disk_storage::node::NodeIndexType}, file_stystem::pager::Pager, secondary_index::secondary_index::SecondaryIndex, shared::enums::{ColumnType, DataType}, table::{disk_storage::{cell::Cell, row::Row}, table::Table}};
+use std::{io::{Read, Write}, sync::{Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard}};
 
+#[cfg(feature = "serde")]
+use super::serialization;
+use super::{csv::Csv, import::ImportReport, json::Json, b_tree::{btree::BTree, disk_storage::node::NodeIndexType}, file_stystem::{buffer_pool::BufferPoolStats, pager::Pager, storage::StorageBackend}, meta::{backup::Backup, disk_storage::{free_list::FreeListStats, master::Master}, vacuum::Vacuum}, options::{CsvOptions, JsonOptions, OpenOptions}, query::{aggregate::Aggregate, builder::Query, filter::Filter, join::Join, planner::{Planner, QueryPlan}, statistics::{IndexStatistics, Statistics}}, secondary_index::secondary_index::SecondaryIndex, shared::{constants::{node::NODE_SIZE, statistics::STATISTICS_REFRESH_RATIO}, enums::{ColumnType, DataType, OpenError, RowBuilderError, SqlError}, utils::bytes_to_u32}, snapshot::Snapshot, sql::{executor::Executor, parser::Parser, result_set::ResultSet}, table::{disk_storage::{cell::Cell, row::Row}, row_builder::RowBuilder, table::Table}};
+
+// Safe to share behind an `Arc`. Writers take the indexes exclusively; lookups share them and walk their own copies
+// of the committed trees, so they never touch the writer's caches. Every page still goes through the pager, whose
+// lock is held for the length of a tree walk, so lookups overlap with each other everywhere but there.
 #[derive(Debug)]
 pub struct PocketDB {
+    indexes: RwLock<Indexes>,
+    pager: Mutex<Pager>,
+}
+
+#[derive(Debug)]
+struct Indexes {
     primary_index_tree: BTree,
     secondary_indexes: SecondaryIndex,
 	table: Table,
-	pager: Pager,
+    // The commit the index statistics were counted at
+    statistics_version: u64,
 }
 
 impl PocketDB {
 	pub fn new() -> PocketDB {
-		let mut pager = Pager::new();
+		PocketDB::open("db.db").expect("Failed to open db.db")
+	}
+
+	pub fn open(path: &str) -> Result<PocketDB, OpenError> {
+		PocketDB::open_with_options(path, &OpenOptions::new())
+	}
+
+	// Fails with `OpenError::Locked` if another handle has the file locked: a writer holds it exclusively, while
+	// read-only handles only exclude writers
+	pub fn open_with_options(path: &str, options: &OpenOptions) -> Result<PocketDB, OpenError> {
+		Ok(PocketDB::from_pager(Pager::new(path, options)?))
+	}
+
+	// Nothing touches the disk unless the database is written out with `save_to`
+	pub fn in_memory() -> PocketDB {
+		PocketDB::in_memory_with_options(&OpenOptions::new())
+	}
+
+	pub fn in_memory_with_options(options: &OpenOptions) -> PocketDB {
+		PocketDB::from_pager(Pager::in_memory(options))
+	}
+
+	// Opens whatever database is in `storage`, or creates one if it's empty
+	pub fn with_storage(storage: Box<dyn StorageBackend>, options: &OpenOptions) -> PocketDB {
+		PocketDB::from_pager(Pager::with_storage(None, storage, options))
+	}
 
-		PocketDB { 
-            primary_index_tree: PocketDB::new_primary_index_tree(&mut pager), 
-            secondary_indexes: PocketDB::new_secondary_indexes(&mut pager),
-            table: PocketDB::new_table(&mut pager),
-            pager,
+	fn from_pager(mut pager: Pager) -> PocketDB {
+		PocketDB {
+            indexes: RwLock::new(Indexes::from_pager(&mut pager)),
+            pager: Mutex::new(pager),
         }
 	}
 
-    pub fn add_column(&mut self, column_name: &str, column_type: ColumnType) -> &mut Self { 
-        self.table.add_column(column_name, column_type);
-        self.flush_table();
+    // Fails if the new columns can't be committed
+    pub fn add_column(&self, column_name: &str, column_type: ColumnType) -> Result<&Self, String> {
+        if self.is_read_only() {
+            return Err(String::from("Database is read-only"));
+        }
+
+        let mut indexes = self.indexes_mut();
+        indexes.add_column(column_name, column_type, &mut self.pager())?;
+
+        Ok(self)
+    }
 
-        self
+    pub fn add_indexed_column(&self, column_name: &str, column_type: ColumnType) -> Result<&Self, String> {
+        if self.is_read_only() {
+            return Err(String::from("Database is read-only"));
+        }
+
+        let mut indexes = self.indexes_mut();
+        let mut pager = self.pager();
+        indexes.add_column(column_name, column_type, &mut pager)?;
+
+        let indexed_column = indexes.table.num_columns() - 1;
+        indexes.secondary_indexes.add_secondary_index(indexed_column, &mut pager);
+
+        Ok(self)
     }
 
-    pub fn add_indexed_column(&mut self, column_name: &str, column_type: ColumnType) -> &mut Self {
-        self.add_column(column_name, column_type);
-        
-        let indexed_column = self.table.num_columns() - 1;
-        self.secondary_indexes.add_secondary_index(indexed_column, &mut self.pager);
+    // Indexes a column added with `add_column`. The rows already in the table are added to the new index.
+    pub fn add_index(&self, column_name: &str) -> Result<(), String> {
+        if self.is_read_only() {
+            return Err(String::from("Database is read-only"));
+        }
+
+        let added = self.indexes_mut().add_index(column_name, &mut self.pager());
+        self.refresh_statistics();
 
-        self
+        added
     }
 
-	pub fn insert(&mut self, key: u32, mut row: Row) -> Result<(), String> {
-        let num_cols = self.table.num_columns();
-        let num_cells = row.num_cells();
+	pub fn insert(&self, key: u32, row: Row) -> Result<(), String> {
+        if self.is_read_only() {
+            return Err(String::from("Database is read-only"));
+        }
 
-        if num_cols != num_cells - 1 {
-            return Err(format!("Expected row length {} but received length {}", num_cells - 1, num_cells));
+        let inserted = self.indexes_mut().insert(key, row, &mut self.pager());
+        self.refresh_statistics();
+
+        inserted
+	}
+
+    // Inserts a row from `row_builder` under the key it was built with
+    pub fn insert_row(&self, row: Row) -> Result<(), String> {
+        let key = bytes_to_u32(row.primary_key_bytes());
+
+        self.insert(key, row)
+    }
+
+    // Runs the checks `insert` would on the row without inserting it
+    pub(crate) fn check_row(&self, row: &Row) -> Result<(), String> {
+        if self.is_read_only() {
+            return Err(String::from("Database is read-only"));
         }
 
-        let type_matches = row.cells()
-            .iter()
-            .enumerate()
-            .all(|(i, cell)| {
-                if i >= num_cols { return true; }
+        self.indexes().check_row(row)
+    }
+
+    // Starts a row checked against the table's columns as they are now. See `RowBuilder`.
+    pub fn row_builder(&self) -> RowBuilder {
+        RowBuilder::new(self.columns())
+    }
+
+    // Inserts the rows under one lock. Each row still commits on its own, but the commits are synced together at the
+    // end, so a crash part-way through can lose the whole batch. Returns a result per row, and every row fails if that
+    // sync does.
+    pub fn insert_batch(&self, rows: Vec<(u32, Row)>) -> Vec<Result<(), String>> {
+        if self.is_read_only() {
+            return rows.iter().map(|_| Err(String::from("Database is read-only"))).collect();
+        }
+
+        let results = {
+            let mut indexes = self.indexes_mut();
+            let mut pager = self.pager();
+
+            pager.begin_batch();
+            let results: Vec<Result<(), String>> = rows
+                .into_iter()
+                .map(|(key, row)| indexes.insert(key, row, &mut pager))
+                .collect();
+
+            match pager.end_batch() {
+                Ok(()) => results,
+                // None of the batch's rows are durable if the sync at the end fails
+                Err(e) => results
+                    .into_iter()
+                    .map(|result| result.and(Err(format!("Failed to sync batch: {}", e))))
+                    .collect(),
+            }
+        };
+        self.refresh_statistics();
+
+        results
+    }
+
+    // Reads CSV whose header names the columns, key included, in any order. Rows that fail to parse or insert are
+    // reported with their line numbers rather than failing the import.
+    pub fn import_csv(&self, reader: impl Read, options: &CsvOptions) -> Result<ImportReport, String> {
+        Csv::import(self, reader, options)
+    }
+
+    // Writes every row as CSV in primary-key order and returns how many there were
+    pub fn export_csv(&self, writer: impl Write) -> Result<usize, String> {
+        self.export_csv_with_options(writer, &CsvOptions::new())
+    }
+
+    pub fn export_csv_with_options(&self, writer: impl Write, options: &CsvOptions) -> Result<usize, String> {
+        Csv::export(self, writer, options)
+    }
+
+    // Reads one JSON object per line, keyed by column name. A database with no columns yet gets them inferred from
+    // the first records; see `Json`.
+    pub fn import_ndjson(&self, reader: impl Read, options: &JsonOptions) -> Result<ImportReport, String> {
+        Json::import_ndjson(self, reader, options)
+    }
+
+    pub fn export_json(&self, writer: impl Write) -> Result<usize, String> {
+        Json::export_json(self, writer)
+    }
+
+    pub fn export_ndjson(&self, writer: impl Write) -> Result<usize, String> {
+        Json::export_ndjson(self, writer)
+    }
+
+    // Runs one SQL statement against the table, which SQL calls `main`. See `Parser` for what's understood and
+    // `Executor` for how rows are found.
+    pub fn execute(&self, sql: &str) -> Result<ResultSet, SqlError> {
+        let statement = Parser::parse(sql)?;
+
+        Executor::execute(self, statement).map_err(SqlError::Execute)
+    }
+
+    // The number of rows, kept as rows are inserted and deleted
+    pub fn len(&self) -> usize {
+        self.indexes().secondary_indexes.statistics_list().rows()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // The row count and, for the primary tree and each secondary index, its shape and how its rows spread over its
+    // keys. The row count is exact; the rest is as of the last time it was recomputed. See `Statistics`.
+    pub fn statistics(&self) -> Statistics {
+        self.snapshot().statistics()
+    }
+
+    // Starts a query over every row and column. See `Query`.
+    pub fn query(&self) -> Query<'_> {
+        Query::new(self)
+    }
+
+    // Starts counts, sums and the like over the table. See `Aggregate`.
+    pub fn aggregate(&self) -> Aggregate<'_> {
+        Aggregate::new(self)
+    }
+
+    // Pairs rows of two tables, each named for the records' column names, where `on.0` of the left holds the same
+    // value as `on.1` of the right. See `Join`.
+    pub fn join<'a>(left: (&str, &'a PocketDB), right: (&str, &'a PocketDB), on: (&str, &str)) -> Result<Join<'a>, String> {
+        Join::new(left, right, on)
+    }
+
+    // Chooses how to find the rows matching `filter` from the indexes and their sizes. See `Planner`.
+    pub fn plan(&self, filter: &Filter) -> Result<QueryPlan, String> {
+        let snapshot = self.snapshot();
+
+        Planner::plan(filter, &snapshot.columns(), &snapshot.statistics())
+    }
+
+    // Rows matching `filter`, found the way `plan` would, in the same form as the search methods return them
+    pub fn find(&self, filter: &Filter) -> Result<Vec<Vec<DataType>>, String> {
+        let snapshot = self.snapshot();
+        let columns = snapshot.columns();
+
+        Ok(Planner::plan(filter, &columns, &snapshot.statistics())?.rows(&snapshot, &columns))
+    }
+
+    // Names and types of the key column and every column after it
+    pub fn columns(&self) -> Vec<(String, ColumnType)> {
+        self.indexes().table.columns()
+    }
+
+    // Names of the columns with a secondary index
+    pub fn indexed_columns(&self) -> Vec<String> {
+        let indexes = self.indexes();
+
+        (0..indexes.secondary_indexes.num_secondary_index_items())
+            .filter_map(|i| indexes.secondary_indexes.secondary_index_item(i))
+            .map(|(_, indexed_column)| indexes.table.column(indexed_column).0)
+            .collect()
+    }
+
+    // Takes a consistent view of the last commit that later commits can't disturb. See `Snapshot`.
+    pub fn snapshot(&self) -> Snapshot<'_> {
+        let indexes = self.indexes();
+        let secondary_index_items = (0..indexes.secondary_indexes.num_secondary_index_items())
+            .filter_map(|i| indexes.secondary_indexes.secondary_index_item(i))
+            .collect();
+        let table_columns = indexes.table.columns_data().to_vec();
+        let statistics_list = indexes.secondary_indexes.statistics_list().clone();
+
+        let mut pager = self.pager();
+        let master = Master::from_bytes(pager.master.data());
+        let version = pager.register_snapshot();
+
+        Snapshot::new(self, version, master, secondary_index_items, table_columns, statistics_list)
+    }
+
+    pub fn search_by_primary_index(&self, key: DataType) -> Option<Vec<Vec<DataType>>> {
+        self.snapshot().search_by_primary_index(key)
+	}
+
+    // Stores any serde struct as a row, its fields matched to the columns by name. See `serialization`.
+    #[cfg(feature = "serde")]
+    pub fn insert_serde<T: serde::Serialize>(&self, key: u32, value: &T) -> Result<(), String> {
+        let columns = self.columns();
+        let row = serialization::to_row(key, value, columns.get(1..).unwrap_or_default())?;
+
+        self.insert(key, row)
+    }
+
+    // None if there's no row with the key. Fails if the row doesn't fit `T`.
+    #[cfg(feature = "serde")]
+    pub fn get_serde<T: serde::de::DeserializeOwned>(&self, key: u32) -> Result<Option<T>, String> {
+        let snapshot = self.snapshot();
+        let Some(row) = snapshot.search_by_primary_index(DataType::Integer(key)).and_then(|rows| rows.into_iter().next()) else {
+            return Ok(None);
+        };
+        let columns = snapshot.columns();
+
+        // Typed rows start with the row head, and can have padding after the last column
+        serialization::from_values(row.into_iter().skip(1).take(columns.len()).collect(), &columns).map(Some)
+    }
+
+    pub fn search_by_secondary_index(&self, key: DataType, column_name: &str) -> Option<Vec<Vec<DataType>>> {
+        self.snapshot().search_by_secondary_index(key, column_name)
+	}
+
+	pub fn delete_by_primary_index(&self, key: DataType) -> Result<(), String> {
+        if self.is_read_only() {
+            return Err(String::from("Database is read-only"));
+        }
+
+        let deleted = self.indexes_mut().delete_by_primary_index(key, &mut self.pager());
+        self.refresh_statistics();
+
+        deleted
+	}
+
+    pub fn delete_by_secondary_index(&self, key: DataType, column_name: &str) -> Result<(), String> {
+        if self.is_read_only() {
+            return Err(String::from("Database is read-only"));
+        }
+
+        let deleted = self.indexes_mut().delete_by_secondary_index(key, column_name, &mut self.pager());
+        self.refresh_statistics();
+
+        deleted
+    }
+
+    pub fn vacuum(&self) -> Result<(), String> {
+        if self.is_read_only() {
+            return Err(String::from("Database is read-only"));
+        }
+
+        let mut indexes = self.indexes_mut();
+        let mut pager = self.pager();
+        // Vacuuming moves live blocks, which open snapshots may still be reading
+        if pager.has_snapshots() {
+            return Err(String::from("Can't vacuum while snapshots are open"));
+        }
+
+        Vacuum::new(&mut pager).vacuum(&mut pager)?;
+        *indexes = Indexes::from_pager(&mut pager);
+
+        Ok(())
+    }
+
+    pub fn incremental_vacuum(&self) -> Result<usize, String> {
+        if self.is_read_only() {
+            return Err(String::from("Database is read-only"));
+        }
+
+        let mut indexes = self.indexes_mut();
+        let mut pager = self.pager();
+        // Vacuuming moves live blocks, which open snapshots may still be reading
+        if pager.has_snapshots() {
+            return Err(String::from("Can't vacuum while snapshots are open"));
+        }
+
+        let reclaimed = Vacuum::new(&mut pager).incremental_vacuum(&mut pager)?;
+        *indexes = Indexes::from_pager(&mut pager);
+
+        Ok(reclaimed)
+    }
+
+    // Writes a compacted copy of the database as of now to `path`. Other threads keep reading and writing while it
+    // runs. See `Backup`.
+    pub fn backup_to(&self, path: &str) -> Result<(), String> {
+        Backup::backup_to(&self.snapshot(), path)
+    }
+
+    // Brings the copy at `path` up to date by writing only the blocks that changed since it was last backed up to.
+    // Returns the number of bytes written.
+    pub fn incremental_backup_to(&self, path: &str) -> Result<usize, String> {
+        Backup::incremental_backup_to(&self.snapshot(), path)
+    }
+
+    // Replaces the database with a backup made by `backup_to` or `incremental_backup_to`
+    pub fn restore_from(&self, path: &str) -> Result<(), String> {
+        if self.is_read_only() {
+            return Err(String::from("Database is read-only"));
+        }
+
+        let mut indexes = self.indexes_mut();
+        let mut pager = self.pager();
+        if pager.has_snapshots() {
+            return Err(String::from("Can't restore while snapshots are open"));
+        }
+
+        Backup::restore_from(&mut pager, path)?;
+        *indexes = Indexes::from_pager(&mut pager);
+
+        Ok(())
+    }
+
+    // Writes the database to a file at `path` that `open` can read, whether it lives in memory or in another file
+    pub fn save_to(&self, path: &str) -> Result<(), String> {
+        let _indexes = self.indexes();
+        self.pager().save_to(path).map_err(|e| format!("Failed to save to {}: {}", path, e))
+    }
+
+    // Syncs every commit so far to disk, for `Durability::Normal` and `Durability::Off`
+    pub fn checkpoint(&self) -> Result<(), String> {
+        let _indexes = self.indexes();
+        self.pager().checkpoint().map_err(|e| format!("Failed to checkpoint: {}", e))
+    }
+
+    pub fn free_space_stats(&self) -> FreeListStats {
+        self.pager().free_list_stats()
+    }
+
+    pub fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.pager().buffer_pool_stats()
+    }
+
+    // Counts the trees again once they're due, from a snapshot of the last commit rather than under the write lock, so
+    // other writers and lookups carry on while the nodes are walked. See `Indexes::install_statistics`.
+    fn refresh_statistics(&self) {
+        if !self.indexes().statistics_due() {
+            return;
+        }
+
+        let snapshot = self.snapshot();
+        let (items, writes) = snapshot.count_statistics();
+        let version = snapshot.version();
+        drop(snapshot);
+
+        self.indexes_mut().install_statistics(items, version, writes);
+    }
+
+    // Locks are always taken indexes first, then the pager. A panic while holding one leaves nothing half-applied
+    // that the next commit doesn't rewrite, so a poisoned lock is taken over rather than passed on.
+    fn indexes(&self) -> RwLockReadGuard<'_, Indexes> {
+        self.indexes.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn indexes_mut(&self) -> RwLockWriteGuard<'_, Indexes> {
+        self.indexes.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn pager(&self) -> MutexGuard<'_, Pager> {
+        self.pager.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.pager().options().read_only()
+    }
+
+    pub fn row(key: u32, cells: Vec<Cell>) -> Row {
+        Row::from_cells(cells, key)
+    }
+
+    pub fn cell(data: DataType) -> Cell {
+        Cell::new_body(data)
+    }
+}
+
+impl Indexes {
+    fn from_pager(pager: &mut Pager) -> Indexes {
+        let mut indexes = Indexes {
+            primary_index_tree: Indexes::new_primary_index_tree(pager),
+            secondary_indexes: Indexes::new_secondary_indexes(pager),
+            table: Indexes::new_table(pager),
+            statistics_version: pager.version(),
+        };
+        // Files written before statistics were kept get them counted here, row count included
+        if indexes.statistics_due() {
+            let primary_root = pager.primary_root_offset_primary_root_size();
+            let items = Statistics::count_trees(pager, primary_root, &indexes.secondary_index_offsets());
+            let (rows, writes) = (items[0].rows, indexes.secondary_indexes.statistics_list().writes());
+            indexes.install_statistics(items, pager.version(), writes);
+            indexes.secondary_indexes.statistics_list_mut().set_rows(rows);
+        }
+
+        indexes
+    }
+
+    fn add_column(&mut self, column_name: &str, column_type: ColumnType, pager: &mut Pager) -> Result<(), String> {
+        self.table.add_column(column_name, column_type);
+        self.flush_table(pager)
+    }
 
-                let (_, column_type) = self.table.column(i);
-                column_type.matches(&cell.to_typed_data())
-            });
+    fn add_index(&mut self, column_name: &str, pager: &mut Pager) -> Result<(), String> {
+        // Column 1 is the key, which the primary index already covers
+        let indexed_column = (2..self.table.num_columns())
+            .find(|i| self.table.column(*i).0 == column_name)
+            .ok_or(format!("No column named {}", column_name))?;
+        if self.secondary_indexes.secondary_index_trees().iter().any(|tree| tree.indexed_column() == Some(indexed_column)) {
+            return Err(format!("Column {} is already indexed", column_name));
+        }
 
-        if !type_matches {
-            return Err(format!("Invalid row data types provided"));
+        let mut row_pointers = vec![];
+        if let Some((primary_root_offset, primary_root_size)) = pager.primary_root_offset_primary_root_size() {
+            BTree::row_pointers(pager, primary_root_offset, primary_root_size, &mut row_pointers);
         }
 
+        self.secondary_indexes.add_secondary_index(indexed_column, pager);
+        if let Some(secondary_tree) = self.secondary_indexes.secondary_index_trees_mut().last_mut() {
+            for (row_offset, row_size) in row_pointers {
+                secondary_tree.insert(row_offset, (row_offset, row_size), pager, &mut self.table);
+            }
+        }
+
+        self.flush_trees(pager)
+    }
+
+	fn insert(&mut self, key: u32, row: Row, pager: &mut Pager) -> Result<(), String> {
+        self.check_row(&row)?;
+
         let row_size = row.data().len();
-        let row_offset = self.pager.add_to_write_buffer(&row.data(), None);
+        let row_offset = pager.add_to_write_buffer(&row.data(), None);
 		self.table.insert_row(row_offset, row);
 
-		self.primary_index_tree.insert(key, (row_offset, row_size), &mut self.pager, &mut self.table);
+		self.primary_index_tree.insert(key, (row_offset, row_size), pager, &mut self.table);
         for secondary_tree in self.secondary_indexes.secondary_index_trees_mut().iter_mut() {
-            secondary_tree.insert(row_offset, (row_offset, row_size), &mut self.pager, &mut self.table);
+            secondary_tree.insert(row_offset, (row_offset, row_size), pager, &mut self.table);
         }
-        
-        self.flush_trees();
-       
-        Ok(())
-	}
 
-    pub fn search_by_primary_index(&mut self, key: DataType) -> Option<Vec<Vec<DataType>>> {        
-		if let Some (rows) = self.primary_index_tree.search(key.as_bytes(), &mut self.pager, &mut self.table) {
-            let data: Vec<Vec<DataType>> = rows.iter()
-                .map(|r| r.cells()
-                    .iter()
-                    .map(|c| c.to_typed_data())
-                    .collect()
-                ).collect();
-
-			return Some(data);
-		}
-        
-        None
+        self.count_write(1, 0);
+        self.flush_trees(pager)
 	}
 
-    pub fn search_by_secondary_index(&mut self, key: DataType, column_name: &str) -> Option<Vec<Vec<DataType>>> {
-        let indexed_column = self.indexed_column_from_column_name(column_name);
-        let secondary_tree = self.secondary_indexes
-            .secondary_index_trees_mut()
-            .iter_mut()
-            .find(|tree| {
-                if let Some(i_col) = tree.indexed_column() {
-                    if indexed_column == i_col {
-                        return true;
-                    }
-                }
-                false
-            });
-
-        if let Some(secondary_tree) = secondary_tree {
-            if let Some (rows) = secondary_tree.search(key.as_bytes(), &mut self.pager, &mut self.table) {
-                let data: Vec<Vec<DataType>> = rows.iter()
-                    .map(|r| r.cells()
-                        .iter()
-                        .map(|c| c.to_typed_data())
-                        .collect()
-                    ).collect();
-    
-                return Some(data);
-            }
+    fn check_row(&self, row: &Row) -> Result<(), String> {
+        let num_cols = self.table.num_columns();
+        let num_cells = row.num_cells();
+
+        // Columns count the row head and key, and rows have those and a padding cell on top of the caller's cells
+        if num_cols != num_cells - 1 {
+            return Err(format!("Expected row length {} but received length {}", num_cols - 2, num_cells - 3));
+        }
+
+        let mismatch = row.cells()
+            .iter()
+            .take(num_cols)
+            .enumerate()
+            .find(|(i, cell)| !self.table.column(*i).1.matches(&cell.to_typed_data()));
+
+        if let Some((i, cell)) = mismatch {
+            let (column, column_type) = self.table.column(i);
+            return Err(RowBuilderError::WrongType { column, expected: column_type, found: cell.to_typed_data().column_type() }.to_string());
         }
 
-        None
+        Ok(())
+    }
+
+	fn delete_by_primary_index(&mut self, key: DataType, pager: &mut Pager) -> Result<(), String> {
+        let row_pointers = pager.primary_root_offset_primary_root_size()
+            .and_then(|(primary_root_offset, primary_root_size)| BTree::search(pager, primary_root_offset, primary_root_size, &key.as_bytes()))
+            .unwrap_or_default();
+
+        self.delete_rows(&row_pointers, pager);
+        self.flush_trees(pager)
 	}
 
-	pub fn delete_by_primary_index(&mut self, key: DataType) {
-		let deleted_row = self.primary_index_tree.delete(key.as_bytes(), &mut self.pager, &mut self.table);
+    fn delete_by_secondary_index(&mut self, key: DataType, column_name: &str, pager: &mut Pager) -> Result<(), String> {
+        let indexed_column = self.indexed_column_from_column_name(column_name);
+        let row_pointers = (0..self.secondary_indexes.num_secondary_index_items())
+            .filter_map(|i| self.secondary_indexes.secondary_index_item(i))
+            .find(|(secondary_index_offset, i_col)| *secondary_index_offset > 0 && *i_col == indexed_column)
+            .and_then(|(secondary_index_offset, _)| BTree::search(pager, secondary_index_offset, NODE_SIZE, &key.as_bytes()))
+            .unwrap_or_default();
+
+        self.delete_rows(&row_pointers, pager);
+        self.flush_trees(pager)
+    }
+
+    // Each row comes out of the secondary trees by its offset, so rows sharing one of its indexed values keep their
+    // entries, and then out of the primary tree, which is the only one that frees it
+    fn delete_rows(&mut self, row_pointers: &[(u32, usize)], pager: &mut Pager) {
+        let mut deleted = 0;
+        for (row_offset, row_size) in row_pointers {
+            let Some(bytes) = pager.read_from_file(*row_offset as usize, *row_size) else {
+                continue;
+            };
+            let row = Row::from_bytes(bytes);
 
-        if let Some(deleted_row) = deleted_row {
             for secondary_tree in self.secondary_indexes.secondary_index_trees_mut().iter_mut() {
                 if let Some(indexed_column) = secondary_tree.indexed_column() {
-                    let key = deleted_row[0].cell_data(indexed_column).to_vec();
-                    secondary_tree.delete(key, &mut self.pager, &mut self.table);
+                    secondary_tree.remove_row(row.cell_data(indexed_column).to_vec(), *row_offset, pager, &mut self.table);
                 }
             }
+
+            if let Some(deleted_rows) = self.primary_index_tree.delete(row.primary_key_bytes().to_vec(), pager, &mut self.table) {
+                deleted += deleted_rows.len();
+            }
         }
 
-        self.flush_trees();
-	}
+        if deleted > 0 {
+            self.count_write(0, deleted);
+        }
+    }
 
-    pub fn delete_by_secondary_index(&mut self, key: DataType, column_name: &str) {
-        let indexed_column = self.indexed_column_from_column_name(column_name);
-        let secondary_tree = self.secondary_indexes
-            .secondary_index_trees_mut()
-            .iter_mut()
-            .find(|tree| {
-                if let Some(i_col) = tree.indexed_column() {
-                    if indexed_column == i_col {
-                        return true;
-                    }
-                }
-                false
-            });
-
-        if let Some(secondary_tree) = secondary_tree {
-            let deleted_rows = secondary_tree.delete(key.as_bytes(), &mut self.pager, &mut self.table);
-            if let Some(deleted_rows) = deleted_rows {
-                for row in &deleted_rows {
-                    let primary_key = row.primary_key_bytes().to_vec();
-                    self.primary_index_tree.delete(primary_key, &mut self.pager, &mut self.table);
-
-                    for secondary_tree in self.secondary_indexes.secondary_index_trees_mut().iter_mut() {
-                        if let Some(i_col) = secondary_tree.indexed_column() {
-                            if i_col == indexed_column { continue; }
-
-                            let key = row.cell_data(i_col).to_vec();
-                            secondary_tree.delete(key, &mut self.pager, &mut self.table);
-                        }
-                    }
-                }
-            }
+    // The row count is kept exact; the index statistics are left to `PocketDB::refresh_statistics`
+    fn count_write(&mut self, inserted: usize, deleted: usize) {
+        let statistics_list = self.secondary_indexes.statistics_list_mut();
+        statistics_list.set_rows((statistics_list.rows() + inserted).saturating_sub(deleted));
+        statistics_list.set_writes(statistics_list.writes() + 1);
+    }
+
+    // Once the writes since the trees were last counted pass a share of the rows, or there's an index without
+    // statistics
+    fn statistics_due(&self) -> bool {
+        let statistics_list = self.secondary_indexes.statistics_list();
+        let missing = statistics_list.num_items() != self.secondary_indexes.num_secondary_index_items() + 1;
+
+        missing || statistics_list.writes() * STATISTICS_REFRESH_RATIO >= statistics_list.rows().max(1)
+    }
+
+    fn secondary_index_offsets(&self) -> Vec<u32> {
+        (0..self.secondary_indexes.num_secondary_index_items())
+            .filter_map(|i| self.secondary_indexes.secondary_index_item(i))
+            .map(|(secondary_index_offset, _)| secondary_index_offset)
+            .collect()
+    }
+
+    // Takes counts made at commit `version`, when `writes` writes had been counted. Writes since then stay counted
+    // towards the next recount. Counts older than the ones installed, or from before an index was added, are dropped.
+    // The row count is left alone, as it's kept exact on every write. They're written with the next commit.
+    fn install_statistics(&mut self, items: Vec<IndexStatistics>, version: u64, writes: usize) {
+        if version < self.statistics_version || items.len() != self.secondary_indexes.num_secondary_index_items() + 1 {
+            return;
         }
 
-        self.flush_trees();
+        let statistics_list = self.secondary_indexes.statistics_list_mut();
+        let writes_since = statistics_list.writes().saturating_sub(writes);
+        statistics_list.set_items(&items);
+        statistics_list.set_writes(writes_since);
+        self.statistics_version = version;
+    }
+
+    // Decoded nodes and rows only live for one call. Between calls everything is fetched through the pager's
+    // buffer pool, which is what bounds memory.
+    fn release_caches(&mut self) {
+        self.primary_index_tree.release_cache();
+        for secondary_tree in self.secondary_indexes.secondary_index_trees_mut().iter_mut() {
+            secondary_tree.release_cache();
+        }
+        self.table.release_rows();
     }
 
     fn indexed_column_from_column_name(&self, column_name: &str) -> usize {
@@ -181,26 +651,27 @@ impl PocketDB {
         indexed_column
     }
 
-    fn flush_table(&mut self) {
+    fn flush_table(&mut self, pager: &mut Pager) -> Result<(), String> {
         let table_columns_size = self.table.columns_data().len();
-        let table_columns_offset = self.pager.add_to_write_buffer(
-            self.table.columns_data(), 
-            self.pager.table_columns_offset_table_columns_size()
+        let table_columns_offset = pager.add_to_write_buffer(
+            self.table.columns_data(),
+            pager.table_columns_offset_table_columns_size()
         );
 
-        self.pager.flush_table_columns((table_columns_offset, table_columns_size));
+        pager.flush_table_columns((table_columns_offset, table_columns_size)).map_err(|e| format!("Failed to commit: {}", e))
     }
 
-    fn flush_trees(&mut self) {
+    // The caches go whether or not the commit does, so the next write starts from what's in the file
+    fn flush_trees(&mut self, pager: &mut Pager) -> Result<(), String> {
         let (primary_root_offset, primary_root_size) = if let Some (primary_root) = self.primary_index_tree.root() {
-            let prev_root_offset_and_size = if let Some(primary_root) = self.pager.primary_root_offset_primary_root_size() {
+            let prev_root_offset_and_size = if let Some(primary_root) = pager.primary_root_offset_primary_root_size() {
                 let (primary_root_offset, primary_root_size) = primary_root;
                 Some((primary_root_offset, primary_root_size))
             } else {
                 None
             };
 
-            (self.pager.add_to_write_buffer(primary_root.data(), prev_root_offset_and_size), primary_root.data().len())
+            (pager.add_to_write_buffer(primary_root.data(), prev_root_offset_and_size), primary_root.data().len())
         } else {
             (0, 0)
         };
@@ -212,28 +683,34 @@ impl PocketDB {
             .filter_map(|(i, secondary_tree)|  {
                 if let Some(secondary_tree_root) = secondary_tree.root() {
                     let data = secondary_tree_root.data();
-                    let prev_offset_prev_size = self.secondary_indexes.secondary_index_item(i);
+                    // List items are (root offset, indexed column), and roots are always a single node
+                    let prev_offset_prev_size = self.secondary_indexes.secondary_index_item(i)
+                        .filter(|(prev_offset, _)| *prev_offset > 0)
+                        .map(|(prev_offset, _)| (prev_offset, data.len()));
 
-                    return Some(self.pager.add_to_write_buffer(data, prev_offset_prev_size));
+                    return Some(pager.add_to_write_buffer(data, prev_offset_prev_size));
                 }
                 None
             })
             .collect();
-        
+
         for (i, offset) in secondary_index_offsets.iter().enumerate() {
             self.secondary_indexes.set_secondary_index_item_offset(*offset, i);
         }
 
-        let secondary_index_list_size = self.secondary_indexes.secondary_index_data().len();
-        let secondary_index_list_offset = self.pager.add_to_write_buffer(
-            self.secondary_indexes.secondary_index_data(), 
-            self.pager.secondary_index_offset_secondary_index_size()
+        let secondary_index_data = self.secondary_indexes.secondary_index_data();
+        let secondary_index_list_offset = pager.add_to_write_buffer(
+            &secondary_index_data,
+            pager.secondary_index_offset_secondary_index_size()
+        );
+
+        let committed = pager.flush_write_buffer_trees(
+            (primary_root_offset, primary_root_size),
+            (secondary_index_list_offset, secondary_index_data.len())
         );
+        self.release_caches();
 
-        self.pager.flush_write_buffer_trees(
-            (primary_root_offset, primary_root_size), 
-            (secondary_index_list_offset, secondary_index_list_size)
-        ); 
+        committed.map_err(|e| format!("Failed to commit: {}", e))
     }
 
     fn new_primary_index_tree(pager: &mut Pager) -> BTree {
@@ -241,7 +718,7 @@ impl PocketDB {
             let (primary_root_offset, _) = root;
             return BTree::new(Some(primary_root_offset), None, None, pager);
         }
-        
+
         BTree::new(None, Some(NodeIndexType::Primary), None, pager)
     }
 
@@ -266,12 +743,311 @@ impl PocketDB {
 
         Table::new()
     }
+}
 
-    pub fn row(key: u32, cells: Vec<Cell>) -> Row {
-        Row::from_cells(cells, key)
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use crate::db::{options::CsvOptions, file_stystem::{fault_injecting_backend::FaultInjectingBackend, memory_backend::MemoryBackend}, options::OpenOptions, shared::{constants::statistics::STATISTICS_REFRESH_RATIO, enums::{ColumnType, DataType, Fault}}};
+
+    use super::PocketDB;
+
+    fn row(key: u32) -> super::Row {
+        PocketDB::row(key, vec![
+            PocketDB::cell(DataType::Text(format!("name {}", key % 5))),
+            PocketDB::cell(DataType::Integer(key)),
+        ])
     }
 
-    pub fn cell(data: DataType) -> Cell {
-        Cell::new_body(data)
+    #[test]
+    fn readers_share_a_handle_with_a_writer() {
+        let db = Arc::new(PocketDB::in_memory());
+        db.add_indexed_column("name", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+        for key in 1..=100 {
+            db.insert(key, row(key)).unwrap();
+        }
+
+        let writer = {
+            let db = Arc::clone(&db);
+            thread::spawn(move || {
+                for key in 101..=200 {
+                    db.insert(key, row(key)).unwrap();
+                }
+            })
+        };
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let db = Arc::clone(&db);
+                thread::spawn(move || {
+                    for key in 1..=100 {
+                        assert!(db.search_by_primary_index(DataType::Integer(key)).is_some(), "lost row {}", key);
+                    }
+                    assert!(db.search_by_secondary_index(DataType::Text(String::from("name 1")), "name").is_some());
+                })
+            })
+            .collect();
+
+        writer.join().unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        for key in 1..=200 {
+            assert!(db.search_by_primary_index(DataType::Integer(key)).is_some(), "lost row {}", key);
+        }
+    }
+
+    #[test]
+    fn row_count_and_statistics_follow_inserts_and_reopens() {
+        let path = std::env::temp_dir().join(format!("pocketdb-statistics-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let path = path.to_str().unwrap().to_string();
+
+        let db = PocketDB::open(&path).unwrap();
+        db.add_indexed_column("email", ColumnType::Text).unwrap();
+        assert!(db.is_empty());
+        for key in 1..=300 {
+            db.insert(key, PocketDB::row(key, vec![PocketDB::cell(DataType::Text(format!("{}@example.com", key)))])).unwrap();
+        }
+        assert_eq!(db.len(), 300);
+
+        let statistics = db.statistics();
+        assert_eq!(statistics.rows, 300);
+        assert_eq!(statistics.primary.column, "key");
+        assert!(statistics.primary.height > 1 && statistics.primary.nodes > 1);
+        let email = statistics.index("email").unwrap();
+        assert!(email.rows > 0 && email.rows <= 300);
+        assert_eq!(email.overflow_lengths.iter().sum::<usize>(), email.distinct_keys);
+        assert!(email.average_fill() > 0.0 && email.average_fill() <= 1.0);
+        drop(db);
+
+        let db = PocketDB::open(&path).unwrap();
+        assert_eq!(db.len(), 300);
+        assert_eq!(db.statistics().rows, 300);
+        assert!(db.statistics().index("email").is_some());
+        drop(db);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn index_statistics_are_recomputed_every_so_many_writes() {
+        let db = PocketDB::in_memory();
+        db.add_indexed_column("email", ColumnType::Text).unwrap();
+        let insert = |key: u32| {
+            db.insert(key, PocketDB::row(key, vec![PocketDB::cell(DataType::Text(format!("{}@example.com", key)))])).unwrap();
+        };
+        for key in 1..=200 {
+            insert(key);
+        }
+
+        // Runs to the next recount, then counts the writes to the one after it
+        let mut key = 200;
+        let mut recounts = vec![];
+        while recounts.len() < 2 {
+            let counted = db.statistics().primary.rows;
+            key += 1;
+            insert(key);
+            assert_eq!(db.statistics().rows, key as usize);
+            if db.statistics().primary.rows != counted {
+                assert_eq!(db.statistics().primary.rows, key as usize);
+                recounts.push(key);
+            }
+        }
+
+        let writes_between = (recounts[1] - recounts[0]) as usize;
+        assert!(writes_between > 1);
+        assert!(writes_between <= recounts[1] as usize / STATISTICS_REFRESH_RATIO + 1);
+    }
+
+    // Counts are taken from a snapshot outside the write lock, so other writes can land before they're installed
+    #[test]
+    fn index_statistics_counted_before_other_writes_keep_those_writes() {
+        let db = PocketDB::in_memory();
+        db.add_indexed_column("name", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+        for key in 1..=100 {
+            db.insert(key, row(key)).unwrap();
+        }
+
+        let snapshot = db.snapshot();
+        let (items, writes) = snapshot.count_statistics();
+        let version = snapshot.version();
+        drop(snapshot);
+        let (stale_items, stale_writes) = (items.clone(), writes);
+
+        db.insert(101, row(101)).unwrap();
+        db.indexes_mut().install_statistics(items, version, writes);
+        assert_eq!(db.len(), 101);
+        assert_eq!(db.statistics().primary.rows, 100);
+        assert_eq!(db.indexes().secondary_indexes.statistics_list().writes(), 1);
+
+        // Newer counts aren't replaced by older ones
+        db.indexes_mut().install_statistics(vec![Default::default(); 2], version + 1, 0);
+        db.indexes_mut().install_statistics(stale_items, version, stale_writes);
+        assert_eq!(db.statistics().primary.rows, 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn in_memory_databases_saved_to_disk_reopen_with_their_rows_and_indexes() {
+        let path = std::env::temp_dir().join(format!("pocketdb-save-to-{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let db = PocketDB::in_memory();
+        db.add_indexed_column("name", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+        for key in 1..=60 {
+            db.insert(key, PocketDB::row(key, vec![
+                PocketDB::cell(DataType::Text(format!("name {}", key % 5))),
+                PocketDB::cell(DataType::Integer(key)),
+            ])).unwrap();
+        }
+        db.save_to(path).unwrap();
+        drop(db);
+
+        let db = PocketDB::open(path).unwrap();
+        assert_eq!(db.len(), 60);
+        for key in 1..=60 {
+            let rows = db.search_by_primary_index(DataType::Integer(key)).unwrap();
+            assert!(rows[0].contains(&DataType::Text(format!("name {}", key % 5))), "row {} changed", key);
+        }
+        for name in 0..5 {
+            let rows = db.search_by_secondary_index(DataType::Text(format!("name {}", name)), "name").unwrap();
+            assert_eq!(rows.len(), 12);
+        }
+        drop(db);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn read_only_handles_return_errors_from_every_write() {
+        let path = std::env::temp_dir().join(format!("pocketdb-read-only-{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let db = PocketDB::in_memory();
+        db.add_indexed_column("name", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+        db.insert(1, row(1)).unwrap();
+        db.save_to(path).unwrap();
+        drop(db);
+
+        let db = PocketDB::open_with_options(path, OpenOptions::new().set_read_only(true)).unwrap();
+        let columns = db.columns();
+        let read_only = Err(String::from("Database is read-only"));
+        assert_eq!(db.add_column("notes", ColumnType::Text).map(|_| ()), read_only);
+        assert_eq!(db.add_indexed_column("city", ColumnType::Text).map(|_| ()), read_only);
+        assert_eq!(db.delete_by_primary_index(DataType::Integer(1)), read_only);
+        assert_eq!(db.delete_by_secondary_index(DataType::from("name 1"), "name"), read_only);
+        assert_eq!(db.columns(), columns);
+        assert_eq!(db.len(), 1);
+        drop(db);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn insert_reports_rows_of_the_wrong_length() {
+        let db = PocketDB::in_memory();
+        db.add_column("name", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+
+        let row = PocketDB::row(1, vec![PocketDB::cell(DataType::Text(String::from("Sammie")))]);
+        assert_eq!(db.insert(1, row), Err(String::from("Expected row length 2 but received length 1")));
+    }
+
+    // Rows sharing an indexed value sit in one overflow list; deleting one of them must leave the others' extents
+    // alone, or the next insert is written over a live row
+    #[test]
+    fn deleting_one_of_several_rows_with_an_indexed_value_keeps_the_others() {
+        let db = PocketDB::in_memory();
+        db.add_indexed_column("company", ColumnType::Text).unwrap();
+        let insert = |key: u32| db.insert(key, PocketDB::row(key, vec![PocketDB::cell(DataType::from("A"))])).unwrap();
+        for key in 1..=3 {
+            insert(key);
+        }
+
+        db.delete_by_primary_index(DataType::Integer(2)).unwrap();
+        insert(2);
+
+        for key in 1..=3 {
+            let rows = db.search_by_primary_index(DataType::Integer(key)).unwrap();
+            assert_eq!(rows[0][1], DataType::Integer(key));
+        }
+        assert_eq!(db.search_by_secondary_index(DataType::from("A"), "company").map(|rows| rows.len()), Some(3));
+    }
+
+    // Enough rows for a few levels of nodes, so deletes reach keys in internal nodes and empty whole leaves
+    #[test]
+    fn deleting_keys_from_a_deep_tree_keeps_every_other_row() {
+        let db = PocketDB::in_memory();
+        db.add_indexed_column("company", ColumnType::Text).unwrap();
+        for key in 1..=200 {
+            db.insert(key, PocketDB::row(key, vec![PocketDB::cell(DataType::from(if key % 2 == 0 { "A" } else { "B" }))])).unwrap();
+        }
+
+        for key in (1..=200).filter(|key| key % 3 == 0) {
+            db.delete_by_primary_index(DataType::Integer(key)).unwrap();
+        }
+
+        for key in 1..=200 {
+            let rows = db.search_by_primary_index(DataType::Integer(key));
+            assert_eq!(rows.map(|rows| rows[0][1].clone()), (key % 3 != 0).then_some(DataType::Integer(key)));
+        }
+        assert_eq!(db.len(), 134);
+        assert_eq!(db.search_by_secondary_index(DataType::from("A"), "company").map(|rows| rows.len()), Some(67));
+    }
+
+    #[test]
+    fn deleting_by_one_secondary_index_keeps_rows_sharing_another_indexed_value() {
+        let db = PocketDB::in_memory();
+        db.add_indexed_column("company", ColumnType::Text).unwrap();
+        db.add_indexed_column("city", ColumnType::Text).unwrap();
+        let insert = |key: u32, company: &str| {
+            db.insert(key, PocketDB::row(key, vec![PocketDB::cell(DataType::from(company)), PocketDB::cell(DataType::from("X"))])).unwrap()
+        };
+        for (key, company) in [(1, "A"), (2, "A"), (3, "B"), (4, "B")] {
+            insert(key, company);
+        }
+
+        db.delete_by_secondary_index(DataType::from("A"), "company").unwrap();
+        insert(5, "C");
+
+        let mut keys: Vec<DataType> = db.search_by_secondary_index(DataType::from("X"), "city")
+            .unwrap()
+            .into_iter()
+            .map(|row| row[1].clone())
+            .collect();
+        keys.sort_by_key(|key| format!("{:?}", key));
+        assert_eq!(keys, vec![DataType::Integer(3), DataType::Integer(4), DataType::Integer(5)]);
+        assert_eq!(db.search_by_primary_index(DataType::Integer(1)), None);
+    }
+
+    // Under `Durability::Full` every commit syncs twice, so the fourth commit's syncs are the seventh and eighth
+    #[test]
+    fn writes_whose_commit_fails_return_the_error() {
+        let mut backend = FaultInjectingBackend::new(MemoryBackend::new());
+        backend.inject(Fault::FailSync(7));
+        let db = PocketDB::with_storage(Box::new(backend), &OpenOptions::new());
+        db.add_indexed_column("name", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+
+        assert_eq!(db.insert(1, row(1)), Err(String::from("Failed to commit: Injected fault")));
+        assert!(db.delete_by_primary_index(DataType::Integer(1)).is_err());
+        assert!(db.add_column("notes", ColumnType::Text).is_err());
+    }
+
+    // Each batch ends in a checkpoint with one sync, so the second batch's is the eighth
+    #[test]
+    fn imported_rows_whose_batch_fails_to_sync_are_reported() {
+        let mut backend = FaultInjectingBackend::new(MemoryBackend::new());
+        backend.inject(Fault::FailSync(8));
+        let db = PocketDB::with_storage(Box::new(backend), &OpenOptions::new());
+        db.add_indexed_column("name", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+
+        let csv = "key,name,age\n1,Sam,30\n2,Jo,41\n3,Alex,25\n4,Lee,52\n";
+        let mut options = CsvOptions::new();
+        options.set_batch_size(2);
+        let import = db.import_csv(csv.as_bytes(), &options).unwrap();
+
+        assert_eq!(import.imported, 2);
+        let errors: Vec<String> = import.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors, vec![
+            "line 4: Failed to sync batch: Injected fault",
+            "line 5: Failed to sync batch: Injected fault",
+        ]);
+    }
+}
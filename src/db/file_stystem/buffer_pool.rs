@@ -0,0 +1,349 @@
+use std::{collections::{BTreeMap, HashMap, VecDeque}, io};
+
+use crate::db::{file_stystem::{page::Page, storage::{Storage, StorageBackend}}, shared::{constants::page::PAGE_SIZE, enums::EvictionPolicy}};
+
+#[derive(Debug)]
+struct Frame {
+    page: Page,
+    pin_count: usize,
+    dirty: bool,
+    referenced: bool,
+    last_used: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BufferPoolStats {
+    pub pages: usize,
+    pub bytes: usize,
+    pub budget: usize,
+    pub dirty_pages: usize,
+    pub pinned_pages: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+// Caches pages up to a byte budget. Pages are pinned while a caller works on them and never evicted while pinned;
+// if every page is pinned the pool goes over budget rather than fail. Dirty pages are written back when they're
+// evicted or when the pager commits through `flush`.
+#[derive(Debug)]
+pub struct BufferPool {
+    frames: HashMap<u32, Frame>,
+    budget: usize,
+    policy: EvictionPolicy,
+    lru: BTreeMap<u64, u32>,
+    clock: VecDeque<u32>,
+    tick: u64,
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+}
+
+impl BufferPool {
+    pub fn new(budget: usize, policy: EvictionPolicy) -> BufferPool {
+        BufferPool {
+            frames: HashMap::new(),
+            budget,
+            policy,
+            lru: BTreeMap::new(),
+            clock: VecDeque::new(),
+            tick: 0,
+            hits: 0,
+            misses: 0,
+            evictions: 0,
+        }
+    }
+
+    pub fn pin(&mut self, id: u32, storage: &mut dyn StorageBackend) -> Result<&mut Page, io::Error> {
+        self.fetch(id, false, storage)
+    }
+
+    // Pins a page the caller is about to overwrite completely, so a miss doesn't read it from the file first
+    pub fn pin_new(&mut self, id: u32, storage: &mut dyn StorageBackend) -> Result<&mut Page, io::Error> {
+        self.fetch(id, true, storage)
+    }
+
+    pub fn contains(&self, id: u32) -> bool {
+        self.frames.contains_key(&id)
+    }
+
+    pub fn unpin(&mut self, id: u32) {
+        if let Some(frame) = self.frames.get_mut(&id) {
+            frame.pin_count = frame.pin_count.saturating_sub(1);
+        }
+    }
+
+    pub fn mark_dirty(&mut self, id: u32) {
+        if let Some(frame) = self.frames.get_mut(&id) {
+            frame.dirty = true;
+        }
+    }
+
+    // Writes every dirty page back to the file. Page 0 holds master, so it goes last and the pages it points at are
+    // already in the file when it lands.
+    pub fn flush(&mut self, storage: &mut dyn StorageBackend) -> Result<(), io::Error> {
+        self.flush_where(storage, |_| true)
+    }
+
+    // Writes every dirty page but master's, so the caller can sync them before master is written
+    pub fn flush_data(&mut self, storage: &mut dyn StorageBackend) -> Result<(), io::Error> {
+        self.flush_where(storage, |id| id != 0)
+    }
+
+    fn flush_where(&mut self, storage: &mut dyn StorageBackend, include: impl Fn(u32) -> bool) -> Result<(), io::Error> {
+        let mut dirty: Vec<u32> = self.frames
+            .iter()
+            .filter(|(id, frame)| frame.dirty && include(**id))
+            .map(|(id, _)| *id)
+            .collect();
+        dirty.sort_by_key(|id| (*id == 0, *id));
+
+        for id in dirty {
+            if let Some(frame) = self.frames.get_mut(&id) {
+                storage.write_page(&frame.page)?;
+                frame.dirty = false;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Drops every page from `id` on without writing it back, for when the file is truncated under the pool
+    pub fn discard_from(&mut self, id: u32) {
+        let discarded: Vec<u32> = self.frames.keys().filter(|page_id| **page_id >= id).copied().collect();
+
+        for page_id in discarded {
+            if let Some(frame) = self.frames.remove(&page_id) {
+                self.lru.remove(&frame.last_used);
+            }
+        }
+        self.clock.retain(|page_id| *page_id < id);
+    }
+
+    pub fn stats(&self) -> BufferPoolStats {
+        BufferPoolStats {
+            pages: self.frames.len(),
+            bytes: self.frames.len() * PAGE_SIZE,
+            budget: self.budget,
+            dirty_pages: self.frames.values().filter(|frame| frame.dirty).count(),
+            pinned_pages: self.frames.values().filter(|frame| frame.pin_count > 0).count(),
+            hits: self.hits,
+            misses: self.misses,
+            evictions: self.evictions,
+        }
+    }
+
+    fn fetch(&mut self, id: u32, overwrite: bool, storage: &mut dyn StorageBackend) -> Result<&mut Page, io::Error> {
+        if self.frames.contains_key(&id) {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+            self.make_room(storage)?;
+
+            let page = if overwrite { Page::new(id) } else { storage.read_page(id)? };
+            self.frames.insert(id, Frame { page, pin_count: 0, dirty: false, referenced: false, last_used: 0 });
+            if self.policy == EvictionPolicy::Clock {
+                self.clock.push_back(id);
+            }
+        }
+
+        self.tick += 1;
+        let frame = self.frames.get_mut(&id).expect("Page was just cached");
+        self.lru.remove(&frame.last_used);
+        self.lru.insert(self.tick, id);
+        frame.last_used = self.tick;
+        frame.referenced = true;
+        frame.pin_count += 1;
+
+        Ok(&mut frame.page)
+    }
+
+    fn make_room(&mut self, storage: &mut dyn StorageBackend) -> Result<(), io::Error> {
+        while (self.frames.len() + 1) * PAGE_SIZE > self.budget {
+            if let Some(id) = self.victim() {
+                self.evict(id, storage)?;
+            } else {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn victim(&mut self) -> Option<u32> {
+        match self.policy {
+            EvictionPolicy::Lru => self.lru
+                .values()
+                .find(|id| self.frames.get(id).is_some_and(|frame| frame.pin_count == 0))
+                .copied(),
+            EvictionPolicy::Clock => {
+                // Two sweeps clear every reference bit, so if nothing turns up by then every page is pinned
+                for _ in 0..self.clock.len() * 2 {
+                    let id = self.clock.pop_front()?;
+                    let frame = self.frames.get_mut(&id)?;
+
+                    if frame.pin_count == 0 && !frame.referenced {
+                        return Some(id);
+                    }
+
+                    frame.referenced = false;
+                    self.clock.push_back(id);
+                }
+
+                None
+            }
+        }
+    }
+
+    fn evict(&mut self, id: u32, storage: &mut dyn StorageBackend) -> Result<(), io::Error> {
+        if let Some(frame) = self.frames.get(&id) {
+            if frame.dirty {
+                storage.write_page(&frame.page)?;
+            }
+
+            self.lru.remove(&frame.last_used);
+            self.frames.remove(&id);
+            self.evictions += 1;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{file_stystem::memory_backend::MemoryBackend, shared::{constants::page::PAGE_SIZE, enums::EvictionPolicy}};
+
+    use super::BufferPool;
+
+    fn touch(pool: &mut BufferPool, storage: &mut MemoryBackend, id: u32) {
+        pool.pin(id, storage).unwrap();
+        pool.unpin(id);
+    }
+
+    #[test]
+    fn eviction_keeps_the_pool_within_its_budget() {
+        let mut storage = MemoryBackend::new();
+        let mut pool = BufferPool::new(3 * PAGE_SIZE, EvictionPolicy::Lru);
+
+        for id in 0..10 {
+            touch(&mut pool, &mut storage, id);
+            assert!(pool.stats().bytes <= pool.stats().budget);
+        }
+
+        assert_eq!(pool.stats().pages, 3);
+        assert_eq!(pool.stats().evictions, 7);
+    }
+
+    #[test]
+    fn pinned_pages_are_never_evicted() {
+        for policy in [EvictionPolicy::Lru, EvictionPolicy::Clock] {
+            let mut storage = MemoryBackend::new();
+            let mut pool = BufferPool::new(2 * PAGE_SIZE, policy);
+
+            pool.pin(0, &mut storage).unwrap();
+            for id in 1..10 {
+                touch(&mut pool, &mut storage, id);
+            }
+
+            assert!(pool.contains(0), "{:?} evicted a pinned page", policy);
+            assert_eq!(pool.stats().pinned_pages, 1);
+        }
+    }
+
+    #[test]
+    fn a_pool_of_pinned_pages_goes_over_budget() {
+        let mut storage = MemoryBackend::new();
+        let mut pool = BufferPool::new(2 * PAGE_SIZE, EvictionPolicy::Lru);
+
+        for id in 0..3 {
+            pool.pin(id, &mut storage).unwrap();
+        }
+
+        assert_eq!(pool.stats().pages, 3);
+        assert_eq!(pool.stats().evictions, 0);
+    }
+
+    #[test]
+    fn dirty_pages_are_written_back_when_evicted() {
+        let mut storage = MemoryBackend::new();
+        let mut pool = BufferPool::new(PAGE_SIZE, EvictionPolicy::Lru);
+
+        pool.pin(1, &mut storage).unwrap().write(10, b"dirty");
+        pool.mark_dirty(1);
+        pool.unpin(1);
+        assert_eq!(storage.data().len(), 0);
+
+        touch(&mut pool, &mut storage, 2);
+        assert!(!pool.contains(1));
+        assert_eq!(&storage.data()[PAGE_SIZE + 10..PAGE_SIZE + 15], b"dirty");
+    }
+
+    #[test]
+    fn clean_pages_are_not_written_back() {
+        let mut storage = MemoryBackend::new();
+        let mut pool = BufferPool::new(PAGE_SIZE, EvictionPolicy::Lru);
+
+        touch(&mut pool, &mut storage, 1);
+        touch(&mut pool, &mut storage, 2);
+
+        assert!(!pool.contains(1));
+        assert_eq!(storage.data().len(), 0);
+    }
+
+    #[test]
+    fn flush_writes_dirty_pages_and_keeps_them_cached() {
+        let mut storage = MemoryBackend::new();
+        let mut pool = BufferPool::new(4 * PAGE_SIZE, EvictionPolicy::Lru);
+
+        for id in 0..2 {
+            pool.pin(id, &mut storage).unwrap().write(0, &[id as u8 + 1]);
+            pool.mark_dirty(id);
+            pool.unpin(id);
+        }
+        pool.flush(&mut storage).unwrap();
+
+        assert_eq!(pool.stats().dirty_pages, 0);
+        assert_eq!(pool.stats().pages, 2);
+        assert_eq!((storage.data()[0], storage.data()[PAGE_SIZE]), (1, 2));
+    }
+
+    // Pages 0, 1 and 2 fill the pool and 0 is used again before 3 needs room
+    fn victim_after_reuse(policy: EvictionPolicy) -> u32 {
+        let mut storage = MemoryBackend::new();
+        let mut pool = BufferPool::new(3 * PAGE_SIZE, policy);
+
+        for id in [0, 1, 2, 0, 3] {
+            touch(&mut pool, &mut storage, id);
+        }
+
+        let evicted: Vec<u32> = (0..3).filter(|id| !pool.contains(*id)).collect();
+        assert_eq!(evicted.len(), 1);
+        evicted[0]
+    }
+
+    #[test]
+    fn lru_evicts_the_least_recently_used_page() {
+        assert_eq!(victim_after_reuse(EvictionPolicy::Lru), 1);
+    }
+
+    // Every page was referenced since the hand last passed, so it sweeps them all clear and takes the first
+    #[test]
+    fn clock_evicts_the_first_page_under_the_hand_once_references_are_cleared() {
+        assert_eq!(victim_after_reuse(EvictionPolicy::Clock), 0);
+    }
+
+    #[test]
+    fn clock_gives_referenced_pages_a_second_chance() {
+        let mut storage = MemoryBackend::new();
+        let mut pool = BufferPool::new(3 * PAGE_SIZE, EvictionPolicy::Clock);
+
+        // Evicting 0 clears the reference bits of 1 and 2; using 1 again sets its bit, so 2 goes next
+        for id in [0, 1, 2, 3, 1, 4] {
+            touch(&mut pool, &mut storage, id);
+        }
+
+        assert!(pool.contains(1));
+        assert!(!pool.contains(2));
+    }
+}
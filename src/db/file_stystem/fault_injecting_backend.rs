@@ -0,0 +1,222 @@
+use std::io;
+
+use crate::db::{file_stystem::storage::StorageBackend, shared::enums::Fault};
+
+// Wraps a backend and fails or tears the writes and syncs it's told to. Once a fault fires the backend behaves as if
+// the machine went down: the writes since the last sync are undone, except for whatever part of a torn write got
+// through, and every later write and sync fails. What's left in the inner backend is what a restart would find.
+#[derive(Debug)]
+pub struct FaultInjectingBackend<B: StorageBackend> {
+    inner: B,
+    faults: Vec<Fault>,
+    writes: usize,
+    syncs: usize,
+    crashed: bool,
+    // What each write since the last sync overwrote, oldest first, and how long the backend was before them
+    unsynced: Vec<(usize, Vec<u8>)>,
+    synced_len: Option<usize>,
+}
+
+impl<B: StorageBackend> FaultInjectingBackend<B> {
+    pub fn new(inner: B) -> FaultInjectingBackend<B> {
+        FaultInjectingBackend { inner, faults: vec![], writes: 0, syncs: 0, crashed: false, unsynced: vec![], synced_len: None }
+    }
+
+    pub fn inject(&mut self, fault: Fault) -> &mut Self {
+        self.faults.push(fault);
+
+        self
+    }
+
+    pub fn syncs(&self) -> usize {
+        self.syncs
+    }
+
+    fn check_crashed(&self) -> Result<(), io::Error> {
+        if self.crashed {
+            return Err(io::Error::other("Backend crashed"));
+        }
+
+        Ok(())
+    }
+
+    fn next_sync(&mut self) -> Result<(), io::Error> {
+        self.check_crashed()?;
+
+        self.syncs += 1;
+        if self.faults.contains(&Fault::FailSync(self.syncs)) {
+            return Err(self.crash());
+        }
+
+        Ok(())
+    }
+
+    fn synced(&mut self) {
+        self.unsynced.clear();
+        self.synced_len = None;
+    }
+
+    // Keeps the bytes a write is about to overwrite, so a crash can put them back
+    fn keep_unsynced(&mut self, offset: usize, len: usize) -> Result<(), io::Error> {
+        if self.synced_len.is_none() {
+            self.synced_len = Some(self.inner.len()?);
+        }
+
+        let mut overwritten = vec![0u8; len];
+        let read = self.inner.read_at(offset, &mut overwritten)?;
+        overwritten.truncate(read);
+        self.unsynced.push((offset, overwritten));
+
+        Ok(())
+    }
+
+    fn crash(&mut self) -> io::Error {
+        self.crashed = true;
+
+        for (offset, overwritten) in self.unsynced.drain(..).rev() {
+            if let Err(e) = self.inner.write_at(offset, &overwritten) {
+                return e;
+            }
+        }
+        if let Some(synced_len) = self.synced_len.take() {
+            if let Err(e) = self.inner.truncate(synced_len) {
+                return e;
+            }
+        }
+
+        io::Error::other("Injected fault")
+    }
+}
+
+impl<B: StorageBackend> StorageBackend for FaultInjectingBackend<B> {
+    fn read_at(&mut self, offset: usize, buf: &mut [u8]) -> Result<usize, io::Error> {
+        self.inner.read_at(offset, buf)
+    }
+
+    fn write_at(&mut self, offset: usize, bytes: &[u8]) -> Result<(), io::Error> {
+        self.check_crashed()?;
+
+        self.writes += 1;
+        for fault in self.faults.clone() {
+            match fault {
+                Fault::FailWrite(n) if n == self.writes => return Err(self.crash()),
+                Fault::TearWrite(n, len) if n == self.writes => {
+                    let error = self.crash();
+                    self.inner.write_at(offset, &bytes[..len.min(bytes.len())])?;
+                    return Err(error);
+                },
+                _ => (),
+            }
+        }
+
+        self.keep_unsynced(offset, bytes.len())?;
+        self.inner.write_at(offset, bytes)
+    }
+
+    fn len(&mut self) -> Result<usize, io::Error> {
+        self.inner.len()
+    }
+
+    fn truncate(&mut self, len: usize) -> Result<(), io::Error> {
+        self.check_crashed()?;
+
+        self.inner.truncate(len)
+    }
+
+    fn sync(&mut self) -> Result<(), io::Error> {
+        self.next_sync()?;
+
+        self.inner.sync()?;
+        self.synced();
+
+        Ok(())
+    }
+
+    fn sync_data(&mut self) -> Result<(), io::Error> {
+        self.next_sync()?;
+
+        self.inner.sync_data()?;
+        self.synced();
+
+        Ok(())
+    }
+
+    fn map(&mut self) -> Result<(), io::Error> {
+        self.inner.map()
+    }
+
+    fn read_mapped(&mut self, offset: usize, size: usize) -> Option<&[u8]> {
+        self.inner.read_mapped(offset, size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::PathBuf};
+
+    use crate::db::{db::PocketDB, file_stystem::file_handler::FileHandler, options::OpenOptions, shared::enums::{ColumnType, DataType, Fault}};
+
+    use super::FaultInjectingBackend;
+
+    const ROWS: u32 = 20;
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("pocketdb-{}-{}.db", name, std::process::id()))
+    }
+
+    fn insert(db: &PocketDB, key: u32) -> Result<(), String> {
+        db.insert(key, PocketDB::row(key, vec![
+            PocketDB::cell(DataType::Text(format!("name {}", key % 3))),
+            PocketDB::cell(DataType::Integer(key)),
+        ]))
+    }
+
+    // Inserts one more row into a copy of `base` with `fault` injected, then reopens the copy the way a restart would.
+    // Returns whether the insert succeeded, having checked that the copy holds exactly the rows it committed.
+    fn crash_insert(base: &PathBuf, fault: Fault) -> bool {
+        let path = temp_path(&format!("fault-{:?}", fault));
+        fs::copy(base, &path).unwrap();
+
+        let mut backend = FaultInjectingBackend::new(FileHandler::new(path.to_str().unwrap()));
+        backend.inject(fault);
+        let db = PocketDB::with_storage(Box::new(backend), &OpenOptions::new());
+        let committed = insert(&db, ROWS + 1).is_ok();
+        drop(db);
+
+        let rows = if committed { ROWS + 1 } else { ROWS };
+        let db = PocketDB::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(db.len(), rows as usize, "{:?}", fault);
+        for key in 1..=ROWS + 1 {
+            assert_eq!(db.search_by_primary_index(DataType::Integer(key)).is_some(), key <= rows, "{:?} row {}", fault, key);
+        }
+        drop(db);
+
+        fs::remove_file(&path).unwrap();
+        committed
+    }
+
+    #[test]
+    fn crash_during_commit_keeps_previous_commits() {
+        let base = temp_path("fault-base");
+        let _ = fs::remove_file(&base);
+
+        let db = PocketDB::open(base.to_str().unwrap()).unwrap();
+        db.add_indexed_column("name", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+        for key in 1..=ROWS {
+            insert(&db, key).unwrap();
+        }
+        drop(db);
+
+        // Master is only written once the rest of the commit is synced, so failing before then loses just the new row
+        assert!(!crash_insert(&base, Fault::TearWrite(1, 100)));
+
+        // Once a fault fires the commit can't finish, so the first insert to succeed is the one whose fault came after
+        // every write or sync it made. `Durability::Full` syncs twice per commit.
+        let failed_writes = (1..).take_while(|n| !crash_insert(&base, Fault::FailWrite(*n))).count();
+        let failed_syncs = (1..).take_while(|n| !crash_insert(&base, Fault::FailSync(*n))).count();
+        assert!(failed_writes > 0);
+        assert_eq!(failed_syncs, 2);
+
+        fs::remove_file(&base).unwrap();
+    }
+}
@@ -1,62 +1,309 @@
-use std::{fs::{File, OpenOptions}, io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write}};
+use std::{fs::{File, OpenOptions, TryLockError}, io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write}, thread, time::{Duration, Instant}};
+
+use memmap2::Mmap;
+
+use crate::db::{file_stystem::storage::StorageBackend, shared::{constants::lock::LOCK_RETRY_INTERVAL, enums::OpenError}};
 
 #[derive(Debug)]
 pub struct FileHandler {
     buf_reader: BufReader<File>,
     buf_writer: BufWriter<File>,
+    mmap: Option<Mmap>,
 }
 
 impl FileHandler {
-    pub fn new() -> FileHandler {
+    pub fn new(path: &str) -> FileHandler {
         let file = OpenOptions::new()
             .read(true)
             .write(true)
             .create(true)
-            .open("db.db")
+            .open(path)
             .expect("Failed to open file");
+
+        FileHandler::from_file(file)
+    }
+
+    // Opens the database file and takes its lock, which is held until the handler is dropped
+    pub fn open(path: &str, read_only: bool, lock_timeout: Option<Duration>) -> Result<FileHandler, OpenError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(!read_only)
+            .create(!read_only)
+            .open(path)
+            .map_err(|e| OpenError::Io(format!("Failed to open {}: {}", path, e)))?;
+
+        FileHandler::lock(&file, read_only, lock_timeout)?;
+
+        Ok(FileHandler::from_file(file))
+    }
+
+    pub fn create(path: &str) -> Result<FileHandler, io::Error> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+
+        Ok(FileHandler::from_file(file))
+    }
+
+    // Creates an empty file and takes its lock, for one that's going to replace a database file. An existing file is
+    // only emptied once it's locked, so a file another handle is still building is left alone.
+    pub fn create_locked(path: &str) -> Result<FileHandler, OpenError> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(|e| OpenError::Io(format!("Failed to create {}: {}", path, e)))?;
+
+        FileHandler::lock(&file, false, None)?;
+        file.set_len(0).map_err(|e| OpenError::Io(format!("Failed to create {}: {}", path, e)))?;
+
+        Ok(FileHandler::from_file(file))
+    }
+
+    // The lock is advisory (flock on Linux), so it only keeps out other PocketDB handles. It belongs to the open
+    // file rather than the process, so two handles in one process exclude each other too.
+    fn lock(file: &File, shared: bool, lock_timeout: Option<Duration>) -> Result<(), OpenError> {
+        let started = Instant::now();
+
+        loop {
+            let locked = if shared { file.try_lock_shared() } else { file.try_lock() };
+
+            match locked {
+                Ok(()) => return Ok(()),
+                Err(TryLockError::WouldBlock) => {
+                    if lock_timeout.is_none_or(|lock_timeout| started.elapsed() >= lock_timeout) {
+                        return Err(OpenError::Locked);
+                    }
+
+                    thread::sleep(LOCK_RETRY_INTERVAL);
+                },
+                Err(TryLockError::Error(e)) => return Err(OpenError::Io(format!("Failed to lock file: {}", e))),
+            }
+        }
+    }
+
+    fn from_file(file: File) -> FileHandler {
         let buf_reader = BufReader::new(file.try_clone().expect("Could not clone"));
         let buf_writer = BufWriter::new(file);
 
         FileHandler {
             buf_reader,
             buf_writer,
+            mmap: None,
         }
     }
+}
 
-    pub fn write(&mut self, buf: &Vec<u8>) -> Result<(), io::Error> {
-        self.buf_writer.write(buf)?;
+impl StorageBackend for FileHandler {
+    fn read_at(&mut self, offset: usize, buf: &mut [u8]) -> Result<usize, io::Error> {
+        self.buf_reader.seek(SeekFrom::Start(offset as u64))?;
+
+        let mut bytes_read = 0;
+        while bytes_read < buf.len() {
+            let n = self.buf_reader.read(&mut buf[bytes_read..])?;
+            if n == 0 {
+                break;
+            }
+            bytes_read += n;
+        }
+
+        Ok(bytes_read)
+    }
+
+    fn write_at(&mut self, offset: usize, bytes: &[u8]) -> Result<(), io::Error> {
+        self.buf_writer.seek(SeekFrom::Start(offset as u64))?;
+        self.buf_writer.write_all(bytes)?;
         self.buf_writer.flush()?;
 
         Ok(())
     }
 
-    pub fn read(&mut self, buf: &mut Vec<u8>) -> Result<usize, io::Error> {
-        let bytes_read = self.buf_reader.read(buf)?;
+    fn len(&mut self) -> Result<usize, io::Error> {
+        self.buf_writer.flush()?;
 
-        Ok(bytes_read)
+        Ok(self.buf_writer.get_ref().metadata()?.len() as usize)
     }
 
-    pub fn seek_overwrite(&mut self, offset: usize) -> Result<(), io::Error> {
-        self.buf_writer.seek(SeekFrom::Start(offset as u64))?;
+    fn truncate(&mut self, len: usize) -> Result<(), io::Error> {
+        self.buf_writer.flush()?;
+        self.buf_writer.get_ref().set_len(len as u64)?;
+
+        // Touching a mapped page past the new end of the file would fault, so shrink the mapping with it
+        if self.mmap.is_some() {
+            self.map()?;
+        }
 
         Ok(())
     }
 
-    pub fn seek_write(&mut self) -> Result<u32, io::Error> {
-        let bytes_seek = self.buf_writer.seek(SeekFrom::End(0))?;
+    fn map(&mut self) -> Result<(), io::Error> {
+        self.buf_writer.flush()?;
+
+        // Safety: the mapping is read-only and only ever covers the file's current length. It's remapped whenever
+        // this handler truncates the file; another process truncating it underneath us is not guarded against.
+        self.mmap = Some(unsafe { Mmap::map(self.buf_writer.get_ref())? });
 
-        Ok(bytes_seek as u32)
+        Ok(())
     }
 
-    pub fn seek_reader(&mut self, offset: usize) -> Result<(), io::Error> {
-        self.buf_reader.seek(SeekFrom::Start(offset as u64))?;
+    // Returns the bytes straight from the memory map, remapping once if the file has grown past the mapping.
+    // Returns None when the range is past the end of the file or the file isn't mapped.
+    fn read_mapped(&mut self, offset: usize, size: usize) -> Option<&[u8]> {
+        let mapped_len = self.mmap.as_ref()?.len();
+
+        if offset + size > mapped_len {
+            self.map().ok()?;
+        }
+
+        self.mmap.as_ref()?.get(offset..offset + size)
+    }
+
+    fn sync_data(&mut self) -> Result<(), io::Error> {
+        self.buf_writer.flush()?;
+        self.buf_writer.get_ref().sync_data()?;
 
         Ok(())
     }
 
-    pub fn seek_end(&mut self) -> Result<(), io::Error> {
-        self.buf_reader.seek(SeekFrom::End(0))?;
+    fn sync(&mut self) -> Result<(), io::Error> {
+        self.buf_writer.flush()?;
+        self.buf_writer.get_ref().sync_all()?;
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, thread, time::Duration};
+
+    use crate::db::{db::PocketDB, file_stystem::storage::StorageBackend, options::OpenOptions, shared::{constants::page::PAGE_SIZE, enums::{ColumnType, DataType, OpenError, ReadMode}}};
+
+    use super::FileHandler;
+
+    fn temp_path(name: &str) -> String {
+        env::temp_dir().join(format!("pocketdb-{}-{}.db", name, std::process::id())).to_str().unwrap().to_string()
+    }
+
+    fn pattern(len: usize, seed: usize) -> Vec<u8> {
+        (0..len).map(|i| ((i * 7 + seed) % 251) as u8).collect()
+    }
+
+    fn buffered(file_handler: &mut FileHandler, offset: usize, size: usize) -> Vec<u8> {
+        let mut bytes = vec![0u8; size];
+        let bytes_read = file_handler.read_at(offset, &mut bytes).unwrap();
+        bytes.truncate(bytes_read);
+
+        bytes
+    }
+
+    #[test]
+    fn writers_exclude_every_other_handle() {
+        let path = env::temp_dir().join(format!("pocketdb-lock-{}.db", std::process::id()));
+        let path = path.to_str().unwrap();
+
+        let writer = FileHandler::open(path, false, None).unwrap();
+        assert_eq!(FileHandler::open(path, false, None).err(), Some(OpenError::Locked));
+        assert_eq!(FileHandler::open(path, true, None).err(), Some(OpenError::Locked));
+        drop(writer);
+
+        let reader = FileHandler::open(path, true, None).unwrap();
+        let other_reader = FileHandler::open(path, true, None).unwrap();
+        assert_eq!(FileHandler::open(path, false, None).err(), Some(OpenError::Locked));
+        drop((reader, other_reader));
+
+        let writer = FileHandler::open(path, false, None).unwrap();
+        let release = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            drop(writer);
+        });
+        assert!(FileHandler::open(path, false, Some(Duration::from_secs(5))).is_ok());
+        release.join().unwrap();
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn mapped_reads_match_buffered_reads() {
+        let path = temp_path("mmap-reads");
+        let mut file_handler = FileHandler::create(&path).unwrap();
+        file_handler.write_at(0, &pattern(3 * PAGE_SIZE, 0)).unwrap();
+        file_handler.map().unwrap();
+
+        for (offset, size) in [(0, 10), (PAGE_SIZE - 5, 10), (1000, 2 * PAGE_SIZE), (0, 3 * PAGE_SIZE)] {
+            let mapped = file_handler.read_mapped(offset, size).unwrap().to_vec();
+            assert_eq!(mapped, buffered(&mut file_handler, offset, size), "read of {} at {}", size, offset);
+        }
+
+        drop(file_handler);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn mapped_reads_past_the_mapping_see_the_grown_file() {
+        let path = temp_path("mmap-growth");
+        let mut file_handler = FileHandler::create(&path).unwrap();
+        file_handler.write_at(0, &pattern(PAGE_SIZE, 0)).unwrap();
+        file_handler.map().unwrap();
+        file_handler.write_at(PAGE_SIZE, &pattern(2 * PAGE_SIZE, 1)).unwrap();
+
+        let mapped = file_handler.read_mapped(PAGE_SIZE / 2, 2 * PAGE_SIZE).unwrap().to_vec();
+        assert_eq!(mapped, buffered(&mut file_handler, PAGE_SIZE / 2, 2 * PAGE_SIZE));
+
+        drop(file_handler);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn mapped_reads_past_the_end_of_the_file_are_none() {
+        let path = temp_path("mmap-end");
+        let mut file_handler = FileHandler::create(&path).unwrap();
+        file_handler.write_at(0, &pattern(PAGE_SIZE, 0)).unwrap();
+        file_handler.map().unwrap();
+
+        assert!(file_handler.read_mapped(PAGE_SIZE - 5, 10).is_none());
+        file_handler.truncate(PAGE_SIZE / 2).unwrap();
+        assert!(file_handler.read_mapped(PAGE_SIZE / 2 - 5, 10).is_none());
+
+        drop(file_handler);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn mmap_and_buffered_databases_read_the_same_rows() {
+        let path = temp_path("mmap-db");
+        let _ = fs::remove_file(&path);
+        let read_all = |db: &PocketDB| -> Vec<Vec<Vec<DataType>>> {
+            (1..=80).filter_map(|key| db.search_by_primary_index(DataType::Integer(key))).collect()
+        };
+        let insert = |db: &PocketDB, keys: std::ops::RangeInclusive<u32>| {
+            for key in keys {
+                db.insert(key, PocketDB::row(key, vec![PocketDB::cell(DataType::Text(format!("{} ", key).repeat(40)))])).unwrap();
+            }
+        };
+
+        // A pool of two pages keeps most reads on the map, so rows written after it was made are read past its end
+        let mut options = OpenOptions::new();
+        options.set_read_mode(ReadMode::Mmap).set_buffer_pool_size(2 * PAGE_SIZE);
+        let db = PocketDB::open_with_options(&path, &options).unwrap();
+        db.add_column("notes", ColumnType::Text).unwrap();
+        insert(&db, 1..=20);
+        assert_eq!(read_all(&db).len(), 20);
+        insert(&db, 21..=80);
+        let mapped = read_all(&db);
+        drop(db);
+
+        let db = PocketDB::open(&path).unwrap();
+        let buffered = read_all(&db);
+        drop(db);
+
+        assert_eq!(mapped.len(), 80);
+        assert_eq!(mapped, buffered);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,63 @@
+use std::io;
+
+use crate::db::file_stystem::storage::StorageBackend;
+
+// Keeps the database image in a `Vec<u8>` laid out exactly like the file would be
+#[derive(Debug, Default)]
+pub struct MemoryBackend {
+    data: Vec<u8>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> MemoryBackend {
+        MemoryBackend::default()
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl StorageBackend for MemoryBackend {
+    fn read_at(&mut self, offset: usize, buf: &mut [u8]) -> Result<usize, io::Error> {
+        let start = offset.min(self.data.len());
+        let end = (offset + buf.len()).min(self.data.len());
+        buf[..end - start].copy_from_slice(&self.data[start..end]);
+
+        Ok(end - start)
+    }
+
+    fn write_at(&mut self, offset: usize, bytes: &[u8]) -> Result<(), io::Error> {
+        let end = offset + bytes.len();
+
+        if self.data.len() < end {
+            self.data.resize(end, 0);
+        }
+        self.data[offset..end].copy_from_slice(bytes);
+
+        Ok(())
+    }
+
+    fn len(&mut self) -> Result<usize, io::Error> {
+        Ok(self.data.len())
+    }
+
+    fn truncate(&mut self, len: usize) -> Result<(), io::Error> {
+        self.data.resize(len, 0);
+
+        Ok(())
+    }
+
+    fn sync(&mut self) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    // The bytes are already in memory, so a "mapped" read is just a slice of them
+    fn map(&mut self) -> Result<(), io::Error> {
+        Ok(())
+    }
+
+    fn read_mapped(&mut self, offset: usize, size: usize) -> Option<&[u8]> {
+        self.data.get(offset..offset + size)
+    }
+}
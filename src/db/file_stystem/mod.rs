@@ -1,3 +1,9 @@
 pub mod pager;
 pub mod file_handler;
-mod write_buffer;
\ No newline at end of file
+pub mod buffer_pool;
+pub mod storage;
+pub mod memory_backend;
+#[cfg(test)]
+pub mod fault_injecting_backend;
+pub mod page;
+mod write_buffer;
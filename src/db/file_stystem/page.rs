@@ -0,0 +1,57 @@
+use crate::db::shared::constants::page::PAGE_SIZE;
+
+// The pager reads and writes the file in whole PAGE_SIZE pages, and the file grows and shrinks by whole pages too.
+// Pages aren't slotted: they have no header or slot directory, and free space is still tracked as byte extents by
+// `FreeList` rather than per page. Blocks keep their byte offsets, so a block's page is `offset / PAGE_SIZE` and it
+// starts `offset % PAGE_SIZE` into it; the allocator makes sure a block that fits in a page never straddles two.
+#[derive(Debug, Clone)]
+pub struct Page {
+    id: u32,
+    data: Vec<u8>,
+}
+
+impl Page {
+    pub fn new(id: u32) -> Page {
+        Page { id, data: vec![0u8; PAGE_SIZE] }
+    }
+
+    pub fn from_bytes(id: u32, bytes: &[u8]) -> Page {
+        let mut page = Page::new(id);
+        let len = bytes.len().min(PAGE_SIZE);
+        page.data[..len].copy_from_slice(&bytes[..len]);
+
+        page
+    }
+
+    pub fn offset(&self) -> usize {
+        self.id as usize * PAGE_SIZE
+    }
+
+    pub fn read(&self, offset: usize, size: usize) -> &[u8] {
+        &self.data[offset..offset + size]
+    }
+
+    pub fn write(&mut self, offset: usize, bytes: &[u8]) {
+        self.data[offset..offset + bytes.len()].copy_from_slice(bytes);
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+pub fn page_id(offset: usize) -> u32 {
+    (offset / PAGE_SIZE) as u32
+}
+
+// Returns the first offset at or after `offset` where a block of `size` bytes starts without straddling a page
+// boundary. Blocks larger than a page start on a page boundary instead.
+pub fn fit_offset(offset: usize, size: usize) -> usize {
+    let page_offset = offset % PAGE_SIZE;
+
+    if page_offset == 0 || (size <= PAGE_SIZE && page_offset + size <= PAGE_SIZE) {
+        offset
+    } else {
+        offset - page_offset + PAGE_SIZE
+    }
+}
@@ -1,50 +1,158 @@
-use std::io::Read;
+use std::{collections::BTreeMap, io};
 
-use crate::db::{file_stystem::{file_handler::FileHandler, write_buffer::WriteBufferItem}, meta::disk_storage::{free_list::{FreeList, FreeListItem}, master::Master}, shared::constants::{self, free_list_item::FREE_ITEM_SIZE, params::ELEMENT_SIZE}};
+use crate::db::{file_stystem::{buffer_pool::{BufferPool, BufferPoolStats}, file_handler::FileHandler, memory_backend::MemoryBackend, page, storage::{Storage, StorageBackend}, write_buffer::WriteBufferItem}, meta::disk_storage::{free_list::{FreeList, FreeListItem, FreeListStats}, master::Master}, options::OpenOptions, shared::{constants::{self, durability::CHECKPOINT_INTERVAL, free_list_item::FREE_ITEM_SIZE, page::PAGE_SIZE, params::ELEMENT_SIZE}, enums::{Durability, OpenError, ReadMode}}};
 
 #[derive(Debug)]
 pub struct Pager {
     pub master: Master,
-    file_handler: FileHandler,
+    path: Option<String>,
+    options: OpenOptions,
+    storage: Box<dyn StorageBackend>,
+    buffer_pool: BufferPool,
+    commits_since_checkpoint: usize,
+    batching: bool,
+    version: u64,
+    snapshots: BTreeMap<u64, usize>,
     free_list: FreeList,
     write_buffers: Vec<WriteBufferItem>,
-    eof_buffer: Option<usize>,
+    eof: usize,
 }
 
 impl Pager {
-    pub fn new() -> Pager {
-        let mut file_handler: FileHandler = FileHandler::new();
+    pub fn new(path: &str, options: &OpenOptions) -> Result<Pager, OpenError> {
+        let file_handler = FileHandler::open(path, options.read_only(), options.lock_timeout())?;
 
-        if let Some(master) = Pager::master_from_file(&mut file_handler) {
-            if let Some(free_list) = Pager::free_list_from_file(&mut file_handler, &master) {
-                return Pager { master, file_handler, write_buffers: vec![], free_list, eof_buffer: None };
+        Ok(Pager::with_storage(Some(path.to_string()), Box::new(file_handler), options))
+    }
+
+    pub fn in_memory(options: &OpenOptions) -> Pager {
+        Pager::with_storage(None, Box::new(MemoryBackend::new()), options)
+    }
+
+    // `path` is where the storage lives on disk, if anywhere
+    pub fn with_storage(path: Option<String>, storage: Box<dyn StorageBackend>, options: &OpenOptions) -> Pager {
+        let buffer_pool = BufferPool::new(options.buffer_pool_size(), options.eviction_policy());
+        let mut pager = Pager {
+            master: Master::new(),
+            path,
+            options: options.clone(),
+            storage,
+            buffer_pool,
+            commits_since_checkpoint: 0,
+            batching: false,
+            version: 0,
+            snapshots: BTreeMap::new(),
+            free_list: FreeList::new(),
+            write_buffers: vec![],
+            eof: PAGE_SIZE,
+        };
+
+        if options.read_mode() == ReadMode::Mmap && pager.storage.map().is_err() {
+            println!("Failed to map file, falling back to buffered reads");
+            pager.options.set_read_mode(ReadMode::Buffered);
+        }
+
+        if let Some(master) = pager.master_from_file() {
+            pager.master = master;
+
+            if let Some(free_list) = pager.free_list_from_file() {
+                pager.free_list = free_list;
+            }
+            pager.eof = pager.eof_from_file();
+        } else if !options.read_only() {
+            // Master takes the start of page 0, the rest of it is free
+            pager.free_list.add_unused(constants::master::MASTER_SIZE, PAGE_SIZE - constants::master::MASTER_SIZE);
+            if pager.free_list_to_file().and_then(|_| pager.master_to_file()).and_then(|_| pager.commit()).is_err() {
+                println!("Failed to write master");
             }
+        }
 
-            Pager { master, file_handler, write_buffers: vec![], free_list: FreeList::new(), eof_buffer: None }
-        } else {
-            let master = Master::new();            
-            let mut pager = Pager { master, file_handler, write_buffers: vec![], free_list: FreeList::new(), eof_buffer: None };
-            pager.master_to_file();
+        pager
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    pub fn options(&self) -> &OpenOptions {
+        &self.options
+    }
+
+    pub fn buffer_pool_stats(&self) -> BufferPoolStats {
+        self.buffer_pool.stats()
+    }
+
+    pub fn write_to_file(&mut self, offset: u32, bytes: &[u8]) -> Result<(), io::Error> {
+        self.write_pages(vec![(offset as usize, bytes)])
+    }
+
+    pub fn file_len(&mut self) -> Result<u32, io::Error> {
+        Ok(self.storage.len()? as u32)
+    }
+
+    // Writes the database image to a new file at `path`, whatever storage it lives in
+    pub fn save_to(&mut self, path: &str) -> Result<(), io::Error> {
+        self.checkpoint()?;
+
+        let mut file_handler = FileHandler::create(path)?;
+        for id in 0..page::page_id(self.eof + PAGE_SIZE - 1) {
+            file_handler.write_page(&self.storage.read_page(id)?)?;
+        }
+        file_handler.truncate(self.eof)?;
+
+        file_handler.sync()
+    }
+
+    // Hands the storage over to the caller, leaving every buffered page written to it
+    pub fn into_storage(mut self) -> Result<Box<dyn StorageBackend>, io::Error> {
+        self.checkpoint()?;
 
-            pager
+        Ok(std::mem::replace(&mut self.storage, Box::new(MemoryBackend::new())))
+    }
+
+    // Replaces the free list with the given holes, writes the new free list and master and then truncates the file
+    // at the end of the page `end` falls in
+    pub fn rebuild_free_list(&mut self, holes: Vec<(u32, usize)>, end: u32) -> Result<(), io::Error> {
+        self.free_list = FreeList::new();
+        for (offset, size) in holes {
+            self.mark_free(size, offset);
         }
+
+        self.eof = page::page_id(end as usize + PAGE_SIZE - 1) as usize * PAGE_SIZE;
+        self.mark_free(self.eof - end as usize, end);
+        self.free_list.condense_free_list();
+
+        self.master.set_free_list_number_items(0);
+        self.master.set_free_list_offset(0);
+        self.master.set_reclaim_list_number_items(0);
+        self.master.set_reclaim_list_offset(0);
+
+        self.free_list_to_file()?;
+        self.master_to_file()?;
+        self.commit()?;
+
+        self.storage.truncate(self.eof)?;
+        self.buffer_pool.discard_from(page::page_id(self.eof));
+
+        Ok(())
     }
 
     pub fn read_from_file(&mut self, offset: usize, size: usize) -> Option<Vec<u8>> {
-        if let Err(_) = self.file_handler.seek_reader(offset) {
-            println!("Failed to seek");
+        // Blocks written since the last flush are only in the write buffer
+        if let Some(bytes) = self.write_buffers.iter().find_map(|write_buffer| write_buffer.block(offset, size)) {
+            return Some(bytes.to_vec());
+        }
+
+        if offset >= self.eof {
             return None;
         }
 
-        let mut bytes: Vec<u8> = vec![0; size];
-        if let Ok(bytes_read) = self.file_handler.read(&mut bytes) {
-            if bytes_read > 0 {
-                Some(bytes)
-            } else {
+        match self.read_pages(offset, size) {
+            Ok(bytes) => Some(bytes),
+            Err(_) => {
+                println!("Failed to read pages");
                 None
             }
-        } else {
-            None
         }
     }
 
@@ -53,94 +161,230 @@ impl Pager {
             self.mark_free(prev_size, prev_offset);
         }
 
-        let offset = if let Some(reclaim_offset) = self.free_list.reclaim_from_free_list(bytes.len()) {
-            if let Some(write_buffer) = self.write_buffers.last_mut() {
-                if write_buffer.offset() + write_buffer.size() == reclaim_offset {   
-                    write_buffer.add_bytes(bytes)                 
-                } else {
-                    self.write_buffers.push(WriteBufferItem::new(bytes, bytes.len(), reclaim_offset));
-                }
-            } else {
-                self.write_buffers.push(WriteBufferItem::new(bytes, bytes.len(), reclaim_offset));
-            }
+        let offset = self.allocate(bytes.len());
+        match self.write_buffers.last_mut() {
+            Some(write_buffer) if write_buffer.offset() + write_buffer.size() == offset => write_buffer.add_bytes(bytes),
+            _ => self.write_buffers.push(WriteBufferItem::new(bytes, bytes.len(), offset)),
+        }
 
-            reclaim_offset as u32
-        } else {
-            if let Some(eof_buffer) = self.eof_buffer {
-                if let Some(write_buffer) = self.write_buffers.get_mut(eof_buffer) {
-                    let offset = write_buffer.offset() + write_buffer.size();
-                    write_buffer.add_bytes(bytes);
-         
-                    offset as u32
-                } else {
-                    println!("Failed to add to EOF buffer");
-                    0
-                }
-            } else {
-                if let Ok(cursor_offset) = self.file_handler.seek_write() {
-                    self.write_buffers.push(WriteBufferItem::new(bytes, bytes.len(), cursor_offset as usize));
-                    self.eof_buffer = Some(self.write_buffers.len() - 1);
+        offset as u32
+    }
 
-                    cursor_offset
-                } else {
-                    println!("Failed to create EOF buffer");
-                    0
-                }
-            }
-        };
+    // Best fit from the free list, or else new pages at the end of the file with whatever the block leaves of the
+    // last one going on the free list. A block that fits in a page always lands within one.
+    fn allocate(&mut self, size: usize) -> usize {
+        if let Some(offset) = self.free_list.reclaim_from_free_list(size) {
+            return offset;
+        }
+
+        let num_pages = size.div_ceil(PAGE_SIZE).max(1);
+        let offset = self.allocate_pages(num_pages) as usize * PAGE_SIZE;
+        self.free_list.add_unused(offset + size, num_pages * PAGE_SIZE - size);
 
         offset
     }
 
-    pub fn flush_write_buffer_trees(&mut self, primary_root: (u32, usize), secondary_index_list: (u32, usize)) {
+    // The file only grows a whole page at a time. Returns the ID of the first new page.
+    fn allocate_pages(&mut self, num_pages: usize) -> u32 {
+        let page_id = page::page_id(self.eof);
+        self.eof += num_pages * PAGE_SIZE;
+
+        page_id
+    }
+
+    pub fn flush_write_buffer_trees(&mut self, primary_root: (u32, usize), secondary_index_list: (u32, usize)) -> Result<(), io::Error> {
         let (primary_root_offset, primary_root_size) = primary_root;
         let (secondary_index_list_offset, secondary_index_list_size) = secondary_index_list;
 
-        for write_buffer in self.write_buffers.iter() {
-            if let Err(_) = self.file_handler.seek_overwrite(write_buffer.offset()) {
-                println!("Failed to write");
-            }
-
-            if let Err(_) = self.file_handler.write(&write_buffer.bytes().to_vec()) {
-                println!("Failed to write");
-            }
-        }
+        self.write_buffers_to_file()?;
 
         self.master.set_primary_root_offset(primary_root_offset);
         self.master.set_primary_root_size(primary_root_size as u32);
         self.master.set_secondary_index_list_offset(secondary_index_list_offset);
         self.master.set_secondary_index_list_size(secondary_index_list_size as u32);
 
-        self.free_list.condense_free_list();
-        self.free_list_to_file();
-        self.master_to_file();
+        self.condense_free_list();
+        self.free_list_to_file()?;
+        self.master_to_file()?;
 
-        self.write_buffers = vec![];
-        self.eof_buffer = None;
+        self.commit()
     }
 
-    pub fn flush_table_columns(&mut self, table_columns: (u32, usize)) {
+    pub fn flush_table_columns(&mut self, table_columns: (u32, usize)) -> Result<(), io::Error> {
         let (table_columns_offset, table_columns_size) = table_columns;
 
-        for write_buffer in self.write_buffers.iter() {
-            if let Err(_) = self.file_handler.seek_overwrite(write_buffer.offset()) {
-                println!("Failed to write");
+        self.write_buffers_to_file()?;
+
+        self.master.set_table_columns_offset(table_columns_offset);
+        self.master.set_table_columns_size(table_columns_size as u32);
+
+        self.condense_free_list();
+        self.free_list_to_file()?;
+        self.master_to_file()?;
+
+        self.commit()
+    }
+
+    // Pins the blocks of the current version until `release_snapshot`: anything a later commit frees is retained
+    // rather than reused. Returns the version to release.
+    pub fn register_snapshot(&mut self) -> u64 {
+        *self.snapshots.entry(self.version).or_insert(0) += 1;
+
+        self.version
+    }
+
+    pub fn release_snapshot(&mut self, version: u64) {
+        if let Some(count) = self.snapshots.get_mut(&version) {
+            *count -= 1;
+            if *count == 0 {
+                self.snapshots.remove(&version);
             }
+        }
+
+        // A block freed by commit v is only visible to snapshots older than v
+        let oldest = self.snapshots.keys().next().copied();
+        self.free_list.release_retained(oldest);
+    }
+
+    // Counts commits since the pager was opened
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn has_snapshots(&self) -> bool {
+        !self.snapshots.is_empty()
+    }
+
+    fn condense_free_list(&mut self) {
+        if self.snapshots.is_empty() {
+            self.free_list.condense_free_list();
+        } else {
+            self.free_list.retain_free_list(self.version + 1);
+        }
+    }
 
-            if let Err(_) = self.file_handler.write(&write_buffer.bytes().to_vec()) {
-                println!("Failed to write");
+    // Writes the buffered pages and syncs them whatever the durability setting
+    pub fn checkpoint(&mut self) -> Result<(), io::Error> {
+        self.buffer_pool.flush(self.storage.as_mut())?;
+        self.storage.sync_data()?;
+        self.commits_since_checkpoint = 0;
+
+        Ok(())
+    }
+
+    // Until `end_batch`, commits are flushed but not synced, whatever the durability setting
+    pub fn begin_batch(&mut self) {
+        self.batching = true;
+    }
+
+    // Syncs the batch's commits together, unless durability is `Off`
+    pub fn end_batch(&mut self) -> Result<(), io::Error> {
+        self.batching = false;
+
+        match self.options.durability() {
+            Durability::Off => Ok(()),
+            _ => self.checkpoint(),
+        }
+    }
+
+    // Writes every dirty page, master's last. Under `Durability::Full` the other pages are synced before master is
+    // written and master is synced after, so after a crash master points either at the old pages or at complete
+    // new ones.
+    fn commit(&mut self) -> Result<(), io::Error> {
+        self.version += 1;
+
+        if self.batching {
+            self.commits_since_checkpoint += 1;
+
+            return self.buffer_pool.flush(self.storage.as_mut());
+        }
+
+        match self.options.durability() {
+            Durability::Full => {
+                self.buffer_pool.flush_data(self.storage.as_mut())?;
+                self.storage.sync_data()?;
+                self.buffer_pool.flush(self.storage.as_mut())?;
+                self.storage.sync_data()
+            },
+            Durability::Normal => {
+                self.buffer_pool.flush(self.storage.as_mut())?;
+                self.commits_since_checkpoint += 1;
+
+                if self.commits_since_checkpoint >= CHECKPOINT_INTERVAL {
+                    self.checkpoint()
+                } else {
+                    Ok(())
+                }
+            },
+            Durability::Off => self.buffer_pool.flush(self.storage.as_mut()),
+        }
+    }
+
+    fn write_buffers_to_file(&mut self) -> Result<(), io::Error> {
+        let write_buffers: Vec<WriteBufferItem> = self.write_buffers.drain(..).collect();
+
+        let writes = write_buffers
+            .iter()
+            .map(|write_buffer| (write_buffer.offset(), write_buffer.bytes()))
+            .collect();
+
+        self.write_pages(writes)
+    }
+
+    // Applies each write to the cached pages it covers. The pages stay dirty in the buffer pool until the next
+    // `flush_buffer_pool` or until they're evicted.
+    fn write_pages(&mut self, writes: Vec<(usize, &[u8])>) -> Result<(), io::Error> {
+        for (offset, bytes) in writes {
+            let mut written = 0;
+            while written < bytes.len() {
+                let page_id = page::page_id(offset + written);
+                let page_offset = (offset + written) % PAGE_SIZE;
+                let len = (PAGE_SIZE - page_offset).min(bytes.len() - written);
+
+                let page = if len == PAGE_SIZE {
+                    self.buffer_pool.pin_new(page_id, self.storage.as_mut())?
+                } else {
+                    self.buffer_pool.pin(page_id, self.storage.as_mut())?
+                };
+                page.write(page_offset, &bytes[written..written + len]);
+                self.buffer_pool.mark_dirty(page_id);
+                self.buffer_pool.unpin(page_id);
+
+                written += len;
             }
         }
 
-        self.master.set_table_columns_offset(table_columns_offset);
-        self.master.set_table_columns_size(table_columns_size as u32);
+        Ok(())
+    }
 
-        self.free_list.condense_free_list();
-        self.free_list_to_file();
-        self.master_to_file();
+    fn read_pages(&mut self, offset: usize, size: usize) -> Result<Vec<u8>, io::Error> {
+        let mut bytes = Vec::with_capacity(size);
 
-        self.write_buffers = vec![];
-        self.eof_buffer = None;
+        while bytes.len() < size {
+            let page_id = page::page_id(offset + bytes.len());
+            let page_offset = (offset + bytes.len()) % PAGE_SIZE;
+            let len = (PAGE_SIZE - page_offset).min(size - bytes.len());
+
+            // Pages in the pool may be dirty, so only pages it doesn't hold come from the map. They're copied like
+            // any other read, since callers parse from the returned bytes.
+            if self.options.read_mode() == ReadMode::Mmap && !self.buffer_pool.contains(page_id) {
+                if let Some(mapped) = self.storage.read_mapped(offset + bytes.len(), len) {
+                    bytes.extend(mapped);
+                    continue;
+                }
+            }
+
+            let page = self.buffer_pool.pin(page_id, self.storage.as_mut())?;
+            bytes.extend(page.read(page_offset, len));
+            self.buffer_pool.unpin(page_id);
+        }
+
+        Ok(bytes)
+    }
+
+
+    pub fn free_list_stats(&self) -> FreeListStats {
+        self.free_list.stats()
     }
 
     pub fn mark_free(&mut self, size: usize, offset: u32) {
@@ -233,176 +477,287 @@ impl Pager {
         }
     }
     
-    fn master_to_file(&mut self) -> u32 {
-        if let Err(_) = self.file_handler.seek_overwrite(0) {
-            println!("Failed to seek");
-        }
-        if let Err(_) = self.file_handler.write(&self.master.data().to_vec()) {
-            println!("Failed to write node");
-        }
-        0
+    fn master_to_file(&mut self) -> Result<(), io::Error> {
+        let master = self.master.data().to_vec();
+
+        self.write_pages(vec![(0, &master)])
     }
 
-    fn free_list_to_file(&mut self) {
-        if self.free_list.free_list_len() <= 0 && self.free_list.reclaim_list_len() <= 0 {
-            return;
+    fn free_list_to_file(&mut self) -> Result<(), io::Error> {
+        if self.free_list.free_list_len() == 0 && self.free_list.reclaim_list_len() == 0 && self.master.free_list_offset() == 0 {
+            return Ok(());
         }
 
-        self.mark_free(self.master.free_list_len() , self.master.free_list_offset());
-        self.mark_free(self.master.reclaim_list_len(), self.master.reclaim_list_offset());
+        if let Some((list_offset, list_size)) = Pager::list_block(&self.master) {
+            self.mark_free(list_size, list_offset);
+        }
+
+        // The lists are allocated like any other block. That can split a free extent in two, so the block has room for
+        // one more item on the reclaim list.
+        let list_size = Pager::list_buffer_len(self.free_list.free_list_len() as u32) + Pager::list_buffer_len(self.free_list.reclaim_list_len() as u32 + 1);
+        let free_list_offset = self.allocate(list_size);
 
+        // The reclaim list ends the block, so any room it didn't need is left between the two lists
         let free_list_buffer = self.free_list_buffer();
         let reclaim_list_buffer = self.reclaim_list_buffer();
-        let free_list_len = free_list_buffer.len();
+        let reclaim_list_offset = free_list_offset + list_size - reclaim_list_buffer.len();
 
-        let free_list_offset = if let Ok(offset) = self.file_handler.seek_write() {
-            offset
-        } else {
-            0
-        };
-
-        if let Err(_) = self.file_handler.write( &(free_list_buffer.into_iter().chain(reclaim_list_buffer).collect())) {
-            println!("Failed to write node");
-        }
+        let mut list_data = free_list_buffer;
+        list_data.resize(list_size - reclaim_list_buffer.len(), 0);
+        list_data.extend(reclaim_list_buffer);
+        self.write_pages(vec![(free_list_offset, &list_data)])?;
 
         self.master.set_free_list_number_items((self.free_list.free_list_len()) as u32);
         self.master.set_reclaim_list_number_items((self.free_list.reclaim_list_len()) as u32);
-        self.master.set_free_list_offset(free_list_offset);
-        self.master.set_reclaim_list_offset(free_list_offset + free_list_len as u32);
+        self.master.set_free_list_offset(free_list_offset as u32);
+        self.master.set_reclaim_list_offset(reclaim_list_offset as u32);
+
+        Ok(())
     }
 
-    fn master_from_file(file_handler: &mut FileHandler) -> Option<Master> {
-        if let Err(_) = file_handler.seek_reader(0) {
-            println!("Failed to seek");
+    // Offset and size of the block holding the free list and the reclaim list after it
+    pub(crate) fn list_block(master: &Master) -> Option<(u32, usize)> {
+        if master.free_list_offset() == 0 {
             return None;
         }
 
-        let mut bytes: Vec<u8> = vec![0; constants::master::MASTER_SIZE as usize];
-        if let Ok(bytes_read) = file_handler.read(&mut bytes) {
-            if bytes_read > 0 { Some(Master::from_bytes(&bytes)) } else {  None }
+        let end = master.reclaim_list_offset() as usize + Pager::list_buffer_len(master.reclaim_list_number_items());
+
+        Some((master.free_list_offset(), end - master.free_list_offset() as usize))
+    }
+
+    fn master_from_file(&mut self) -> Option<Master> {
+        match self.storage.len() {
+            Ok(file_len) if file_len > 0 => (),
+            _ => return None,
+        }
+
+        if let Ok(bytes) = self.read_pages(0, constants::master::MASTER_SIZE) {
+            Some(Master::from_bytes(&bytes))
         } else {
-            return None;
+            println!("Failed to read master");
+            None
         }
     }
 
-    fn free_list_from_file(file_handler: &mut FileHandler, master: &Master) -> Option<FreeList> {
+    // Files grow and shrink a whole page at a time, so new pages go after the last one. An incremental backup is cut
+    // back to its last block, so free extents can run past its end.
+    fn eof_from_file(&mut self) -> usize {
+        let len = self.storage.len().unwrap_or(PAGE_SIZE).max(self.free_list.end());
+
+        page::page_id(len + PAGE_SIZE - 1).max(1) as usize * PAGE_SIZE
+    }
+
+    // Mirrors `free_list_buffer` and `reclaim_list_buffer`: a count followed by the items, padded to ELEMENT_SIZE
+    pub(crate) fn list_buffer_len(number_items: u32) -> usize {
+        let len = 4 + number_items as usize * FREE_ITEM_SIZE;
+
+        len.div_ceil(ELEMENT_SIZE) * ELEMENT_SIZE
+    }
+
+    fn free_list_from_file(&mut self) -> Option<FreeList> {
         // TODO: replace hard-coded 4
-        let free_list_bytes =  if let Ok(_) = file_handler.seek_reader(master.free_list_offset() as usize + 4) {
-            let mut free_list_bytes = vec![0; master.free_list_number_items() as usize * FREE_ITEM_SIZE];
-            if let Ok(_) = file_handler.read(&mut free_list_bytes) {
-                Some(free_list_bytes)
-            } else {
-                None
-            }
-        } else {
-            println!("Failed to seek");
-            None
-        };
+        let free_list_bytes = self.read_pages(
+            self.master.free_list_offset() as usize + 4,
+            self.master.free_list_number_items() as usize * FREE_ITEM_SIZE
+        ).ok();
 
         // TODO: replace hard-coded 4
-        let reclaim_list_bytes =  if let Ok(_) = file_handler.seek_reader(master.reclaim_list_offset() as usize + 4) {
-            let mut reclaim_list_bytes = vec![0; master.reclaim_list_number_items() as usize * FREE_ITEM_SIZE];
-            if let Ok(_) = file_handler.read(&mut reclaim_list_bytes) {
-                Some(reclaim_list_bytes)
-            } else {
-                None
-            }
-        } else {
-            println!("Failed to seek");
-            None
-        };
+        let reclaim_list_bytes = self.read_pages(
+            self.master.reclaim_list_offset() as usize + 4,
+            self.master.reclaim_list_number_items() as usize * FREE_ITEM_SIZE
+        ).ok();
 
         if let (Some(free_list_bytes), Some(reclaim_list_bytes)) = (free_list_bytes, reclaim_list_bytes) {
             return Some(FreeList::from_bytes(free_list_bytes, reclaim_list_bytes));
         }
 
         None
-    } 
+    }
+}
 
-    // pub fn add_to_write_buffer(&mut self, bytes: &[u8], prev_offset: Option<u32>) -> u32 {
-    //     if let Some(prev_offset) = prev_offset {
-    //         self.mark_free(bytes.len(), prev_offset);
-    //     }
-        
-    //     let offset = if let Some(reclaim_offset) = self.free_list.reclaim_from_free_list(bytes.len()) {
-    //         if let Some(write_buffer) = self.write_buffers.last_mut() {
-    //             if write_buffer.offset() + write_buffer.size() == reclaim_offset {   
-    //                 write_buffer.add_bytes(bytes)                 
-    //                 // write_buffer.add_item(WriteBufferItem::new(&bytes, bytes.len()));
-    //             } else {
-    //                 self.write_buffers.push(WriteBufferItem::new(bytes, bytes.len(), reclaim_offset));
-    //                 // self.write_buffers.push(
-    //                 //     WriteBuffer::from_write_buffer_item(WriteBufferItem::new(&bytes, bytes.len()), reclaim_offset)
-    //                 // );
-    //             }
-    //         } else {
-    //             self.write_buffers.push(WriteBufferItem::new(bytes, bytes.len(), reclaim_offset));
-    //             // self.write_buffers.push(
-    //             //     WriteBuffer::from_write_buffer_item(WriteBufferItem::new(&bytes, bytes.len()), reclaim_offset)
-    //             // );
-    //         }
-
-    //         reclaim_offset as u32
-    //     } else {
-    //         if let Some(eof_buffer) = self.eof_buffer {
-    //             if let Some(write_buffer) = self.write_buffers.get_mut(eof_buffer) {
-    //                 let offset = write_buffer.offset() + write_buffer.size();
-    //                 write_buffer.add_bytes(bytes);
-    //                 // write_buffer.add_item(WriteBufferItem::new(&bytes, bytes.len()));
-
-    //                 offset as u32
-    //             } else {
-    //                 println!("Failed to add to EOF buffer");
-    //                 0
-    //             }
-    //         } else {
-    //             if let Ok(cursor_offset) = self.file_handler.seek_write() {
-    //                 self.write_buffers.push(WriteBufferItem::new(bytes, bytes.len(), cursor_offset as usize));
-    //                 // self.write_buffers.push(
-    //                 //     WriteBuffer::from_write_buffer_item(WriteBufferItem::new(&bytes, bytes.len()), cursor_offset as usize)
-    //                 // );
-    //                 self.eof_buffer = Some(self.write_buffers.len() - 1);
-
-    //                 cursor_offset
-    //             } else {
-    //                 println!("Failed to create EOF buffer");
-    //                 0
-    //             }
-    //         }
-
-    //         // if let Ok(cursor_offset) = self.file_handler.seek_write() {
-    //         //     if let Some(write_buffer) = self.write_buffers.last_mut() {
-    //         //         println!("WRITE BUFFER: {:?}", write_buffer);
-    //         //         if write_buffer.offset() == cursor_offset as usize {
-
-    //         //             let offset = cursor_offset + write_buffer.size() as u32;
-    //         //             write_buffer.add_item(WriteBufferItem::new(&bytes, bytes.len()));
-
-    //         //             offset
-    //         //         } else {
-    //         //             println!("MISTAKE?");
-    //         //             self.write_buffers.push(
-    //         //                 WriteBuffer::from_write_buffer_item(WriteBufferItem::new(&bytes, bytes.len()), cursor_offset as usize)
-    //         //             );
-
-    //         //             cursor_offset
-    //         //         }
-    //         //     } else {
-    //         //         self.write_buffers.push(
-    //         //             WriteBuffer::from_write_buffer_item(WriteBufferItem::new(&bytes, bytes.len()), cursor_offset as usize)
-    //         //         );
-
-    //         //         cursor_offset
-    //         //     }
-    //         // } else {
-    //         //     self.write_buffers.push(
-    //         //         WriteBuffer::from_write_buffer_item(WriteBufferItem::new(&bytes, bytes.len()), 0)
-    //         //     );
-
-    //         //     0
-    //         // }
-    //     };
-
-    //     offset
-    // }
-}
\ No newline at end of file
+impl Drop for Pager {
+    fn drop(&mut self) {
+        if self.commits_since_checkpoint > 0 && self.checkpoint().is_err() {
+            println!("Failed to checkpoint");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io, sync::{Arc, Mutex}};
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::db::{file_stystem::{fault_injecting_backend::FaultInjectingBackend, memory_backend::MemoryBackend, page, storage::StorageBackend}, options::OpenOptions, shared::{constants::{durability::CHECKPOINT_INTERVAL, page::PAGE_SIZE}, enums::{Durability, Fault}}};
+
+    use super::Pager;
+
+    // Lets the test count the syncs of a backend the pager owns
+    #[derive(Debug, Clone)]
+    struct SharedBackend(Arc<Mutex<FaultInjectingBackend<MemoryBackend>>>);
+
+    impl SharedBackend {
+        fn syncs(&self) -> usize {
+            self.0.lock().unwrap().syncs()
+        }
+    }
+
+    impl StorageBackend for SharedBackend {
+        fn read_at(&mut self, offset: usize, buf: &mut [u8]) -> Result<usize, io::Error> {
+            self.0.lock().unwrap().read_at(offset, buf)
+        }
+
+        fn write_at(&mut self, offset: usize, bytes: &[u8]) -> Result<(), io::Error> {
+            self.0.lock().unwrap().write_at(offset, bytes)
+        }
+
+        fn len(&mut self) -> Result<usize, io::Error> {
+            self.0.lock().unwrap().len()
+        }
+
+        fn truncate(&mut self, len: usize) -> Result<(), io::Error> {
+            self.0.lock().unwrap().truncate(len)
+        }
+
+        fn sync(&mut self) -> Result<(), io::Error> {
+            self.0.lock().unwrap().sync()
+        }
+
+        fn sync_data(&mut self) -> Result<(), io::Error> {
+            self.0.lock().unwrap().sync_data()
+        }
+    }
+
+    // A pager with `durability` and the backend it writes to, with the syncs of creating the file behind it
+    fn pager(durability: Durability) -> (Pager, SharedBackend) {
+        let backend = SharedBackend(Arc::new(Mutex::new(FaultInjectingBackend::new(MemoryBackend::new()))));
+        let pager = Pager::with_storage(None, Box::new(backend.clone()), OpenOptions::new().set_durability(durability));
+
+        (pager, backend)
+    }
+
+    // Returns how many syncs `commits` commits took
+    fn commit(pager: &mut Pager, backend: &SharedBackend, commits: usize) -> usize {
+        let syncs = backend.syncs();
+        for _ in 0..commits {
+            pager.add_to_write_buffer(&[1u8; 100], None);
+            pager.flush_table_columns((0, 0)).unwrap();
+        }
+
+        backend.syncs() - syncs
+    }
+
+    #[test]
+    fn full_durability_syncs_before_and_after_master() {
+        let (mut pager, backend) = pager(Durability::Full);
+
+        assert_eq!(commit(&mut pager, &backend, 1), 2);
+        assert_eq!(commit(&mut pager, &backend, 3), 6);
+    }
+
+    #[test]
+    fn normal_durability_syncs_at_checkpoints() {
+        let (mut pager, backend) = pager(Durability::Normal);
+
+        // Writing the new file's master was the first commit
+        assert_eq!(commit(&mut pager, &backend, CHECKPOINT_INTERVAL - 2), 0);
+        assert_eq!(commit(&mut pager, &backend, 1), 1);
+        assert_eq!(commit(&mut pager, &backend, 1), 0);
+
+        // Closing checkpoints whatever is left
+        drop(pager);
+        assert_eq!(backend.syncs(), 2);
+    }
+
+    #[test]
+    fn off_durability_never_syncs_on_commit() {
+        let (mut pager, backend) = pager(Durability::Off);
+
+        assert_eq!(commit(&mut pager, &backend, CHECKPOINT_INTERVAL * 2), 0);
+        pager.checkpoint().unwrap();
+        assert_eq!(backend.syncs(), 1);
+    }
+
+    #[test]
+    fn failed_syncs_are_returned_from_the_commit() {
+        let (mut pager, backend) = pager(Durability::Full);
+        let next_sync = backend.syncs() + 1;
+        backend.0.lock().unwrap().inject(Fault::FailSync(next_sync));
+
+        pager.add_to_write_buffer(&[1u8; 100], None);
+        assert!(pager.flush_table_columns((0, 0)).is_err());
+    }
+
+    #[test]
+    fn batches_sync_once_when_they_end() {
+        for (durability, syncs) in [(Durability::Full, 1), (Durability::Normal, 1), (Durability::Off, 0)] {
+            let (mut pager, backend) = pager(durability);
+
+            pager.begin_batch();
+            assert_eq!(commit(&mut pager, &backend, 5), 0, "{:?}", durability);
+            let before = backend.syncs();
+            pager.end_batch().unwrap();
+            assert_eq!(backend.syncs() - before, syncs, "{:?}", durability);
+        }
+    }
+
+    fn assert_within_a_page(offset: u32, size: usize) {
+        let offset = offset as usize;
+
+        if size <= PAGE_SIZE {
+            assert_eq!(page::page_id(offset), page::page_id(offset + size - 1), "block {:?} straddles a page", (offset, size));
+        } else {
+            assert_eq!(offset % PAGE_SIZE, 0, "block {:?} doesn't start a page", (offset, size));
+        }
+    }
+
+    #[test]
+    fn allocated_blocks_stay_within_a_page() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut pager = Pager::in_memory(&OpenOptions::new());
+        let mut live: Vec<(u32, usize)> = vec![];
+
+        for i in 0..1_000 {
+            let size = rng.gen_range(1..PAGE_SIZE * 2);
+            let prev = if !live.is_empty() && rng.gen_bool(0.4) {
+                Some(live.swap_remove(rng.gen_range(0..live.len())))
+            } else {
+                None
+            };
+
+            let offset = pager.add_to_write_buffer(&vec![1u8; size], prev);
+            assert_within_a_page(offset, size);
+            live.push((offset, size));
+
+            if i % 10 == 0 {
+                pager.flush_table_columns((0, 0)).unwrap();
+                assert_eq!(pager.file_len().unwrap() as usize % PAGE_SIZE, 0);
+
+                let (list_offset, list_size) = Pager::list_block(&pager.master).unwrap();
+                assert_within_a_page(list_offset, list_size);
+            }
+        }
+    }
+
+    #[test]
+    fn files_are_truncated_on_a_page_boundary() {
+        let mut pager = Pager::in_memory(&OpenOptions::new());
+        for _ in 0..20 {
+            pager.add_to_write_buffer(&[1u8; 1_000], None);
+        }
+        pager.flush_table_columns((0, 0)).unwrap();
+
+        pager.rebuild_free_list(vec![], PAGE_SIZE as u32 + 10).unwrap();
+        let len = pager.file_len().unwrap() as usize;
+        assert_eq!(len % PAGE_SIZE, 0);
+        assert!(len < PAGE_SIZE * 5);
+
+        // The rest of the page the data ends in is free, so small blocks and the lists don't grow the file
+        let offset = pager.add_to_write_buffer(&[1u8; 100], None) as usize;
+        pager.flush_table_columns((0, 0)).unwrap();
+        assert!(offset < len);
+        assert_eq!(pager.file_len().unwrap() as usize, len);
+    }
+}
@@ -0,0 +1,49 @@
+use std::{fmt::Debug, io};
+
+use crate::db::{file_stystem::page::Page, shared::constants::page::PAGE_SIZE};
+
+// Byte-addressed storage the pager sits on. Reads past the end come back short and writes past the end grow it,
+// so the pager never has to care whether a page exists yet.
+pub trait StorageBackend: Debug + Send {
+    // Fills as much of `buf` as there is data for and returns how many bytes that was
+    fn read_at(&mut self, offset: usize, buf: &mut [u8]) -> Result<usize, io::Error>;
+
+    fn write_at(&mut self, offset: usize, bytes: &[u8]) -> Result<(), io::Error>;
+
+    fn len(&mut self) -> Result<usize, io::Error>;
+
+    fn truncate(&mut self, len: usize) -> Result<(), io::Error>;
+
+    fn sync(&mut self) -> Result<(), io::Error>;
+
+    // Backends that can skip syncing metadata on commit override this
+    fn sync_data(&mut self) -> Result<(), io::Error> {
+        self.sync()
+    }
+
+    // Backends that can't hand out their bytes directly keep the pager on buffered reads
+    fn map(&mut self) -> Result<(), io::Error> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "Storage can't be mapped"))
+    }
+
+    fn read_mapped(&mut self, _offset: usize, _size: usize) -> Option<&[u8]> {
+        None
+    }
+}
+
+// The page-sized view of a backend that the buffer pool works in
+pub trait Storage: StorageBackend {
+    // Bytes past the end read as zeros, so the last page of storage that isn't page aligned is still whole
+    fn read_page(&mut self, id: u32) -> Result<Page, io::Error> {
+        let mut bytes = vec![0u8; PAGE_SIZE];
+        self.read_at(id as usize * PAGE_SIZE, &mut bytes)?;
+
+        Ok(Page::from_bytes(id, &bytes))
+    }
+
+    fn write_page(&mut self, page: &Page) -> Result<(), io::Error> {
+        self.write_at(page.offset(), page.data())
+    }
+}
+
+impl<B: StorageBackend + ?Sized> Storage for B {}
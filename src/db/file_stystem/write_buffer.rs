@@ -26,6 +26,12 @@ impl WriteBufferItem {
     pub fn offset(&self) -> usize {
         self.offset
     }
+
+    // The block at `offset`, if it's one of this item's
+    pub fn block(&self, offset: usize, size: usize) -> Option<&[u8]> {
+        let start = offset.checked_sub(self.offset)?;
+        self.bytes.get(start..start + size)
+    }
 }
 
 // TODO: delete this
@@ -0,0 +1,37 @@
+use std::fmt;
+
+use super::{db::PocketDB, table::disk_storage::row::Row};
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub errors: Vec<RowError>,
+}
+
+// A row that wasn't imported. `line` is where its record starts in the input.
+#[derive(Debug)]
+pub struct RowError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl ImportReport {
+    // Inserts the parsed rows, given as (line, key, row), and records the ones the database turns down
+    pub(crate) fn insert_batch(&mut self, db: &PocketDB, batch: &mut Vec<(usize, u32, Row)>) {
+        let lines: Vec<usize> = batch.iter().map(|(line, _, _)| *line).collect();
+        let rows = batch.drain(..).map(|(_, key, row)| (key, row)).collect();
+
+        for (line, result) in lines.into_iter().zip(db.insert_batch(rows)) {
+            match result {
+                Ok(()) => self.imported += 1,
+                Err(message) => self.errors.push(RowError { line, message }),
+            }
+        }
+    }
+}
@@ -0,0 +1,509 @@
+use std::{io::{BufRead, BufReader, BufWriter, Read, Write}, iter::Peekable, str::Chars};
+
+use super::{db::PocketDB, import::{ImportReport, RowError}, options::JsonOptions, shared::enums::{ColumnType, DataType}, table::disk_storage::row::Row};
+
+// A parsed JSON value. Numbers keep their text, so nothing is lost to floating point before a column type is picked.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(String),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    pub fn parse(text: &str) -> Result<JsonValue, String> {
+        let mut parser = JsonParser { chars: text.chars().peekable() };
+        let value = parser.value()?;
+
+        parser.skip_whitespace();
+        if parser.chars.peek().is_some() {
+            return Err(String::from("Unexpected characters after JSON value"));
+        }
+
+        Ok(value)
+    }
+
+    pub fn to_json(&self) -> String {
+        match self {
+            JsonValue::Null => String::from("null"),
+            JsonValue::Bool(value) => value.to_string(),
+            JsonValue::Number(value) => value.clone(),
+            JsonValue::String(value) => JsonValue::quote(value),
+            JsonValue::Array(values) => {
+                let values: Vec<String> = values.iter().map(|value| value.to_json()).collect();
+                format!("[{}]", values.join(","))
+            },
+            JsonValue::Object(fields) => {
+                let fields: Vec<String> = fields
+                    .iter()
+                    .map(|(name, value)| format!("{}:{}", JsonValue::quote(name), value.to_json()))
+                    .collect();
+                format!("{{{}}}", fields.join(","))
+            },
+        }
+    }
+
+    fn quote(value: &str) -> String {
+        let mut quoted = String::from("\"");
+        for c in value.chars() {
+            match c {
+                '"' => quoted.push_str("\\\""),
+                '\\' => quoted.push_str("\\\\"),
+                '\n' => quoted.push_str("\\n"),
+                '\r' => quoted.push_str("\\r"),
+                '\t' => quoted.push_str("\\t"),
+                c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('"');
+
+        quoted
+    }
+}
+
+struct JsonParser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl JsonParser<'_> {
+    fn value(&mut self) -> Result<JsonValue, String> {
+        self.skip_whitespace();
+
+        match self.chars.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => self.string().map(JsonValue::String),
+            Some('t') => self.literal("true", JsonValue::Bool(true)),
+            Some('f') => self.literal("false", JsonValue::Bool(false)),
+            Some('n') => self.literal("null", JsonValue::Null),
+            Some(c) if *c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(format!("Unexpected character {:?}", c)),
+            None => Err(String::from("Unexpected end of JSON")),
+        }
+    }
+
+    fn object(&mut self) -> Result<JsonValue, String> {
+        self.chars.next();
+        let mut fields = vec![];
+
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&'}').is_some() {
+            return Ok(JsonValue::Object(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+            let name = self.string()?;
+
+            self.skip_whitespace();
+            self.expect(':')?;
+            fields.push((name, self.value()?));
+
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some('}') => return Ok(JsonValue::Object(fields)),
+                _ => return Err(String::from("Expected , or } in object")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<JsonValue, String> {
+        self.chars.next();
+        let mut values = vec![];
+
+        self.skip_whitespace();
+        if self.chars.next_if_eq(&']').is_some() {
+            return Ok(JsonValue::Array(values));
+        }
+
+        loop {
+            values.push(self.value()?);
+
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some(',') => continue,
+                Some(']') => return Ok(JsonValue::Array(values)),
+                _ => return Err(String::from("Expected , or ] in array")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut value = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some('"') => return Ok(value),
+                Some('\\') => match self.chars.next() {
+                    Some('"') => value.push('"'),
+                    Some('\\') => value.push('\\'),
+                    Some('/') => value.push('/'),
+                    Some('b') => value.push('\u{8}'),
+                    Some('f') => value.push('\u{c}'),
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some('u') => value.push(self.unicode_escape()?),
+                    _ => return Err(String::from("Invalid escape in string")),
+                },
+                Some(c) => value.push(c),
+                None => return Err(String::from("Unterminated string")),
+            }
+        }
+    }
+
+    // Characters outside the basic plane come as a surrogate pair of escapes
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        if !(0xd800..0xdc00).contains(&high) {
+            return char::from_u32(high).ok_or(String::from("Invalid unicode escape"));
+        }
+
+        if self.chars.next() != Some('\\') || self.chars.next() != Some('u') {
+            return Err(String::from("Unpaired surrogate in string"));
+        }
+        let low = self.hex4()?;
+        if !(0xdc00..0xe000).contains(&low) {
+            return Err(String::from("Unpaired surrogate in string"));
+        }
+
+        char::from_u32(0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)).ok_or(String::from("Invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let hex: String = (0..4).filter_map(|_| self.chars.next()).collect();
+
+        u32::from_str_radix(&hex, 16).map_err(|_| String::from("Invalid unicode escape"))
+    }
+
+    fn number(&mut self) -> Result<JsonValue, String> {
+        let mut number = String::new();
+        while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+            number.push(c);
+        }
+
+        if number.parse::<f64>().is_err() {
+            return Err(format!("Invalid number {}", number));
+        }
+
+        Ok(JsonValue::Number(number))
+    }
+
+    fn literal(&mut self, literal: &str, value: JsonValue) -> Result<JsonValue, String> {
+        for expected in literal.chars() {
+            if self.chars.next() != Some(expected) {
+                return Err(format!("Expected {}", literal));
+            }
+        }
+
+        Ok(value)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.chars.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(format!("Expected {}", expected)),
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+}
+
+// Rows as JSON objects keyed by column name, key included. Importing into a database with no columns yet infers
+// them from a sample of the records first.
+#[derive(Debug)]
+pub struct Json;
+
+impl Json {
+    pub fn import_ndjson(db: &PocketDB, reader: impl Read, options: &JsonOptions) -> Result<ImportReport, String> {
+        let mut records = NdjsonRecords { lines: BufReader::new(reader).lines(), line: 0 };
+
+        let mut columns = db.columns();
+        let mut sample = vec![];
+        if columns.len() == 1 && db.snapshot().rows().next().is_none() {
+            while sample.len() < options.sample_size() {
+                match records.next() {
+                    Some(record) => sample.push(record),
+                    None => break,
+                }
+            }
+
+            let fields: Vec<&Vec<(String, JsonValue)>> = sample.iter().filter_map(|record| record.as_ref().ok()).map(|(_, fields)| fields).collect();
+            for (name, column_type) in Json::infer_columns(&fields, &columns[0].0)? {
+                if options.indexed_columns().contains(&name) {
+                    db.add_indexed_column(&name, column_type)?;
+                } else {
+                    db.add_column(&name, column_type)?;
+                }
+            }
+            columns = db.columns();
+        }
+        if let Some(name) = options.indexed_columns().iter().find(|name| !columns.iter().any(|(column, _)| column == *name)) {
+            return Err(format!("No column named {} to index", name));
+        }
+
+        let mut import = ImportReport::default();
+        let mut batch = vec![];
+        for record in sample.into_iter().chain(records) {
+            let row = record.and_then(|(line, fields)| {
+                Json::row(&fields, &columns)
+                    .map(|(key, row)| (line, key, row))
+                    .map_err(|message| RowError { line, message })
+            });
+
+            match row {
+                Ok(row) => batch.push(row),
+                Err(e) => import.errors.push(e),
+            }
+
+            if batch.len() >= options.batch_size() {
+                import.insert_batch(db, &mut batch);
+            }
+        }
+        import.insert_batch(db, &mut batch);
+
+        Ok(import)
+    }
+
+    // A JSON array of every row in primary-key order, one object per line. Returns the number of rows.
+    pub fn export_json(db: &PocketDB, writer: impl Write) -> Result<usize, String> {
+        Json::export(db, writer, false)
+    }
+
+    // One object per line and nothing else, in primary-key order. Returns the number of rows.
+    pub fn export_ndjson(db: &PocketDB, writer: impl Write) -> Result<usize, String> {
+        Json::export(db, writer, true)
+    }
+
+    fn export(db: &PocketDB, writer: impl Write, ndjson: bool) -> Result<usize, String> {
+        let snapshot = db.snapshot();
+        let columns = snapshot.columns();
+        let mut writer = BufWriter::new(writer);
+        let write_error = |e: std::io::Error| format!("Failed to write JSON: {}", e);
+
+        if !ndjson {
+            write!(writer, "[").map_err(write_error)?;
+        }
+
+        let mut exported = 0;
+        for row in snapshot.rows() {
+            // Cell 0 is the row head, so the key is cell 1 like its column
+            let fields = columns
+                .iter()
+                .zip(row.into_iter().skip(1))
+                .map(|((name, _), data)| {
+                    let value = match data {
+                        DataType::Integer(value) => JsonValue::Number(value.to_string()),
+                        DataType::Text(value) => JsonValue::String(value),
+                        DataType::Bool(value) => JsonValue::Bool(value),
+                        DataType::Meta => JsonValue::Null,
+                    };
+                    (name.clone(), value)
+                })
+                .collect();
+            let object = JsonValue::Object(fields).to_json();
+
+            if ndjson {
+                writeln!(writer, "{}", object)
+            } else if exported == 0 {
+                write!(writer, "\n{}", object)
+            } else {
+                write!(writer, ",\n{}", object)
+            }.map_err(write_error)?;
+            exported += 1;
+        }
+
+        if !ndjson {
+            writeln!(writer, "\n]").map_err(write_error)?;
+        }
+        writer.flush().map_err(write_error)?;
+
+        Ok(exported)
+    }
+
+    // Columns in the order their fields first turn up. Whole numbers that fit a u32 are Integer, true and false are
+    // Bool, and everything else, or a field whose values disagree, is Text. Nulls don't count either way.
+    fn infer_columns(records: &[&Vec<(String, JsonValue)>], key_name: &str) -> Result<Vec<(String, ColumnType)>, String> {
+        let mut columns: Vec<(String, Option<ColumnType>)> = vec![];
+        let mut has_key = false;
+
+        for (name, value) in records.iter().flat_map(|fields| fields.iter()) {
+            if name == key_name {
+                has_key = true;
+                continue;
+            }
+
+            let value_type = match value {
+                JsonValue::Null => None,
+                JsonValue::Bool(_) => Some(ColumnType::Bool),
+                JsonValue::Number(number) if number.parse::<u32>().is_ok() => Some(ColumnType::Integer),
+                _ => Some(ColumnType::Text),
+            };
+
+            if let Some((_, column_type)) = columns.iter_mut().find(|(column, _)| column == name) {
+                *column_type = match (column_type.take(), value_type) {
+                    (None, value_type) => value_type,
+                    (column_type, None) => column_type,
+                    (Some(column_type), Some(value_type)) if column_type == value_type => Some(column_type),
+                    _ => Some(ColumnType::Text),
+                };
+            } else {
+                columns.push((name.clone(), value_type));
+            }
+        }
+
+        if !has_key {
+            return Err(format!("Records have no {} field", key_name));
+        }
+
+        Ok(columns
+            .into_iter()
+            .map(|(name, column_type)| (name, column_type.unwrap_or(ColumnType::Text)))
+            .collect())
+    }
+
+    fn row(fields: &[(String, JsonValue)], columns: &[(String, ColumnType)]) -> Result<(u32, Row), String> {
+        if let Some((name, _)) = fields.iter().find(|(name, _)| !columns.iter().any(|(column, _)| column == name)) {
+            return Err(format!("No column named {}", name));
+        }
+
+        let mut cells = vec![];
+        for (name, column_type) in columns {
+            let value = fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, value)| value)
+                .filter(|value| **value != JsonValue::Null)
+                .ok_or(format!("Missing value for column {}", name))?;
+
+            cells.push(Json::data(value, name, column_type)?);
+        }
+
+        let key = match cells.remove(0) {
+            DataType::Integer(key) => key,
+            _ => return Err(String::from("Key column isn't an integer")),
+        };
+
+        Ok((key, PocketDB::row(key, cells.into_iter().map(PocketDB::cell).collect())))
+    }
+
+    // Text columns take any value, keeping the JSON of anything that isn't a string
+    fn data(value: &JsonValue, name: &str, column_type: &ColumnType) -> Result<DataType, String> {
+        match (column_type, value) {
+            (ColumnType::Integer, JsonValue::Number(number)) => number
+                .parse()
+                .map(DataType::Integer)
+                .map_err(|_| format!("Invalid integer {} for column {}", number, name)),
+            (ColumnType::Bool, JsonValue::Bool(value)) => Ok(DataType::Bool(*value)),
+            (ColumnType::Text, JsonValue::String(value)) => Ok(DataType::Text(value.clone())),
+            (ColumnType::Text, value) => Ok(DataType::Text(value.to_json())),
+            (column_type, value) => Err(format!("Invalid {:?} {} for column {}", column_type, value.to_json(), name)),
+        }
+    }
+}
+
+// One JSON object per line; blank lines are skipped
+struct NdjsonRecords<R: BufRead> {
+    lines: std::io::Lines<R>,
+    line: usize,
+}
+
+impl<R: BufRead> Iterator for NdjsonRecords<R> {
+    type Item = Result<(usize, Vec<(String, JsonValue)>), RowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let text = match self.lines.next()? {
+                Ok(text) => text,
+                Err(e) => {
+                    self.line += 1;
+                    return Some(Err(RowError { line: self.line, message: format!("Failed to read NDJSON: {}", e) }));
+                },
+            };
+            self.line += 1;
+
+            if text.trim().is_empty() {
+                continue;
+            }
+
+            let record = match JsonValue::parse(&text) {
+                Ok(JsonValue::Object(fields)) => Ok((self.line, fields)),
+                Ok(_) => Err(RowError { line: self.line, message: String::from("Expected a JSON object") }),
+                Err(message) => Err(RowError { line: self.line, message }),
+            };
+
+            return Some(record);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{db::PocketDB, options::JsonOptions, shared::enums::{ColumnType, DataType}};
+
+    use super::JsonValue;
+
+    #[test]
+    fn import_infers_a_schema_and_export_round_trips() {
+        let db = PocketDB::in_memory();
+        let ndjson = r#"{"key": 2, "name": "Alex \"A\" é", "age": 30, "active": true, "tags": ["a"]}
+{"key": 1, "name": "Sam", "age": null, "active": false, "tags": "none"}
+
+{"key": 3, "name": "Jo", "age": 41, "active": 1, "tags": "x"}
+[1, 2]
+{"key": 4, "name": "Lee", "age": 25, "active": false, "tags": "y", "extra": 0}
+{"key": 5, "name": "Kim", "age": 25, "active": true, "tags": {}}
+"#;
+        let mut options = JsonOptions::new();
+        options.set_sample_size(2).set_batch_size(2).set_indexed_columns(&["name"]);
+        let import = db.import_ndjson(ndjson.as_bytes(), &options).unwrap();
+
+        assert_eq!(db.columns(), vec![
+            (String::from("key"), ColumnType::Integer),
+            (String::from("name"), ColumnType::Text),
+            (String::from("age"), ColumnType::Integer),
+            (String::from("active"), ColumnType::Bool),
+            (String::from("tags"), ColumnType::Text),
+        ]);
+        assert_eq!(import.imported, 2);
+        let errors: Vec<String> = import.errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(errors, vec![
+            "line 2: Missing value for column age",
+            "line 4: Invalid Bool 1 for column active",
+            "line 5: Expected a JSON object",
+            "line 6: No column named extra",
+        ]);
+        assert!(db.search_by_secondary_index(DataType::Text(String::from("Kim")), "name").is_some());
+
+        let mut exported = vec![];
+        assert_eq!(db.export_json(&mut exported).unwrap(), 2);
+        let exported = String::from_utf8(exported).unwrap();
+        assert_eq!(exported, "[\n\
+            {\"key\":2,\"name\":\"Alex \\\"A\\\" \u{e9}\",\"age\":30,\"active\":true,\"tags\":\"[\\\"a\\\"]\"},\n\
+            {\"key\":5,\"name\":\"Kim\",\"age\":25,\"active\":true,\"tags\":\"{}\"}\n\
+            ]\n");
+        assert!(JsonValue::parse(&exported).is_ok());
+
+        let copy = PocketDB::in_memory();
+        let mut ndjson = vec![];
+        db.export_ndjson(&mut ndjson).unwrap();
+        assert_eq!(copy.import_ndjson(ndjson.as_slice(), &JsonOptions::new()).unwrap().imported, 2);
+        assert_eq!(copy.columns(), db.columns());
+    }
+
+    #[test]
+    fn parse_rejects_malformed_json() {
+        assert_eq!(JsonValue::parse(r#""😀""#), Ok(JsonValue::String(String::from("\u{1f600}"))));
+        for text in [r#"{"a" 1}"#, "[1,]", "tru", r#""open"#, "1 2", r#""\ud83d""#] {
+            assert!(JsonValue::parse(text).is_err(), "parsed {}", text);
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::{collections::HashMap, fs};
+
+use crate::db::{file_stystem::{file_handler::FileHandler, pager::Pager, storage::StorageBackend}, meta::{disk_storage::master::Master, vacuum::{BlockSource, BlockWalker, Vacuum}}, shared::constants::{master::MASTER_SIZE, page::PAGE_SIZE}, snapshot::Snapshot};
+
+// Copies a snapshot out to a backup file. Blocks are read through the snapshot, so writers keep committing while a
+// backup runs and the blocks it copies stay put until it's done.
+#[derive(Debug)]
+pub struct Backup;
+
+impl Backup {
+    // Writes only the blocks reachable from the snapshot's master, back-to-back like a vacuum. The copy is built next
+    // to `path` and renamed over it, so an earlier backup there stays whole until the new one is.
+    pub fn backup_to(snapshot: &Snapshot, path: &str) -> Result<(), String> {
+        let master = Master::from_bytes(snapshot.master().data());
+        let mut source = snapshot;
+        let staged_path = format!("{}.backup", path);
+        let file_handler = FileHandler::create(&staged_path).map_err(|e| format!("Failed to create {}: {}", staged_path, e))?;
+
+        let storage = Vacuum::from_source(&mut source, &master).compact_into(&mut source, &master, Box::new(file_handler), Some(&staged_path))?;
+        drop(storage);
+
+        fs::rename(&staged_path, path).map_err(|e| format!("Failed to replace {}: {}", path, e))
+    }
+
+    // Keeps every block at its offset in the database, so the backup at `path` only needs the blocks that differ from
+    // what's already there, and none at all if its master matches. Unlike `backup_to` this works in place: blocks the
+    // old backup needed are overwritten before the new master, so an interrupted run has to be finished before the
+    // backup can be restored. Returns the number of bytes written.
+    pub fn incremental_backup_to(snapshot: &Snapshot, path: &str) -> Result<usize, String> {
+        let master = Master::from_bytes(snapshot.master().data());
+        let mut source = snapshot;
+        let mut backup = FileHandler::open(path, false, None).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+
+        let mut backup_master = vec![0u8; MASTER_SIZE];
+        let read = backup.read_at(0, &mut backup_master).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if read == MASTER_SIZE && backup_master == master.data() {
+            return Ok(0);
+        }
+
+        let mut written = 0;
+        let mut end = MASTER_SIZE;
+        let mut write_error = None;
+        let mut copy_block = |offset: u32, bytes: &[u8]| {
+            end = end.max(offset as usize + bytes.len());
+            if write_error.is_some() {
+                return;
+            }
+
+            let mut backup_bytes = vec![0u8; bytes.len()];
+            let unchanged = backup.read_at(offset as usize, &mut backup_bytes)
+                .map(|read| read == bytes.len() && backup_bytes == bytes);
+            let copied = match unchanged {
+                Ok(true) => Ok(()),
+                Ok(false) => backup.write_at(offset as usize, bytes).map(|_| written += bytes.len()),
+                Err(e) => Err(e),
+            };
+            if let Err(e) = copied {
+                write_error = Some(e);
+            }
+        };
+
+        let relocations = HashMap::new();
+        BlockWalker::walk(&mut source, &master, &relocations, &mut |offset, bytes, _| copy_block(offset, bytes));
+
+        if let Some((list_offset, list_size)) = Pager::list_block(&master) {
+            if let Some(bytes) = source.read_block(list_offset, list_size) {
+                copy_block(list_offset, &bytes);
+            }
+        }
+
+        if let Some(e) = write_error {
+            return Err(format!("Failed to write {}: {}", path, e));
+        }
+
+        backup.truncate(end)
+            .and_then(|_| backup.sync())
+            .and_then(|_| backup.write_at(0, master.data()))
+            .and_then(|_| backup.sync())
+            .map_err(|e| format!("Failed to write {}: {}", path, e))?;
+
+        Ok(written + MASTER_SIZE)
+    }
+
+    // Replaces the database with the backup at `path`, full or incremental. It's copied into new storage first and
+    // swapped in like a vacuum, so a failed restore leaves the database as it was.
+    pub fn restore_from(pager: &mut Pager, path: &str) -> Result<(), String> {
+        let mut backup = FileHandler::open(path, true, None).map_err(|e| format!("Failed to open {}: {}", path, e))?;
+        let len = backup.len().map_err(|e| format!("Failed to read {}: {}", path, e))?;
+        if len < MASTER_SIZE {
+            return Err(format!("{} is not a database backup", path));
+        }
+
+        let (mut storage, staged_path) = Vacuum::stage(pager, "restore")?;
+        let mut offset = 0;
+        while offset < len {
+            let mut bytes = vec![0u8; PAGE_SIZE.min(len - offset)];
+            backup.read_at(offset, &mut bytes)
+                .and_then(|_| storage.write_at(offset, &bytes))
+                .map_err(|e| format!("Failed to copy {}: {}", path, e))?;
+            offset += bytes.len();
+        }
+        storage.sync().map_err(|e| format!("Failed to copy {}: {}", path, e))?;
+
+        Vacuum::swap_in(pager, storage, staged_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, sync::Arc, thread};
+
+    use crate::db::{db::PocketDB, shared::enums::{ColumnType, DataType}};
+
+    fn insert(db: &PocketDB, key: u32) {
+        db.insert(key, PocketDB::row(key, vec![
+            PocketDB::cell(DataType::Text(format!("name {}", key % 5))),
+            PocketDB::cell(DataType::Integer(key)),
+        ])).unwrap();
+    }
+
+    fn has_rows(db: &PocketDB, keys: std::ops::RangeInclusive<u32>) -> bool {
+        keys.into_iter().all(|key| db.search_by_primary_index(DataType::Integer(key)).is_some())
+    }
+
+    #[test]
+    fn backups_restore_the_database_they_were_taken_from() {
+        let full_path = env::temp_dir().join(format!("pocketdb-backup-full-{}.db", std::process::id()));
+        let incremental_path = env::temp_dir().join(format!("pocketdb-backup-incremental-{}.db", std::process::id()));
+        let (full_path, incremental_path) = (full_path.to_str().unwrap(), incremental_path.to_str().unwrap());
+        let _ = fs::remove_file(incremental_path);
+
+        let db = Arc::new(PocketDB::in_memory());
+        db.add_indexed_column("name", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+        for key in 1..=50 {
+            insert(&db, key);
+        }
+
+        let writer = {
+            let db = Arc::clone(&db);
+            thread::spawn(move || (51..=100).for_each(|key| insert(&db, key)))
+        };
+        db.backup_to(full_path).unwrap();
+        writer.join().unwrap();
+
+        assert!(db.incremental_backup_to(incremental_path).unwrap() > 0);
+        assert_eq!(db.incremental_backup_to(incremental_path).unwrap(), 0);
+        insert(&db, 101);
+        let written = db.incremental_backup_to(incremental_path).unwrap();
+        assert!(written > 0 && written < fs::metadata(incremental_path).unwrap().len() as usize);
+
+        db.restore_from(full_path).unwrap();
+        assert!(has_rows(&db, 1..=50));
+        assert!(db.search_by_primary_index(DataType::Integer(101)).is_none());
+
+        db.restore_from(incremental_path).unwrap();
+        assert!(has_rows(&db, 1..=101));
+        assert_eq!(db.search_by_secondary_index(DataType::Text(String::from("name 1")), "name").map(|rows| rows.len()), Some(21));
+
+        fs::remove_file(full_path).unwrap();
+        fs::remove_file(incremental_path).unwrap();
+    }
+}
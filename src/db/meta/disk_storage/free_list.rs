@@ -1,6 +1,6 @@
-use std::{f32::consts::E, ops::{Deref, DerefMut}};
+use std::{collections::{BTreeMap, BTreeSet}, ops::{Deref, DerefMut}};
 
-use crate::db::shared::{constants::free_list_item::{FREE_ITEM_DATA_OFFSET, FREE_ITEM_DATA_OFFSET_OFFSET, FREE_ITEM_DATA_SIZE, FREE_ITEM_DATA_SIZE_OFFSET, FREE_ITEM_SIZE}, utils::bytes_to_u32};
+use crate::db::{file_stystem::page, shared::{constants::free_list_item::{FREE_ITEM_DATA_OFFSET, FREE_ITEM_DATA_OFFSET_OFFSET, FREE_ITEM_DATA_SIZE, FREE_ITEM_DATA_SIZE_OFFSET, FREE_ITEM_SIZE}, utils::bytes_to_u32}};
 
 
 #[derive(Debug)]
@@ -79,94 +79,143 @@ impl DerefMut for FreeListItem {
     }
 }
 
+#[derive(Debug)]
+pub struct FreeListStats {
+    pub free_extents: usize,
+    pub free_bytes: usize,
+    pub largest_extent: usize,
+    pub pending_bytes: usize,
+    pub retained_bytes: usize,
+}
+
+impl FreeListStats {
+    // Share of free bytes that can't be handed out as one allocation: 0.0 when all free space is a single extent
+    pub fn fragmentation(&self) -> f64 {
+        if self.free_bytes == 0 {
+            return 0.0;
+        }
+
+        1.0 - (self.largest_extent as f64 / self.free_bytes as f64)
+    }
+}
+
+// Items freed since the last flush sit on `free_list` and can't be reused until the flush commits master, because the
+// on-disk tree still points at them. Flushing moves them onto the reclaim extents, which are kept in two indexes: by
+// offset to coalesce neighbours and by (size, offset) to find the best fit. While snapshots are open, committed frees
+// are retained instead, tagged with the version that freed them, until no snapshot older than that version is left.
+// Retained items are written out with the pending ones, so they're reclaimed after a restart.
 #[derive(Debug)]
 pub struct FreeList {
     free_list: Vec<FreeListItem>,
-    reclaim_list: Vec<FreeListItem>,
+    retained: Vec<(u64, FreeListItem)>,
+    reclaim_by_offset: BTreeMap<usize, usize>,
+    reclaim_by_size: BTreeSet<(usize, usize)>,
 }
 
 impl FreeList {
     pub fn new() -> FreeList {
-        FreeList { free_list: vec![], reclaim_list: vec![] }
+        FreeList { free_list: vec![], retained: vec![], reclaim_by_offset: BTreeMap::new(), reclaim_by_size: BTreeSet::new() }
     }
 
     pub fn from_bytes(free_list_bytes: Vec<u8>, reclaim_list_bytes: Vec<u8>) -> FreeList {
-        let free_list = free_list_bytes
-            .chunks(FREE_ITEM_SIZE)
-            .map(|item_bytes| FreeListItem::from_bytes(&item_bytes))
-            .collect();
-        
-        let reclaim_list = reclaim_list_bytes
-            .chunks(FREE_ITEM_SIZE)
-            .map(|item_bytes| FreeListItem::from_bytes(&item_bytes))
-            .collect(); 
-        
-        FreeList { free_list, reclaim_list }
+        let mut free_list = FreeList::new();
+
+        for item_bytes in free_list_bytes.chunks(FREE_ITEM_SIZE) {
+            free_list.add_to_free_list(FreeListItem::from_bytes(item_bytes));
+        }
+
+        for item_bytes in reclaim_list_bytes.chunks(FREE_ITEM_SIZE) {
+            let item = FreeListItem::from_bytes(item_bytes);
+            free_list.add_reclaim_extent(item.offset(), item.size());
+        }
+
+        free_list
     }
 
     pub fn add_to_free_list(&mut self, item: FreeListItem) {
+        if item.offset() == 0 || item.size() == 0 {
+            return;
+        }
+
+        // Freeing an extent twice would hand it out twice once it's reclaimed
+        if self.is_free(item.offset(), item.size()) {
+            return;
+        }
+
         self.free_list.push(item);
     }
 
+    // Space nothing on disk points at, like the rest of a page that was just added to the file, can be reused
+    // straight away
+    pub fn add_unused(&mut self, offset: usize, size: usize) {
+        self.add_reclaim_extent(offset, size);
+    }
+
+    // Where the last free extent ends, pending and retained ones included
+    pub fn end(&self) -> usize {
+        self.free_list.iter()
+            .chain(self.retained.iter().map(|(_, item)| item))
+            .map(|item| item.offset() + item.size())
+            .chain(self.reclaim_by_offset.iter().map(|(offset, size)| offset + size))
+            .max()
+            .unwrap_or(0)
+    }
+
     pub fn reclaim_from_free_list(&mut self, size: usize) -> Option<usize> {
-        if let Some(mut item) = self.reclaim_list.pop() {
-            if size <= item.size() {
-                let curr_offset = item.offset();
-
-                if size < item.size() {
-                    item.move_offset(size);
-                    item.decrease_size(size);
-                    self.reclaim_list.push(item);
-                }
+        if size == 0 {
+            return None;
+        }
 
-                return Some(curr_offset);
-            } else {
-                if let Some(item) = self.reclaim_list
-                    .iter_mut()
-                    .find(|item| size < item.size()) {
-                        let curr_offset = item.offset();
-                        item.move_offset(size);
-                        item.decrease_size(size);
-                        
-                        return Some(curr_offset);
-                    } else {
-                        self.free_list.push(item);
-                        return None;
-                    }
-            }
+        // Smallest extent that still holds the block once it's moved off any page boundary it would straddle
+        let (extent_size, extent_offset, offset) = self.reclaim_by_size
+            .range((size, 0)..)
+            .map(|(extent_size, extent_offset)| (*extent_size, *extent_offset, page::fit_offset(*extent_offset, size)))
+            .find(|(extent_size, extent_offset, offset)| offset + size <= extent_offset + extent_size)?;
+        self.remove_reclaim_extent(extent_offset, extent_size);
+
+        if offset > extent_offset {
+            self.insert_reclaim_extent(extent_offset, offset - extent_offset);
+        }
+
+        if extent_offset + extent_size > offset + size {
+            self.insert_reclaim_extent(offset + size, extent_offset + extent_size - offset - size);
         }
 
-        None 
+        Some(offset)
     }
 
     pub fn condense_free_list(&mut self) {
-        let mut free_memory = vec![];
-        free_memory.extend(self.free_list.drain(..));
-        free_memory.extend(self.reclaim_list.drain(..));
-        free_memory
-            .sort_by(|a, b| b.offset().cmp(&a.offset()));
-        
-        let mut condensed_free_list: Vec<FreeListItem> = vec![];
-        for item in free_memory {
-            if let Some(condensed_item) = condensed_free_list.last_mut() {
-                if condensed_item.offset() + condensed_item.size() == item.offset() {
-                    condensed_item.increase_size(item.size());
-                } else {
-                    condensed_free_list.push(item);
-                }
-            } else {
-                condensed_free_list.push(item);
-            }
+        let free_list: Vec<FreeListItem> = self.free_list.drain(..).collect();
+
+        for item in free_list {
+            self.add_reclaim_extent(item.offset(), item.size());
         }
+    }
 
-        condensed_free_list
-            .sort_by(|a, b| b.size().cmp(&a.size()));
+    // Holds this commit's frees back from reuse until `release_retained` says the snapshots that can see them are gone
+    pub fn retain_free_list(&mut self, version: u64) {
+        let free_list: Vec<FreeListItem> = self.free_list.drain(..).collect();
 
-        self.reclaim_list = condensed_free_list;
+        for item in free_list {
+            self.retained.push((version, item));
+        }
+    }
+
+    // Reclaims every retained item freed at or before `version`, or all of them when there's no version
+    pub fn release_retained(&mut self, version: Option<u64>) {
+        let (released, retained) = self.retained
+            .drain(..)
+            .partition(|(freed_at, _)| version.is_none_or(|version| *freed_at <= version));
+        self.retained = retained;
+
+        for (_, item) in released {
+            self.add_reclaim_extent(item.offset(), item.size());
+        }
     }
 
     pub fn free_list_data(&self) -> Vec<u8> {
         self.free_list.iter()
+            .chain(self.retained.iter().map(|(_, item)| item))
             .fold(vec![], |mut data, item| {
                 data.extend(item.data().to_vec());
                 data
@@ -174,18 +223,232 @@ impl FreeList {
     }
 
     pub fn recliam_list_data(&self) -> Vec<u8> {
-        self.reclaim_list.iter()
-            .fold(vec![], |mut data, item| {
-                data.extend(item.data().to_vec());
+        self.reclaim_by_offset.iter()
+            .fold(vec![], |mut data, (offset, size)| {
+                data.extend(FreeListItem::new(*offset, *size).data().to_vec());
                 data
         }) 
     }
 
     pub fn free_list_len(&self) -> usize {
-        self.free_list.len()
+        self.free_list.len() + self.retained.len()
     }
 
     pub fn reclaim_list_len(&self) -> usize {
-        self.reclaim_list.len()
+        self.reclaim_by_offset.len()
+    }
+
+    pub fn stats(&self) -> FreeListStats {
+        FreeListStats {
+            free_extents: self.reclaim_by_offset.len(),
+            free_bytes: self.reclaim_by_offset.values().sum(),
+            largest_extent: self.reclaim_by_size.last().map(|(size, _)| *size).unwrap_or(0),
+            pending_bytes: self.free_list.iter().map(|item| item.size()).sum(),
+            retained_bytes: self.retained.iter().map(|(_, item)| item.size()).sum(),
+        }
     }
-}
\ No newline at end of file
+
+    // Whether any of the extent is already on the list, pending, retained or reclaimable
+    fn is_free(&self, offset: usize, size: usize) -> bool {
+        let overlaps = |other_offset: usize, other_size: usize| other_offset < offset + size && offset < other_offset + other_size;
+
+        self.free_list.iter()
+            .chain(self.retained.iter().map(|(_, item)| item))
+            .any(|item| overlaps(item.offset(), item.size()))
+            || self.reclaim_by_offset.range(..offset + size).next_back().is_some_and(|(other_offset, other_size)| overlaps(*other_offset, *other_size))
+    }
+
+    // Adds an extent to the reclaim indexes, merging it with the extents directly before and after it. Extents that
+    // overlap free space already on the list (a double free) are dropped rather than handed out twice.
+    fn add_reclaim_extent(&mut self, offset: usize, size: usize) {
+        if offset == 0 || size == 0 {
+            return;
+        }
+
+        let (mut offset, mut size) = (offset, size);
+
+        if let Some((&prev_offset, &prev_size)) = self.reclaim_by_offset.range(..offset).next_back() {
+            if prev_offset + prev_size > offset {
+                return;
+            }
+
+            if prev_offset + prev_size == offset {
+                self.remove_reclaim_extent(prev_offset, prev_size);
+                offset = prev_offset;
+                size += prev_size;
+            }
+        }
+
+        if let Some((&next_offset, &next_size)) = self.reclaim_by_offset.range(offset..).next() {
+            if next_offset < offset + size {
+                return;
+            }
+
+            if next_offset == offset + size {
+                self.remove_reclaim_extent(next_offset, next_size);
+                size += next_size;
+            }
+        }
+
+        self.insert_reclaim_extent(offset, size);
+    }
+
+    fn insert_reclaim_extent(&mut self, offset: usize, size: usize) {
+        self.reclaim_by_offset.insert(offset, size);
+        self.reclaim_by_size.insert((size, offset));
+    }
+
+    fn remove_reclaim_extent(&mut self, offset: usize, size: usize) {
+        self.reclaim_by_offset.remove(&offset);
+        self.reclaim_by_size.remove(&(size, offset));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::db::{file_stystem::page, shared::constants::page::PAGE_SIZE};
+
+    use super::{FreeList, FreeListItem};
+
+    fn assert_disjoint(mut blocks: Vec<(usize, usize)>) {
+        blocks.sort_by_key(|(offset, _)| *offset);
+
+        for pair in blocks.windows(2) {
+            let ((offset, size), (next_offset, _)) = (pair[0], pair[1]);
+            assert!(offset + size <= next_offset, "block {:?} overlaps block {:?}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn live_blocks_never_overlap() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut free_list = FreeList::new();
+            let mut live: Vec<(usize, usize)> = vec![];
+            let mut pending: Vec<(usize, usize)> = vec![];
+            let mut eof = PAGE_SIZE;
+
+            for _ in 0..2_000 {
+                match rng.gen_range(0..10) {
+                    0..=4 => {
+                        let size = rng.gen_range(1..PAGE_SIZE / 8);
+                        let offset = if let Some(offset) = free_list.reclaim_from_free_list(size) {
+                            offset
+                        } else {
+                            // Same as the pager: take a new page and reuse the rest of it straight away
+                            let offset = eof;
+                            free_list.add_unused(offset + size, PAGE_SIZE - size);
+                            eof += PAGE_SIZE;
+                            offset
+                        };
+
+                        assert_eq!(page::page_id(offset), page::page_id(offset + size - 1), "block {:?} straddles a page", (offset, size));
+                        live.push((offset, size));
+                    },
+                    5..=8 if !live.is_empty() => {
+                        let block = live.swap_remove(rng.gen_range(0..live.len()));
+                        free_list.add_to_free_list(FreeListItem::new(block.0, block.1));
+                        pending.push(block);
+                    },
+                    _ => {
+                        free_list.condense_free_list();
+                        pending.clear();
+                    },
+                }
+
+                // Blocks freed since the last flush must not be handed out again until the flush
+                let reclaimable: Vec<(usize, usize)> = free_list.reclaim_by_offset.iter().map(|(o, s)| (*o, *s)).collect();
+                let every_block: Vec<(usize, usize)> = live.iter().chain(&pending).chain(&reclaimable).copied().collect();
+                assert_disjoint(every_block);
+            }
+        }
+    }
+
+    #[test]
+    fn freed_blocks_wait_for_a_condense_before_reuse() {
+        let mut free_list = FreeList::new();
+        free_list.add_to_free_list(FreeListItem::new(100, 50));
+
+        assert_eq!(free_list.reclaim_from_free_list(50), None);
+        free_list.condense_free_list();
+        assert_eq!(free_list.reclaim_from_free_list(50), Some(100));
+    }
+
+    #[test]
+    fn reclaim_takes_the_smallest_extent_that_fits() {
+        let mut free_list = FreeList::new();
+        free_list.add_unused(100, 80);
+        free_list.add_unused(300, 40);
+        free_list.add_unused(500, 60);
+
+        assert_eq!(free_list.reclaim_from_free_list(30), Some(300));
+        assert_eq!(free_list.reclaim_from_free_list(50), Some(500));
+    }
+
+    #[test]
+    fn reclaiming_part_of_an_extent_leaves_the_rest_free() {
+        let mut free_list = FreeList::new();
+        free_list.add_unused(100, 50);
+
+        assert_eq!(free_list.reclaim_from_free_list(20), Some(100));
+        assert_eq!(free_list.reclaim_from_free_list(30), Some(120));
+        assert_eq!(free_list.reclaim_from_free_list(1), None);
+    }
+
+    #[test]
+    fn reclaimed_blocks_never_straddle_a_page() {
+        let mut free_list = FreeList::new();
+        free_list.add_unused(PAGE_SIZE - 10, PAGE_SIZE);
+
+        assert_eq!(free_list.reclaim_from_free_list(20), Some(PAGE_SIZE));
+    }
+
+    #[test]
+    fn freeing_the_same_block_twice_frees_it_once() {
+        let mut free_list = FreeList::new();
+        free_list.add_to_free_list(FreeListItem::new(100, 50));
+        free_list.add_to_free_list(FreeListItem::new(100, 50));
+        free_list.condense_free_list();
+
+        assert_eq!(free_list.stats().free_bytes, 50);
+        assert_eq!(free_list.reclaim_from_free_list(50), Some(100));
+        assert_eq!(free_list.reclaim_from_free_list(50), None);
+    }
+
+    #[test]
+    fn freeing_a_block_that_is_already_reclaimable_is_dropped() {
+        let mut free_list = FreeList::new();
+        free_list.add_unused(100, 50);
+        free_list.add_to_free_list(FreeListItem::new(120, 10));
+        assert_eq!(free_list.stats().pending_bytes, 0);
+
+        free_list.condense_free_list();
+        assert_eq!(free_list.stats().free_bytes, 50);
+    }
+
+    #[test]
+    fn stats_count_free_bytes_and_fragmentation() {
+        let mut free_list = FreeList::new();
+        free_list.add_unused(100, 50);
+        assert_eq!(free_list.stats().free_bytes, 50);
+        assert_eq!(free_list.stats().fragmentation(), 0.0);
+
+        free_list.add_unused(300, 50);
+        assert_eq!(free_list.stats().free_bytes, 100);
+        assert_eq!(free_list.stats().fragmentation(), 0.5);
+    }
+
+    #[test]
+    fn condense_coalesces_neighbouring_extents() {
+        let mut free_list = FreeList::new();
+        free_list.add_to_free_list(FreeListItem::new(100, 50));
+        free_list.add_to_free_list(FreeListItem::new(200, 50));
+        free_list.add_to_free_list(FreeListItem::new(150, 50));
+        free_list.condense_free_list();
+
+        assert_eq!(free_list.reclaim_list_len(), 1);
+        assert_eq!(free_list.reclaim_from_free_list(150), Some(100));
+    }
+}
@@ -1 +1,3 @@
-pub mod disk_storage;
\ No newline at end of file
+pub mod backup;
+pub mod disk_storage;
+pub mod vacuum;
@@ -0,0 +1,523 @@
+use std::{cmp::Reverse, collections::{HashMap, HashSet}, fs};
+
+use crate::db::{b_tree::disk_storage::{node::{Node, NodeIndexType, NodeType}, node_overflow::NodeOverflow}, file_stystem::{file_handler::FileHandler, memory_backend::MemoryBackend, page, pager::Pager, storage::StorageBackend}, meta::disk_storage::master::Master, options::OpenOptions, secondary_index::disk_storage::secondary_index_list::SecondaryIndexList, shared::constants::{master::MASTER_SIZE, node::NODE_SIZE}};
+
+type EmitBlock<'a> = &'a mut dyn FnMut(u32, &[u8], &[u8]);
+
+// Where the walker reads blocks from. The pager reads its latest commit; a snapshot reads the commit it was taken at.
+pub(crate) trait BlockSource {
+    fn read_block(&mut self, offset: u32, size: usize) -> Option<Vec<u8>>;
+}
+
+impl BlockSource for Pager {
+    fn read_block(&mut self, offset: u32, size: usize) -> Option<Vec<u8>> {
+        self.read_from_file(offset as usize, size)
+    }
+}
+
+// Offset and size of the blocks master points at, after relocation
+pub(crate) struct MasterPointers {
+    primary_root: Option<(u32, usize)>,
+    secondary_index_list: Option<(u32, usize)>,
+    table_columns: Option<(u32, usize)>,
+}
+
+// Walks every block reachable from master (trees, overflow lists, rows, secondary index list, table columns) and re-emits
+// each one with its pointers rewritten through `relocations`. An empty relocation map re-emits the blocks unchanged.
+pub(crate) struct BlockWalker<'a> {
+    relocations: &'a HashMap<u32, u32>,
+    seen: HashSet<u32>,
+    emit: EmitBlock<'a>,
+}
+
+impl<'a> BlockWalker<'a> {
+    pub(crate) fn walk(source: &mut dyn BlockSource, master: &Master, relocations: &'a HashMap<u32, u32>, emit: EmitBlock<'a>) -> MasterPointers {
+        let mut walker = BlockWalker { relocations, seen: HashSet::new(), emit };
+
+        let primary_root = if master.primary_root_offset() > 0 {
+            let (primary_root_offset, primary_root_size) = (master.primary_root_offset(), master.primary_root_size() as usize);
+            walker.walk_node(primary_root_offset, primary_root_size, source);
+            Some((walker.relocated(primary_root_offset), primary_root_size))
+        } else {
+            None
+        };
+
+        let secondary_index_list = if master.secondary_index_list_offset() > 0 && master.secondary_index_list_size() > 0 {
+            let (secondary_index_list_offset, secondary_index_list_size) = (master.secondary_index_list_offset(), master.secondary_index_list_size() as usize);
+            walker.walk_secondary_index_list(secondary_index_list_offset, secondary_index_list_size, source);
+            Some((walker.relocated(secondary_index_list_offset), secondary_index_list_size))
+        } else {
+            None
+        };
+
+        let table_columns = if master.table_columns_offset() > 0 && master.table_columns_size() > 0 {
+            let (table_columns_offset, table_columns_size) = (master.table_columns_offset(), master.table_columns_size() as usize);
+            walker.walk_bytes(table_columns_offset, table_columns_size, source);
+            Some((walker.relocated(table_columns_offset), table_columns_size))
+        } else {
+            None
+        };
+
+        MasterPointers { primary_root, secondary_index_list, table_columns }
+    }
+
+    fn walk_node(&mut self, offset: u32, size: usize, source: &mut dyn BlockSource) {
+        if offset == 0 || !self.seen.insert(offset) {
+            return;
+        }
+
+        let bytes = if let Some(bytes) = source.read_block(offset, size) {
+            bytes
+        } else {
+            return;
+        };
+        let mut node = Node::from_bytes(&bytes);
+
+        if let NodeIndexType::Secondary = node.node_index_type() {
+            for i in 0..node.num_keys() {
+                let row_offset = node.key_value_as_u32(i);
+                let row_size = node.key_remote_item_size(i);
+
+                self.walk_bytes(row_offset, row_size, source);
+                node.replace_key(self.relocated(row_offset), row_size, i);
+            }
+        }
+
+        for i in 0..node.num_children() {
+            let child_offset = node.child_offset_as_u32(i);
+            let child_size = node.child_size_as_usize(i);
+            let child_is_overflowing = node.child_is_overflowing(i);
+
+            match (node.node_type(), child_is_overflowing) {
+                (NodeType::Internal, _) => self.walk_node(child_offset, child_size, source),
+                (NodeType::Leaf, true) => self.walk_node_overflow(child_offset, child_size, source),
+                (NodeType::Leaf, false) => self.walk_bytes(child_offset, child_size, source),
+            }
+
+            node.replace_child(self.relocated(child_offset), child_size, child_is_overflowing, i);
+        }
+
+        (self.emit)(offset, &bytes[0..NODE_SIZE], node.data());
+    }
+
+    fn walk_node_overflow(&mut self, offset: u32, size: usize, source: &mut dyn BlockSource) {
+        if offset == 0 || !self.seen.insert(offset) {
+            return;
+        }
+
+        let bytes = if let Some(bytes) = source.read_block(offset, size) {
+            bytes
+        } else {
+            return;
+        };
+        let mut node_overflow = NodeOverflow::from_bytes(bytes.clone());
+
+        for (i, (row_offset, row_size)) in node_overflow.items().into_iter().enumerate() {
+            self.walk_bytes(row_offset, row_size, source);
+            node_overflow.set_item_offset(self.relocated(row_offset), i);
+        }
+
+        (self.emit)(offset, &bytes, node_overflow.data());
+    }
+
+    fn walk_secondary_index_list(&mut self, offset: u32, size: usize, source: &mut dyn BlockSource) {
+        if offset == 0 || !self.seen.insert(offset) {
+            return;
+        }
+
+        let bytes = if let Some(bytes) = source.read_block(offset, size) {
+            bytes
+        } else {
+            return;
+        };
+        let mut secondary_index_list = SecondaryIndexList::from_bytes(bytes.clone());
+        // Whatever follows the list, the statistics, doesn't point anywhere and is kept as it is
+        let statistics = bytes[secondary_index_list.size().min(bytes.len())..].to_vec();
+
+        for i in 0..secondary_index_list.num_items() {
+            if let Some((secondary_root_offset, _)) = secondary_index_list.item(i) {
+                self.walk_node(secondary_root_offset, NODE_SIZE, source);
+                secondary_index_list.set_item_offset(self.relocated(secondary_root_offset), i);
+            }
+        }
+
+        (self.emit)(offset, &bytes, &[secondary_index_list.data(), &statistics].concat());
+    }
+
+    fn walk_bytes(&mut self, offset: u32, size: usize, source: &mut dyn BlockSource) {
+        if offset == 0 || !self.seen.insert(offset) {
+            return;
+        }
+
+        if let Some(bytes) = source.read_block(offset, size) {
+            (self.emit)(offset, &bytes, &bytes);
+        }
+    }
+
+    fn relocated(&self, offset: u32) -> u32 {
+        *self.relocations.get(&offset).unwrap_or(&offset)
+    }
+}
+
+#[derive(Debug)]
+pub struct Vacuum {
+    blocks: Vec<(u32, usize)>,
+}
+
+impl Vacuum {
+    pub fn new(pager: &mut Pager) -> Vacuum {
+        let master = Master::from_bytes(pager.master.data());
+
+        Vacuum::from_source(pager, &master)
+    }
+
+    pub(crate) fn from_source(source: &mut dyn BlockSource, master: &Master) -> Vacuum {
+        let mut blocks = vec![];
+        let relocations = HashMap::new();
+        BlockWalker::walk(source, master, &relocations, &mut |offset, bytes, _| blocks.push((offset, bytes.len())));
+
+        Vacuum { blocks }
+    }
+
+    // Rewrites every live block back-to-back into new storage and swaps it in. On disk that's a new file renamed over
+    // the database file.
+    pub fn vacuum(&self, pager: &mut Pager) -> Result<(), String> {
+        let (storage, staged_path) = Vacuum::stage(pager, "vacuum")?;
+        let master = Master::from_bytes(pager.master.data());
+        let storage = self.compact_into(pager, &master, storage, staged_path.as_deref())?;
+
+        Vacuum::swap_in(pager, storage, staged_path)
+    }
+
+    // Writes the blocks reachable from `master` back-to-back into `storage`, followed by a master pointing at them
+    pub(crate) fn compact_into(&self, source: &mut dyn BlockSource, master: &Master, storage: Box<dyn StorageBackend>, path: Option<&str>) -> Result<Box<dyn StorageBackend>, String> {
+        let mut relocations = HashMap::new();
+        let mut offset = MASTER_SIZE as u32;
+        for (block_offset, block_size) in &self.blocks {
+            offset = page::fit_offset(offset as usize, *block_size) as u32;
+            relocations.insert(*block_offset, offset);
+            offset += *block_size as u32;
+        }
+
+        let target_name = path.unwrap_or("in-memory database").to_string();
+
+        let mut target = Pager::with_storage(path.map(|path| path.to_string()), storage, &OpenOptions::new());
+        let mut write_error = None;
+        let master_pointers = BlockWalker::walk(source, master, &relocations, &mut |offset, _, bytes| {
+            if write_error.is_some() {
+                return;
+            }
+
+            if let Err(e) = target.write_to_file(relocations[&offset], bytes) {
+                write_error = Some(e);
+            }
+        });
+        if let Some(e) = write_error {
+            return Err(format!("Failed to write {}: {}", target_name, e));
+        }
+
+        // The gaps left to keep blocks within a page and the rest of the last page go on the new free list, and the
+        // storage is cut back after that page
+        let blocks: Vec<(u32, usize)> = self.blocks.iter().map(|(block_offset, size)| (relocations[block_offset], *size)).collect();
+        Vacuum::set_master_pointers(&mut target.master, master_pointers);
+        let mut storage = target.rebuild_free_list(Vacuum::holes(&blocks), offset)
+            .and_then(|_| target.into_storage())
+            .map_err(|e| format!("Failed to write {}: {}", target_name, e))?;
+        storage.sync().map_err(|e| format!("Failed to write {}: {}", target_name, e))?;
+
+        Ok(storage)
+    }
+
+    // New storage to build a replacement database in: a file next to the database file named with `suffix`, or memory
+    // for an in-memory database. The file is locked from the start, and the lock goes with it when it's swapped in.
+    pub(crate) fn stage(pager: &Pager, suffix: &str) -> Result<(Box<dyn StorageBackend>, Option<String>), String> {
+        if let Some(path) = pager.path() {
+            let staged_path = format!("{}.{}", path, suffix);
+            let file_handler = FileHandler::create_locked(&staged_path).map_err(|e| format!("Failed to stage {}: {}", staged_path, e))?;
+
+            Ok((Box::new(file_handler), Some(staged_path)))
+        } else {
+            Ok((Box::new(MemoryBackend::new()), None))
+        }
+    }
+
+    // Replaces the database with storage from `stage`. A staged file is renamed over the database file and the
+    // pager keeps the handle it was built through, lock and all, so no other handle can open the file in between. If
+    // the rename fails the database file and the pager are left as they were.
+    pub(crate) fn swap_in(pager: &mut Pager, storage: Box<dyn StorageBackend>, staged_path: Option<String>) -> Result<(), String> {
+        let options = pager.options().clone();
+        let path = pager.path().map(|path| path.to_string());
+
+        if let (Some(path), Some(staged_path)) = (&path, staged_path) {
+            if let Err(e) = fs::rename(&staged_path, path) {
+                let _ = fs::remove_file(&staged_path);
+                return Err(format!("Failed to replace {}: {}", path, e));
+            }
+        }
+        *pager = Pager::with_storage(path, storage, &options);
+
+        Ok(())
+    }
+
+    // Moves blocks from the end of the file into free holes nearer the start and truncates the file. Unlike `vacuum`
+    // this works in place, so it needs no extra disk space but the pointer rewrites are not atomic. Returns the
+    // number of bytes the file shrank by.
+    pub fn incremental_vacuum(&self, pager: &mut Pager) -> Result<usize, String> {
+        let file_len = pager.file_len().map_err(|e| format!("Failed to read file length: {}", e))?;
+
+        let mut holes = Vacuum::holes(&self.blocks);
+        let mut tail_blocks = self.blocks.clone();
+        tail_blocks.sort_by_key(|(offset, _)| Reverse(*offset));
+
+        let mut relocations = HashMap::new();
+        for (block_offset, block_size) in tail_blocks {
+            let hole = holes
+                .iter_mut()
+                .map(|(hole_offset, hole_size)| {
+                    let offset = page::fit_offset(*hole_offset as usize, block_size) as u32;
+                    (offset, hole_offset, hole_size)
+                })
+                .find(|(offset, hole_offset, hole_size)| {
+                    *offset < block_offset && *offset as usize + block_size <= **hole_offset as usize + **hole_size
+                });
+
+            if let Some((offset, hole_offset, hole_size)) = hole {
+                relocations.insert(block_offset, offset);
+                *hole_size -= (offset - *hole_offset) as usize + block_size;
+                *hole_offset = offset + block_size as u32;
+            } else {
+                break;
+            }
+        }
+
+        let mut relocated_writes: Vec<(u32, Vec<u8>)> = vec![];
+        let mut rewrites: Vec<(u32, Vec<u8>)> = vec![];
+        let master = Master::from_bytes(pager.master.data());
+        let master_pointers = BlockWalker::walk(pager, &master, &relocations, &mut |offset, original, bytes| {
+            if let Some(relocated_offset) = relocations.get(&offset) {
+                relocated_writes.push((*relocated_offset, bytes.to_vec()));
+            } else if original != bytes {
+                rewrites.push((offset, bytes.to_vec()));
+            }
+        });
+
+        // Relocated blocks land in free holes so they go first, in-place pointer rewrites follow
+        for (offset, bytes) in relocated_writes.into_iter().chain(rewrites) {
+            pager.write_to_file(offset, &bytes).map_err(|e| format!("Failed to write block at {}: {}", offset, e))?;
+        }
+
+        let blocks: Vec<(u32, usize)> = self.blocks
+            .iter()
+            .map(|(offset, size)| (*relocations.get(offset).unwrap_or(offset), *size))
+            .collect();
+        let end = blocks
+            .iter()
+            .map(|(offset, size)| *offset + *size as u32)
+            .max()
+            .unwrap_or(MASTER_SIZE as u32);
+
+        Vacuum::set_master_pointers(&mut pager.master, master_pointers);
+        pager.rebuild_free_list(Vacuum::holes(&blocks), end).map_err(|e| format!("Failed to truncate file: {}", e))?;
+
+        let vacuumed_len = pager.file_len().map_err(|e| format!("Failed to read file length: {}", e))?;
+
+        Ok(file_len.saturating_sub(vacuumed_len) as usize)
+    }
+
+    fn holes(blocks: &[(u32, usize)]) -> Vec<(u32, usize)> {
+        let mut blocks = blocks.to_vec();
+        blocks.sort_by_key(|(offset, _)| *offset);
+
+        let mut holes = vec![];
+        let mut end = MASTER_SIZE as u32;
+        for (offset, size) in blocks {
+            if offset > end {
+                holes.push((end, (offset - end) as usize));
+            }
+            end = end.max(offset + size as u32);
+        }
+
+        holes
+    }
+
+    fn set_master_pointers(master: &mut Master, master_pointers: MasterPointers) {
+        let (primary_root_offset, primary_root_size) = master_pointers.primary_root.unwrap_or((0, 0));
+        let (secondary_index_list_offset, secondary_index_list_size) = master_pointers.secondary_index_list.unwrap_or((0, 0));
+        let (table_columns_offset, table_columns_size) = master_pointers.table_columns.unwrap_or((0, 0));
+
+        master.set_primary_root_offset(primary_root_offset);
+        master.set_primary_root_size(primary_root_size as u32);
+        master.set_secondary_index_list_offset(secondary_index_list_offset);
+        master.set_secondary_index_list_size(secondary_index_list_size as u32);
+        master.set_table_columns_offset(table_columns_offset);
+        master.set_table_columns_size(table_columns_size as u32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use crate::db::{db::PocketDB, shared::enums::{ColumnType, DataType, OpenError}};
+
+    use super::Vacuum;
+
+    const NUM_ROWS: u32 = 18;
+
+    fn path(name: &str) -> String {
+        env::temp_dir().join(format!("pocketdb-{}-{}.db", name, std::process::id())).to_str().unwrap().to_string()
+    }
+
+    // Rows are large enough that deleting every third one leaves whole pages free
+    fn database(path: &str) -> PocketDB {
+        let _ = fs::remove_file(path);
+        let db = PocketDB::open(path).unwrap();
+        db.add_indexed_column("name", ColumnType::Text).unwrap().add_column("notes", ColumnType::Text).unwrap();
+        for key in 1..=NUM_ROWS {
+            db.insert(key, PocketDB::row(key, vec![
+                PocketDB::cell(DataType::Text(format!("name {:02}", key))),
+                PocketDB::cell(DataType::Text(format!("{} ", key).repeat(500))),
+            ])).unwrap();
+        }
+        for key in (1..=NUM_ROWS).step_by(3) {
+            db.delete_by_primary_index(DataType::Integer(key)).unwrap();
+        }
+
+        db
+    }
+
+    fn assert_rows(db: &PocketDB) {
+        for key in 1..=NUM_ROWS {
+            let expected = if key % 3 == 1 { 0 } else { 1 };
+
+            let by_key = db.search_by_primary_index(DataType::Integer(key)).unwrap_or_default();
+            assert_eq!(by_key.len(), expected, "key {}", key);
+            if let Some(row) = by_key.first() {
+                assert!(row.contains(&DataType::Text(format!("{} ", key).repeat(500))));
+            }
+
+            let by_name = db.search_by_secondary_index(DataType::Text(format!("name {:02}", key)), "name").unwrap_or_default();
+            assert_eq!(by_name.len(), expected, "name {:02}", key);
+        }
+    }
+
+    #[test]
+    fn vacuum_shrinks_the_file_and_keeps_every_row() {
+        let path = path("vacuum");
+        let db = database(&path);
+        assert_rows(&db);
+        let len = fs::metadata(&path).unwrap().len();
+
+        db.vacuum().unwrap();
+        assert_rows(&db);
+        drop(db);
+
+        assert!(fs::metadata(&path).unwrap().len() < len);
+        let db = PocketDB::open(&path).unwrap();
+        assert_rows(&db);
+
+        drop(db);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn incremental_vacuum_shrinks_the_file_and_keeps_every_row() {
+        let path = path("incremental-vacuum");
+        let db = database(&path);
+        let len = fs::metadata(&path).unwrap().len();
+
+        assert!(db.incremental_vacuum().unwrap() > 0);
+        assert_rows(&db);
+        drop(db);
+
+        assert!(fs::metadata(&path).unwrap().len() < len);
+        let db = PocketDB::open(&path).unwrap();
+        assert_rows(&db);
+
+        drop(db);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn vacuumed_files_stay_writable() {
+        let path = path("vacuum-writable");
+        let db = database(&path);
+        db.vacuum().unwrap();
+        assert!(db.incremental_vacuum().is_ok());
+        drop(db);
+
+        let db = PocketDB::open(&path).unwrap();
+        db.insert(100, PocketDB::row(100, vec![
+            PocketDB::cell(DataType::Text(String::from("name 100"))),
+            PocketDB::cell(DataType::Text(String::from("notes"))),
+        ])).unwrap();
+        assert_rows(&db);
+        assert_eq!(db.search_by_secondary_index(DataType::Text(String::from("name 100")), "name").map(|rows| rows.len()), Some(1));
+
+        drop(db);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn vacuumed_files_stay_locked() {
+        let path = path("vacuum-locked");
+        let db = database(&path);
+        db.vacuum().unwrap();
+
+        assert_eq!(PocketDB::open(&path).err(), Some(OpenError::Locked));
+        drop(db);
+        assert!(PocketDB::open(&path).is_ok());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn staged_files_are_locked_until_swapped_in() {
+        let path = path("vacuum-staged");
+        let db = database(&path);
+        let pager = db.pager();
+
+        let (storage, staged_path) = Vacuum::stage(&pager, "vacuum").unwrap();
+        assert!(Vacuum::stage(&pager, "vacuum").is_err());
+        drop(storage);
+        assert!(Vacuum::stage(&pager, "vacuum").is_ok());
+
+        drop(pager);
+        drop(db);
+        fs::remove_file(staged_path.unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn failed_swaps_keep_the_database_file() {
+        let path = path("vacuum-failed-swap");
+        let db = database(&path);
+        let mut pager = db.pager();
+
+        let (storage, staged_path) = Vacuum::stage(&pager, "vacuum").unwrap();
+        let staged_path = staged_path.unwrap();
+        fs::remove_file(&staged_path).unwrap();
+        assert!(Vacuum::swap_in(&mut pager, storage, Some(staged_path)).is_err());
+        drop(pager);
+
+        assert_rows(&db);
+        drop(db);
+        let db = PocketDB::open(&path).unwrap();
+        assert_rows(&db);
+
+        drop(db);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn vacuum_waits_for_snapshots_to_close() {
+        let db = PocketDB::in_memory();
+        db.add_column("name", ColumnType::Text).unwrap();
+        let snapshot = db.snapshot();
+
+        assert_eq!(db.vacuum(), Err(String::from("Can't vacuum while snapshots are open")));
+        assert_eq!(db.incremental_vacuum(), Err(String::from("Can't vacuum while snapshots are open")));
+        drop(snapshot);
+        assert!(db.vacuum().is_ok());
+    }
+}
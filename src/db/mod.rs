@@ -1,4 +1,13 @@
 pub mod db;
+pub mod csv;
+pub mod import;
+pub mod json;
+pub mod options;
+pub mod query;
+#[cfg(feature = "serde")]
+pub mod serialization;
+pub mod snapshot;
+pub mod sql;
 pub mod b_tree;
 pub mod table;
 pub mod file_stystem;
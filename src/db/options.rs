@@ -0,0 +1,186 @@
+use std::time::Duration;
+
+use super::shared::{constants::{import::{IMPORT_BATCH_SIZE, SCHEMA_SAMPLE_SIZE}, page::BUFFER_POOL_SIZE}, enums::{Durability, EvictionPolicy, ReadMode}};
+
+#[derive(Debug, Clone)]
+pub struct OpenOptions {
+    buffer_pool_size: usize,
+    eviction_policy: EvictionPolicy,
+    read_mode: ReadMode,
+    durability: Durability,
+    read_only: bool,
+    lock_timeout: Option<Duration>,
+}
+
+impl OpenOptions {
+    pub fn new() -> OpenOptions {
+        OpenOptions { buffer_pool_size: BUFFER_POOL_SIZE, eviction_policy: EvictionPolicy::Lru, read_mode: ReadMode::Buffered, durability: Durability::Full, read_only: false, lock_timeout: None }
+    }
+
+    // Byte budget for cached pages
+    pub fn set_buffer_pool_size(&mut self, buffer_pool_size: usize) -> &mut Self {
+        self.buffer_pool_size = buffer_pool_size;
+
+        self
+    }
+
+    pub fn set_eviction_policy(&mut self, eviction_policy: EvictionPolicy) -> &mut Self {
+        self.eviction_policy = eviction_policy;
+
+        self
+    }
+
+    // `Mmap` reads pages the buffer pool doesn't hold from a memory map of the file instead of seeking a buffered
+    // reader. It saves the seek and read calls, not the copy: nodes and rows are still parsed from bytes copied out of
+    // the map.
+    pub fn set_read_mode(&mut self, read_mode: ReadMode) -> &mut Self {
+        self.read_mode = read_mode;
+
+        self
+    }
+
+    // `Full` syncs every commit, `Normal` syncs every CHECKPOINT_INTERVAL commits, on `checkpoint` and when the
+    // database is dropped, and `Off` leaves it to the OS. Writers commit one at a time, so concurrent commits don't
+    // share a sync; `insert_batch` is how many rows get synced together.
+    pub fn set_durability(&mut self, durability: Durability) -> &mut Self {
+        self.durability = durability;
+
+        self
+    }
+
+    // Read-only opens share the file's lock with each other; a writer needs it to itself
+    pub fn set_read_only(&mut self, read_only: bool) -> &mut Self {
+        self.read_only = read_only;
+
+        self
+    }
+
+    // How long to wait for another handle to release the lock before failing with `OpenError::Locked`. Without a
+    // timeout a held lock fails the open straight away.
+    pub fn set_lock_timeout(&mut self, lock_timeout: Duration) -> &mut Self {
+        self.lock_timeout = Some(lock_timeout);
+
+        self
+    }
+
+    pub fn buffer_pool_size(&self) -> usize {
+        self.buffer_pool_size
+    }
+
+    pub fn eviction_policy(&self) -> EvictionPolicy {
+        self.eviction_policy
+    }
+
+    pub fn read_mode(&self) -> ReadMode {
+        self.read_mode
+    }
+
+    pub fn durability(&self) -> Durability {
+        self.durability
+    }
+
+    pub fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    pub fn lock_timeout(&self) -> Option<Duration> {
+        self.lock_timeout
+    }
+}
+
+impl Default for OpenOptions {
+    fn default() -> Self {
+        OpenOptions::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    delimiter: u8,
+    batch_size: usize,
+}
+
+impl CsvOptions {
+    pub fn new() -> CsvOptions {
+        CsvOptions { delimiter: b',', batch_size: IMPORT_BATCH_SIZE }
+    }
+
+    pub fn set_delimiter(&mut self, delimiter: u8) -> &mut Self {
+        self.delimiter = delimiter;
+
+        self
+    }
+
+    // Rows inserted per `insert_batch`, which syncs once for the lot
+    pub fn set_batch_size(&mut self, batch_size: usize) -> &mut Self {
+        self.batch_size = batch_size.max(1);
+
+        self
+    }
+
+    pub fn delimiter(&self) -> u8 {
+        self.delimiter
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions::new()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JsonOptions {
+    batch_size: usize,
+    sample_size: usize,
+    indexed_columns: Vec<String>,
+}
+
+impl JsonOptions {
+    pub fn new() -> JsonOptions {
+        JsonOptions { batch_size: IMPORT_BATCH_SIZE, sample_size: SCHEMA_SAMPLE_SIZE, indexed_columns: vec![] }
+    }
+
+    // Rows inserted per `insert_batch`, which syncs once for the lot
+    pub fn set_batch_size(&mut self, batch_size: usize) -> &mut Self {
+        self.batch_size = batch_size.max(1);
+
+        self
+    }
+
+    // How many records to look at when inferring a schema for an empty database
+    pub fn set_sample_size(&mut self, sample_size: usize) -> &mut Self {
+        self.sample_size = sample_size.max(1);
+
+        self
+    }
+
+    // Inferred columns to give a secondary index
+    pub fn set_indexed_columns(&mut self, indexed_columns: &[&str]) -> &mut Self {
+        self.indexed_columns = indexed_columns.iter().map(|name| name.to_string()).collect();
+
+        self
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    pub fn sample_size(&self) -> usize {
+        self.sample_size
+    }
+
+    pub fn indexed_columns(&self) -> &[String] {
+        &self.indexed_columns
+    }
+}
+
+impl Default for JsonOptions {
+    fn default() -> Self {
+        JsonOptions::new()
+    }
+}
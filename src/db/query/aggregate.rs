@@ -0,0 +1,332 @@
+use std::{cmp::Ordering, collections::HashMap, sync::Arc};
+
+use crate::db::{db::PocketDB, shared::enums::{AggregateValue, Aggregation, ColumnType, DataType}, snapshot::Snapshot};
+
+use super::{builder::Predicate, filter::Filter, planner::Planner, record::Record};
+
+// Counts, sums, minimums, maximums and averages over the table, optionally per group of rows sharing the values of
+// some columns: `db.aggregate().group_by(&["company"]).count().avg("age").fetch()`. Rows are read once each
+// from a snapshot. Without groups or filters, counts and the bounds of the key or an indexed column are read
+// from the trees instead; see `Snapshot::count` and `Snapshot::min_max`.
+pub struct Aggregate<'a> {
+    db: &'a PocketDB,
+    group_by: Vec<String>,
+    aggregations: Vec<Aggregation>,
+    matching: Option<Filter>,
+    predicates: Vec<Predicate<'a>>,
+}
+
+// One group's values, with an aggregate value per aggregation in the order they were asked for
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    group_by: Arc<Vec<String>>,
+    aggregations: Arc<Vec<Aggregation>>,
+    key: Vec<DataType>,
+    values: Vec<AggregateValue>,
+}
+
+#[derive(Default)]
+struct Accumulator {
+    count: u64,
+    sum: u64,
+    min: Option<DataType>,
+    max: Option<DataType>,
+}
+
+impl<'a> Aggregate<'a> {
+    pub(crate) fn new(db: &'a PocketDB) -> Aggregate<'a> {
+        Aggregate { db, group_by: vec![], aggregations: vec![], matching: None, predicates: vec![] }
+    }
+
+    pub fn group_by(&mut self, columns: &[&str]) -> &mut Self {
+        self.group_by = columns.iter().map(|column| column.to_string()).collect();
+
+        self
+    }
+
+    pub fn count(&mut self) -> &mut Self {
+        self.aggregate(Aggregation::Count)
+    }
+
+    pub fn sum(&mut self, column: &str) -> &mut Self {
+        self.aggregate(Aggregation::Sum(column.to_string()))
+    }
+
+    pub fn min(&mut self, column: &str) -> &mut Self {
+        self.aggregate(Aggregation::Min(column.to_string()))
+    }
+
+    pub fn max(&mut self, column: &str) -> &mut Self {
+        self.aggregate(Aggregation::Max(column.to_string()))
+    }
+
+    pub fn avg(&mut self, column: &str) -> &mut Self {
+        self.aggregate(Aggregation::Avg(column.to_string()))
+    }
+
+    pub fn aggregate(&mut self, aggregation: Aggregation) -> &mut Self {
+        self.aggregations.push(aggregation);
+
+        self
+    }
+
+    // ANDed with any filter given before
+    pub fn matching(&mut self, filter: Filter) -> &mut Self {
+        self.matching = Some(match self.matching.take() {
+            Some(matching) => matching.and(filter),
+            None => filter,
+        });
+
+        self
+    }
+
+    pub fn filter(&mut self, predicate: impl Fn(&Record) -> bool + 'a) -> &mut Self {
+        self.predicates.push(Box::new(predicate));
+
+        self
+    }
+
+    // Groups come back ordered by their values. Without `group_by` there's always exactly one group, even for no rows.
+    pub fn fetch(&self) -> Result<Vec<Group>, String> {
+        let snapshot = self.db.snapshot();
+        let columns = snapshot.columns();
+        let group_by = self.group_by.iter().map(|name| Aggregate::position(&columns, name)).collect::<Result<Vec<usize>, String>>()?;
+        let aggregated = self.aggregations.iter().map(|aggregation| Aggregate::check(&columns, aggregation)).collect::<Result<Vec<Option<usize>>, String>>()?;
+        let group = |key: Vec<DataType>, values: Vec<AggregateValue>| Group {
+            group_by: Arc::new(self.group_by.clone()),
+            aggregations: Arc::new(self.aggregations.clone()),
+            key,
+            values,
+        };
+
+        if group_by.is_empty() && self.matching.is_none() && self.predicates.is_empty() {
+            if let Some(values) = self.aggregations.iter().map(|aggregation| Aggregate::from_trees(&snapshot, aggregation)).collect() {
+                return Ok(vec![group(vec![], values)]);
+            }
+        }
+
+        let rows: Box<dyn Iterator<Item = Vec<DataType>>> = match &self.matching {
+            Some(filter) => Box::new(Planner::plan(filter, &columns, &snapshot.statistics())?.rows(&snapshot, &columns).into_iter()),
+            None => Box::new(snapshot.rows()),
+        };
+
+        // Typed rows start with the row head, and can have padding after the last column
+        let num_columns = columns.len();
+        let columns = Arc::new(columns);
+        let mut groups: HashMap<Vec<DataType>, Vec<Accumulator>> = HashMap::new();
+        if group_by.is_empty() {
+            groups.insert(vec![], self.aggregations.iter().map(|_| Accumulator::default()).collect());
+        }
+
+        for row in rows {
+            let record = Record::new(columns.clone(), row.into_iter().skip(1).take(num_columns).collect());
+            if !self.predicates.iter().all(|predicate| predicate(&record)) {
+                continue;
+            }
+
+            let key = group_by.iter().map(|position| record.values()[*position].clone()).collect();
+            let accumulators = groups.entry(key).or_insert_with(|| self.aggregations.iter().map(|_| Accumulator::default()).collect());
+            for (accumulator, position) in accumulators.iter_mut().zip(&aggregated) {
+                accumulator.add(position.map(|position| &record.values()[position]));
+            }
+        }
+
+        let mut groups: Vec<Group> = groups
+            .into_iter()
+            .map(|(key, accumulators)| {
+                let values = accumulators.iter().zip(&self.aggregations).map(|(accumulator, aggregation)| accumulator.value(aggregation)).collect();
+                group(key, values)
+            })
+            .collect();
+        groups.sort_by(|a, b| {
+            a.key.iter().zip(&b.key).map(|(a, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal)).find(|ordering| ordering.is_ne()).unwrap_or(Ordering::Equal)
+        });
+
+        Ok(groups)
+    }
+
+    // The position of the column the aggregation reads, if it reads one. Sums and averages need integers.
+    fn check(columns: &[(String, ColumnType)], aggregation: &Aggregation) -> Result<Option<usize>, String> {
+        let column = match aggregation {
+            Aggregation::Count => return Ok(None),
+            Aggregation::Sum(column) | Aggregation::Min(column) | Aggregation::Max(column) | Aggregation::Avg(column) => column,
+        };
+        let position = Aggregate::position(columns, column)?;
+
+        let column_type = columns[position].1;
+        if matches!(aggregation, Aggregation::Sum(_) | Aggregation::Avg(_)) && column_type != ColumnType::Integer {
+            let column_type = format!("{:?}", column_type).to_lowercase();
+            return Err(format!("Can't take the {} of {} column {}", aggregation, column_type, column));
+        }
+
+        Ok(Some(position))
+    }
+
+    fn from_trees(snapshot: &Snapshot, aggregation: &Aggregation) -> Option<AggregateValue> {
+        let value = |value: Option<DataType>| value.map_or(AggregateValue::Null, AggregateValue::Value);
+
+        match aggregation {
+            Aggregation::Count => Some(AggregateValue::Integer(snapshot.count() as u64)),
+            Aggregation::Min(column) => snapshot.min_max(column).map(|bounds| value(bounds.map(|(min, _)| min))),
+            Aggregation::Max(column) => snapshot.min_max(column).map(|bounds| value(bounds.map(|(_, max)| max))),
+            Aggregation::Sum(_) | Aggregation::Avg(_) => None,
+        }
+    }
+
+    fn position(columns: &[(String, ColumnType)], column: &str) -> Result<usize, String> {
+        columns.iter().position(|(name, _)| name == column).ok_or(format!("No column named {}", column))
+    }
+}
+
+impl Group {
+    // The group's value of one of the columns it was grouped by
+    pub fn key(&self, column: &str) -> Option<&DataType> {
+        self.group_by.iter().position(|name| name == column).and_then(|i| self.key.get(i))
+    }
+
+    pub fn value(&self, aggregation: &Aggregation) -> Option<&AggregateValue> {
+        self.aggregations.iter().position(|a| a == aggregation).and_then(|i| self.values.get(i))
+    }
+
+    pub fn count(&self) -> Option<u64> {
+        match self.value(&Aggregation::Count)? {
+            AggregateValue::Integer(count) => Some(*count),
+            _ => None,
+        }
+    }
+
+    pub fn keys(&self) -> &[DataType] {
+        &self.key
+    }
+
+    pub fn values(&self) -> &[AggregateValue] {
+        &self.values
+    }
+}
+
+impl Accumulator {
+    fn add(&mut self, value: Option<&DataType>) {
+        self.count += 1;
+        let Some(value) = value else {
+            return;
+        };
+
+        if let DataType::Integer(value) = value {
+            self.sum += *value as u64;
+        }
+        if self.min.as_ref().is_none_or(|min| value < min) {
+            self.min = Some(value.clone());
+        }
+        if self.max.as_ref().is_none_or(|max| value > max) {
+            self.max = Some(value.clone());
+        }
+    }
+
+    fn value(&self, aggregation: &Aggregation) -> AggregateValue {
+        match aggregation {
+            Aggregation::Count => AggregateValue::Integer(self.count),
+            Aggregation::Sum(_) => AggregateValue::Integer(self.sum),
+            Aggregation::Min(_) => self.min.clone().map_or(AggregateValue::Null, AggregateValue::Value),
+            Aggregation::Max(_) => self.max.clone().map_or(AggregateValue::Null, AggregateValue::Value),
+            Aggregation::Avg(_) if self.count == 0 => AggregateValue::Null,
+            Aggregation::Avg(_) => AggregateValue::Float(self.sum as f64 / self.count as f64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{db::PocketDB, query::filter::Filter, shared::enums::{AggregateValue, Aggregation, ColumnType, DataType}};
+
+    fn database() -> PocketDB {
+        let db = PocketDB::in_memory();
+        db.add_indexed_column("company", ColumnType::Text).unwrap().add_indexed_column("age", ColumnType::Integer).unwrap().add_column("salary", ColumnType::Integer).unwrap();
+        let rows = (1..=300).map(|key| (key, PocketDB::row(key, vec![
+            PocketDB::cell(DataType::Text(format!("company {}", key * 7919 % 7))),
+            PocketDB::cell(DataType::Integer(key * 31 % 300 + 200)),
+            PocketDB::cell(DataType::Integer(key * 10)),
+        ]))).collect();
+        assert!(db.insert_batch(rows).iter().all(|result| result.is_ok()));
+
+        db
+    }
+
+    #[test]
+    fn trees_answer_counts_and_bounds_the_way_rows_do() {
+        let db = database();
+
+        for column in ["key", "company", "age"] {
+            let from_trees = db.aggregate().count().min(column).max(column).fetch().unwrap();
+            let from_rows = db.aggregate().count().min(column).max(column).filter(|_| true).fetch().unwrap();
+            assert_eq!(from_trees, from_rows, "{}", column);
+        }
+    }
+
+    #[test]
+    fn counts_without_groups_count_every_row() {
+        let db = database();
+
+        let groups = db.aggregate().count().fetch().unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].count(), Some(300));
+    }
+
+    #[test]
+    fn bounds_come_in_the_order_they_were_asked_for() {
+        let db = database();
+
+        let bounds = db.aggregate().min("age").max("key").fetch().unwrap();
+        assert_eq!(bounds[0].values(), &[AggregateValue::Value(DataType::Integer(200)), AggregateValue::Value(DataType::Integer(300))]);
+    }
+
+    #[test]
+    fn groups_come_one_per_value_in_value_order() {
+        let db = database();
+
+        let groups = db.aggregate().group_by(&["company"]).count().matching(Filter::range("key", 1..=14)).fetch().unwrap();
+        let companies: Vec<&DataType> = groups.iter().filter_map(|group| group.key("company")).collect();
+        assert_eq!(companies, (0..7).map(|i| DataType::Text(format!("company {}", i))).collect::<Vec<_>>().iter().collect::<Vec<_>>());
+        assert!(groups.iter().all(|group| group.count() == Some(2)));
+    }
+
+    // Keys 3 and 10 are in company 7919 * 3 % 7 = 6
+    #[test]
+    fn groups_roll_up_their_rows() {
+        let db = database();
+
+        let groups = db.aggregate().group_by(&["company"]).sum("salary").avg("salary").max("age").matching(Filter::range("key", 1..=14)).fetch().unwrap();
+        let group = &groups[6];
+        assert_eq!(group.value(&Aggregation::Sum(String::from("salary"))), Some(&AggregateValue::Integer(130)));
+        assert_eq!(group.value(&Aggregation::Avg(String::from("salary"))), Some(&AggregateValue::Float(65.0)));
+        assert_eq!(group.value(&Aggregation::Max(String::from("age"))), Some(&AggregateValue::Value(DataType::Integer(293))));
+    }
+
+    #[test]
+    fn aggregates_of_no_rows_are_zero_or_null() {
+        let db = database();
+
+        let empty = db.aggregate().count().avg("salary").filter(|record| record.key() == Some(0)).fetch().unwrap();
+        assert_eq!(empty[0].values(), &[AggregateValue::Integer(0), AggregateValue::Null]);
+    }
+
+    #[test]
+    fn grouping_no_rows_gives_no_groups() {
+        let db = database();
+
+        assert!(db.aggregate().group_by(&["company"]).count().filter(|_| false).fetch().unwrap().is_empty());
+    }
+
+    #[test]
+    fn sums_of_text_columns_fail() {
+        let db = database();
+
+        assert_eq!(db.aggregate().sum("company").fetch(), Err(String::from("Can't take the sum(company) of text column company")));
+    }
+
+    #[test]
+    fn grouping_by_a_missing_column_fails() {
+        let db = database();
+
+        assert_eq!(db.aggregate().group_by(&["city"]).count().fetch(), Err(String::from("No column named city")));
+    }
+}
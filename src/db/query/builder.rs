@@ -0,0 +1,234 @@
+use std::{cmp::Ordering, sync::Arc};
+
+use crate::db::{db::PocketDB, shared::enums::{ColumnType, DataType}};
+
+use super::{filter::Filter, planner::Planner, record::Record};
+
+pub(crate) type Predicate<'a> = Box<dyn Fn(&Record) -> bool + 'a>;
+
+// Rows of the table as records, built up a step at a time and read with `fetch`:
+// `db.query().select(&["firstname", "company"]).filter(|record| record.integer("age") > Some(30)).limit(10).fetch()`
+// A `Filter` given to `matching` picks the rows through the planner; closures given to `filter` are checked on each
+// row it finds, with every column available whatever is selected.
+pub struct Query<'a> {
+    db: &'a PocketDB,
+    projection: Option<Vec<String>>,
+    matching: Option<Filter>,
+    predicates: Vec<Predicate<'a>>,
+    order_by: Option<(String, bool)>,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+impl<'a> Query<'a> {
+    pub(crate) fn new(db: &'a PocketDB) -> Query<'a> {
+        Query { db, projection: None, matching: None, predicates: vec![], order_by: None, limit: None, offset: 0 }
+    }
+
+    // The columns each record keeps, in this order. Without it every column is kept, key first.
+    pub fn select(&mut self, columns: &[&str]) -> &mut Self {
+        self.projection = Some(columns.iter().map(|column| column.to_string()).collect());
+
+        self
+    }
+
+    // ANDed with any filter given before
+    pub fn matching(&mut self, filter: Filter) -> &mut Self {
+        self.matching = Some(match self.matching.take() {
+            Some(matching) => matching.and(filter),
+            None => filter,
+        });
+
+        self
+    }
+
+    pub fn filter(&mut self, predicate: impl Fn(&Record) -> bool + 'a) -> &mut Self {
+        self.predicates.push(Box::new(predicate));
+
+        self
+    }
+
+    // Values of different types compare equal, so they keep the order they were found in
+    pub fn order_by(&mut self, column: &str) -> &mut Self {
+        self.order_by = Some((column.to_string(), false));
+
+        self
+    }
+
+    pub fn order_by_descending(&mut self, column: &str) -> &mut Self {
+        self.order_by = Some((column.to_string(), true));
+
+        self
+    }
+
+    pub fn limit(&mut self, limit: usize) -> &mut Self {
+        self.limit = Some(limit);
+
+        self
+    }
+
+    pub fn offset(&mut self, offset: usize) -> &mut Self {
+        self.offset = offset;
+
+        self
+    }
+
+    // Reads from a snapshot taken now. Fails if a column named anywhere in the query doesn't exist.
+    pub fn fetch(&self) -> Result<Vec<Record>, String> {
+        let snapshot = self.db.snapshot();
+        let columns = snapshot.columns();
+        let projection = match &self.projection {
+            Some(names) => Some(names.iter().map(|name| Query::position(&columns, name)).collect::<Result<Vec<usize>, String>>()?),
+            None => None,
+        };
+        let order_by = match &self.order_by {
+            Some((column, descending)) => Some((Query::position(&columns, column)?, *descending)),
+            None => None,
+        };
+
+        let rows: Box<dyn Iterator<Item = Vec<DataType>>> = match &self.matching {
+            Some(filter) => Box::new(Planner::plan(filter, &columns, &snapshot.statistics())?.rows(&snapshot, &columns).into_iter()),
+            None => Box::new(snapshot.rows()),
+        };
+
+        // Typed rows start with the row head, and can have padding after the last column
+        let num_columns = columns.len();
+        let all_columns = Arc::new(columns);
+        let records = rows
+            .map(|row| Record::new(all_columns.clone(), row.into_iter().skip(1).take(num_columns).collect()))
+            .filter(|record| self.predicates.iter().all(|predicate| predicate(record)));
+
+        // Without an order the rows past the limit are never read
+        let records: Vec<Record> = match order_by {
+            Some((position, descending)) => {
+                let mut records: Vec<Record> = records.collect();
+                records.sort_by(|a, b| {
+                    let ordering = a.values()[position].partial_cmp(&b.values()[position]).unwrap_or(Ordering::Equal);
+                    if descending { ordering.reverse() } else { ordering }
+                });
+
+                records.into_iter().skip(self.offset).take(self.limit.unwrap_or(usize::MAX)).collect()
+            },
+            None => records.skip(self.offset).take(self.limit.unwrap_or(usize::MAX)).collect(),
+        };
+
+        let Some(projection) = projection else {
+            return Ok(records);
+        };
+        let selected_columns: Arc<Vec<(String, ColumnType)>> = Arc::new(projection.iter().map(|position| all_columns[*position].clone()).collect());
+
+        Ok(records
+            .into_iter()
+            .map(|record| Record::new(selected_columns.clone(), projection.iter().map(|position| record.values()[*position].clone()).collect()))
+            .collect())
+    }
+
+    fn position(columns: &[(String, ColumnType)], column: &str) -> Result<usize, String> {
+        columns.iter().position(|(name, _)| name == column).ok_or(format!("No column named {}", column))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{db::PocketDB, query::{filter::Filter, record::Record}, shared::enums::{ColumnType, DataType}};
+
+    fn database() -> PocketDB {
+        let db = PocketDB::in_memory();
+        db.add_column("firstname", ColumnType::Text).unwrap().add_indexed_column("company", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+        for (key, firstname, company, age) in [(1, "Sammie", "Vanguard", 30), (2, "Alex", "Acme", 41), (3, "Jo", "Vanguard", 25), (4, "Kim", "Acme", 35)] {
+            db.insert(key, PocketDB::row(key, vec![
+                PocketDB::cell(DataType::from(firstname)),
+                PocketDB::cell(DataType::from(company)),
+                PocketDB::cell(DataType::Integer(age)),
+            ])).unwrap();
+        }
+
+        db
+    }
+
+    fn keys(records: &[Record]) -> Vec<Option<u32>> {
+        records.iter().map(|record| record.key()).collect()
+    }
+
+    #[test]
+    fn closures_filter_records() {
+        let db = database();
+
+        let records = db.query().filter(|record| record.integer("age") > Some(28)).fetch().unwrap();
+        assert_eq!(keys(&records), vec![Some(1), Some(2), Some(4)]);
+    }
+
+    #[test]
+    fn filters_find_records_through_the_planner() {
+        let db = database();
+
+        let records = db.query().matching(Filter::eq("company", "Vanguard")).fetch().unwrap();
+        assert_eq!(keys(&records), vec![Some(1), Some(3)]);
+    }
+
+    #[test]
+    fn records_hold_only_the_selected_columns() {
+        let db = database();
+
+        let records = db.query().select(&["firstname", "company"]).fetch().unwrap();
+        assert_eq!(records[0].columns(), &[(String::from("firstname"), ColumnType::Text), (String::from("company"), ColumnType::Text)]);
+        assert_eq!(records[0].text("firstname"), Some("Sammie"));
+        assert_eq!(records[0].get("age"), None);
+        assert_eq!(records[0].key(), None);
+    }
+
+    #[test]
+    fn records_hold_every_column_without_a_selection() {
+        let db = database();
+
+        let records = db.query().fetch().unwrap();
+        assert_eq!(records[2].values(), &[DataType::Integer(3), DataType::from("Jo"), DataType::from("Vanguard"), DataType::Integer(25)]);
+    }
+
+    #[test]
+    fn records_come_ordered_by_a_column() {
+        let db = database();
+
+        let records = db.query().order_by("firstname").fetch().unwrap();
+        let names: Vec<&str> = records.iter().map(|record| record.text("firstname").unwrap()).collect();
+        assert_eq!(names, vec!["Alex", "Jo", "Kim", "Sammie"]);
+    }
+
+    #[test]
+    fn records_come_ordered_by_a_column_descending() {
+        let db = database();
+
+        let records = db.query().order_by_descending("age").fetch().unwrap();
+        assert_eq!(keys(&records), vec![Some(2), Some(4), Some(1), Some(3)]);
+    }
+
+    #[test]
+    fn offset_and_limit_apply_after_ordering() {
+        let db = database();
+
+        let records = db.query().order_by("age").offset(1).limit(2).fetch().unwrap();
+        assert_eq!(keys(&records), vec![Some(1), Some(4)]);
+    }
+
+    #[test]
+    fn limits_past_the_table_return_every_record() {
+        let db = database();
+
+        assert_eq!(db.query().limit(3).fetch().unwrap().len(), 3);
+        assert_eq!(db.query().limit(10).fetch().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn selecting_a_missing_column_fails() {
+        let db = database();
+
+        assert_eq!(db.query().select(&["lastname"]).fetch(), Err(String::from("No column named lastname")));
+    }
+
+    #[test]
+    fn ordering_by_a_missing_column_fails() {
+        let db = database();
+
+        assert_eq!(db.query().order_by("height").fetch(), Err(String::from("No column named height")));
+    }
+}
@@ -0,0 +1,145 @@
+use std::{fmt, ops::{Bound, RangeBounds}};
+
+use crate::db::shared::enums::{ColumnType, DataType};
+
+// A condition on a row's columns, built up with `eq`, `range`, `and` and `or`:
+// `Filter::eq("firstname", "Sammie").and(Filter::range("key", 10..20))`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Eq(String, DataType),
+    Range(String, Bound<DataType>, Bound<DataType>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+}
+
+impl Filter {
+    pub fn eq(column: &str, value: impl Into<DataType>) -> Filter {
+        Filter::Eq(column.to_string(), value.into())
+    }
+
+    pub fn range<T: Into<DataType> + Clone>(column: &str, range: impl RangeBounds<T>) -> Filter {
+        let bound = |bound: Bound<&T>| bound.map(|value| value.clone().into());
+
+        Filter::Range(column.to_string(), bound(range.start_bound()), bound(range.end_bound()))
+    }
+
+    pub fn and(self, other: Filter) -> Filter {
+        Filter::And(Box::new(self), Box::new(other))
+    }
+
+    pub fn or(self, other: Filter) -> Filter {
+        Filter::Or(Box::new(self), Box::new(other))
+    }
+
+    // The filters that all have to hold for this one to, splitting up nested ANDs
+    pub fn conjuncts(&self) -> Vec<&Filter> {
+        match self {
+            Filter::And(left, right) => {
+                let mut conjuncts = left.conjuncts();
+                conjuncts.extend(right.conjuncts());
+                conjuncts
+            },
+            filter => vec![filter],
+        }
+    }
+
+    // Fails if a column doesn't exist or is compared with a value of another type
+    pub fn check(&self, columns: &[(String, ColumnType)]) -> Result<(), String> {
+        match self {
+            Filter::Eq(column, value) => Filter::check_values(columns, column, &[value]),
+            Filter::Range(column, lower, upper) => {
+                let values: Vec<&DataType> = [lower, upper]
+                    .into_iter()
+                    .filter_map(|bound| match bound {
+                        Bound::Included(value) | Bound::Excluded(value) => Some(value),
+                        Bound::Unbounded => None,
+                    })
+                    .collect();
+
+                Filter::check_values(columns, column, &values)
+            },
+            Filter::And(left, right) | Filter::Or(left, right) => left.check(columns).and_then(|_| right.check(columns)),
+        }
+    }
+
+    // `values` are a row's values in the order of `columns`, key first
+    pub fn matches(&self, columns: &[(String, ColumnType)], values: &[DataType]) -> bool {
+        let value = |column: &str| columns.iter().position(|(name, _)| name == column).and_then(|i| values.get(i));
+
+        match self {
+            Filter::Eq(column, expected) => value(column) == Some(expected),
+            Filter::Range(column, lower, upper) => value(column).is_some_and(|value| {
+                let above = match lower {
+                    Bound::Included(lower) => value >= lower,
+                    Bound::Excluded(lower) => value > lower,
+                    Bound::Unbounded => true,
+                };
+                let below = match upper {
+                    Bound::Included(upper) => value <= upper,
+                    Bound::Excluded(upper) => value < upper,
+                    Bound::Unbounded => true,
+                };
+
+                above && below
+            }),
+            Filter::And(left, right) => left.matches(columns, values) && right.matches(columns, values),
+            Filter::Or(left, right) => left.matches(columns, values) || right.matches(columns, values),
+        }
+    }
+
+    fn check_values(columns: &[(String, ColumnType)], column: &str, values: &[&DataType]) -> Result<(), String> {
+        let (_, column_type) = columns.iter().find(|(name, _)| name == column).ok_or(format!("No column named {}", column))?;
+        if let Some(value) = values.iter().find(|value| !column_type.matches(value)) {
+            let (expected, found) = (format!("{:?}", column_type).to_lowercase(), format!("{:?}", value.column_type()).to_lowercase());
+            return Err(format!("Expected {} for column {} but found {}", expected, column, found));
+        }
+
+        Ok(())
+    }
+
+    fn parenthesized_in_and(&self) -> String {
+        match self {
+            Filter::Or(_, _) => format!("({})", self),
+            filter => filter.to_string(),
+        }
+    }
+
+    // Values are written the way SQL would, with text in single quotes
+    pub(crate) fn literal(value: &DataType) -> String {
+        match value {
+            DataType::Integer(value) => value.to_string(),
+            DataType::Text(value) => format!("'{}'", value.replace('\'', "''")),
+            DataType::Bool(value) => value.to_string(),
+            DataType::Meta => String::from("meta"),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::Eq(column, value) => write!(f, "{} = {}", column, Filter::literal(value)),
+            Filter::Range(column, lower, upper) => {
+                let mut bounds = vec![];
+                match lower {
+                    Bound::Included(value) => bounds.push(format!("{} >= {}", column, Filter::literal(value))),
+                    Bound::Excluded(value) => bounds.push(format!("{} > {}", column, Filter::literal(value))),
+                    Bound::Unbounded => (),
+                }
+                match upper {
+                    Bound::Included(value) => bounds.push(format!("{} <= {}", column, Filter::literal(value))),
+                    Bound::Excluded(value) => bounds.push(format!("{} < {}", column, Filter::literal(value))),
+                    Bound::Unbounded => (),
+                }
+
+                if bounds.is_empty() {
+                    write!(f, "{} is anything", column)
+                } else {
+                    write!(f, "{}", bounds.join(" AND "))
+                }
+            },
+            Filter::And(left, right) => write!(f, "{} AND {}", left.parenthesized_in_and(), right.parenthesized_in_and()),
+            Filter::Or(left, right) => write!(f, "{} OR {}", left, right),
+        }
+    }
+}
@@ -0,0 +1,244 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::db::{db::PocketDB, shared::enums::{ColumnType, DataType}, snapshot::Snapshot};
+
+use super::record::Record;
+
+// How rows of the right table are found for each row of the left
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Strategy {
+    KeyLookup,
+    IndexLookup,
+    Hash,
+}
+
+// Pairs of rows from two tables whose columns hold the same value. A database holds a single table, so each side is
+// its own handle, named by the caller: records have columns like `people.firstname` and `companies.name`, left table
+// first. The left table is read a row at a time; the right is looked up by its key or secondary index on the joined
+// column where it has one, and otherwise read once into a hash table.
+// `PocketDB::join(("people", &people), ("companies", &companies), ("company", "name"))?.records()`
+#[derive(Debug)]
+pub struct Join<'a> {
+    left: Snapshot<'a>,
+    right: Snapshot<'a>,
+    on: (usize, usize),
+    right_column: String,
+    right_name: String,
+    strategy: Strategy,
+    columns: Arc<Vec<(String, ColumnType)>>,
+}
+
+impl<'a> Join<'a> {
+    // Fails if either column doesn't exist, or they hold different types
+    pub(crate) fn new(left: (&str, &'a PocketDB), right: (&str, &'a PocketDB), on: (&str, &str)) -> Result<Join<'a>, String> {
+        let (left_name, left_db) = left;
+        let (right_name, right_db) = right;
+        let (left_snapshot, right_snapshot) = (left_db.snapshot(), right_db.snapshot());
+        let (left_columns, right_columns) = (left_snapshot.columns(), right_snapshot.columns());
+
+        let left_position = Join::position(&left_columns, left_name, on.0)?;
+        let right_position = Join::position(&right_columns, right_name, on.1)?;
+        let (left_type, right_type) = (left_columns[left_position].1, right_columns[right_position].1);
+        if left_type != right_type {
+            return Err(format!(
+                "Can't join {}.{} to {}.{}, one is {} and the other {}",
+                left_name, on.0, right_name, on.1, format!("{:?}", left_type).to_lowercase(), format!("{:?}", right_type).to_lowercase()
+            ));
+        }
+
+        let strategy = if right_position == 0 {
+            Strategy::KeyLookup
+        } else if right_snapshot.statistics().index(on.1).is_some() {
+            Strategy::IndexLookup
+        } else {
+            Strategy::Hash
+        };
+
+        let columns = left_columns
+            .iter()
+            .map(|(column, column_type)| (format!("{}.{}", left_name, column), *column_type))
+            .chain(right_columns.iter().map(|(column, column_type)| (format!("{}.{}", right_name, column), *column_type)))
+            .collect();
+
+        Ok(Join {
+            left: left_snapshot,
+            right: right_snapshot,
+            on: (left_position, right_position),
+            right_column: on.1.to_string(),
+            right_name: right_name.to_string(),
+            strategy,
+            columns: Arc::new(columns),
+        })
+    }
+
+    pub fn explain(&self) -> String {
+        let strategy = match self.strategy {
+            Strategy::KeyLookup => "Key lookup",
+            Strategy::IndexLookup => "Index lookup",
+            Strategy::Hash => "Hash join",
+        };
+
+        format!("{} on {}.{}", strategy, self.right_name, self.right_column)
+    }
+
+    pub fn columns(&self) -> &[(String, ColumnType)] {
+        &self.columns
+    }
+
+    // Rows of the left table without a match are left out. A hash join reads the whole right table before the first
+    // record; the lookups don't read anything ahead.
+    pub fn records(&self) -> impl Iterator<Item = Record> + '_ {
+        let (left_position, right_position) = self.on;
+        let (num_left_columns, num_right_columns) = (self.left.columns().len(), self.right.columns().len());
+
+        let mut hashed: HashMap<DataType, Vec<Vec<DataType>>> = HashMap::new();
+        if self.strategy == Strategy::Hash {
+            for row in self.right.rows() {
+                let values = Join::values(row, num_right_columns);
+                if let Some(value) = values.get(right_position) {
+                    hashed.entry(value.clone()).or_default().push(values);
+                }
+            }
+        }
+
+        self.left.rows().flat_map(move |row| {
+            let left_values = Join::values(row, num_left_columns);
+            let matches = match left_values.get(left_position) {
+                Some(value) => self.matches(value, &hashed, num_right_columns),
+                None => vec![],
+            };
+
+            matches.into_iter().map(move |right_values| {
+                Record::new(self.columns.clone(), left_values.iter().cloned().chain(right_values).collect())
+            })
+        })
+    }
+
+    fn matches(&self, value: &DataType, hashed: &HashMap<DataType, Vec<Vec<DataType>>>, num_right_columns: usize) -> Vec<Vec<DataType>> {
+        let rows = match self.strategy {
+            Strategy::KeyLookup => self.right.search_by_primary_index(value.clone()),
+            Strategy::IndexLookup => self.right.search_by_secondary_index(value.clone(), &self.right_column),
+            Strategy::Hash => return hashed.get(value).cloned().unwrap_or_default(),
+        };
+
+        // Lookups can find rows the value only shares a prefix of its bytes with
+        rows.unwrap_or_default()
+            .into_iter()
+            .map(|row| Join::values(row, num_right_columns))
+            .filter(|values| values.get(self.on.1) == Some(value))
+            .collect()
+    }
+
+    // Typed rows start with the row head, and can have padding after the last column
+    fn values(row: Vec<DataType>, num_columns: usize) -> Vec<DataType> {
+        row.into_iter().skip(1).take(num_columns).collect()
+    }
+
+    fn position(columns: &[(String, ColumnType)], table: &str, column: &str) -> Result<usize, String> {
+        columns.iter().position(|(name, _)| name == column).ok_or(format!("No column named {}.{}", table, column))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{db::PocketDB, shared::enums::{ColumnType, DataType}};
+
+    use super::Join;
+
+    fn databases() -> (PocketDB, PocketDB) {
+        let people = PocketDB::in_memory();
+        people.add_column("firstname", ColumnType::Text).unwrap().add_column("company", ColumnType::Text).unwrap().add_column("company_key", ColumnType::Integer).unwrap();
+        for (key, firstname, company, company_key) in [(1, "Sammie", "Vanguard", 20), (2, "Alex", "Acme", 10), (3, "Jo", "Vanguard", 20), (4, "Kim", "Initech", 30)] {
+            people.insert(key, PocketDB::row(key, vec![
+                PocketDB::cell(DataType::from(firstname)),
+                PocketDB::cell(DataType::from(company)),
+                PocketDB::cell(DataType::Integer(company_key)),
+            ])).unwrap();
+        }
+
+        let companies = PocketDB::in_memory();
+        companies.add_indexed_column("name", ColumnType::Text).unwrap().add_column("city", ColumnType::Text).unwrap();
+        for (key, name, city) in [(10, "Acme", "Leeds"), (20, "Vanguard", "York")] {
+            companies.insert(key, PocketDB::row(key, vec![
+                PocketDB::cell(DataType::from(name)),
+                PocketDB::cell(DataType::from(city)),
+            ])).unwrap();
+        }
+
+        (people, companies)
+    }
+
+    fn pairs(join: &Join) -> Vec<(String, String)> {
+        join.records().map(|record| (record.text("people.firstname").unwrap().to_string(), record.text("companies.city").unwrap().to_string())).collect()
+    }
+
+    fn expected() -> Vec<(String, String)> {
+        vec![
+            (String::from("Sammie"), String::from("York")),
+            (String::from("Alex"), String::from("Leeds")),
+            (String::from("Jo"), String::from("York")),
+        ]
+    }
+
+    #[test]
+    fn indexed_columns_join_by_index_lookup() {
+        let (people, companies) = databases();
+
+        let join = PocketDB::join(("people", &people), ("companies", &companies), ("company", "name")).unwrap();
+        assert_eq!(join.explain(), "Index lookup on companies.name");
+        assert_eq!(pairs(&join), expected());
+    }
+
+    #[test]
+    fn keys_join_by_key_lookup() {
+        let (people, companies) = databases();
+
+        let join = PocketDB::join(("people", &people), ("companies", &companies), ("company_key", "key")).unwrap();
+        assert_eq!(join.explain(), "Key lookup on companies.key");
+        assert_eq!(pairs(&join), expected());
+    }
+
+    #[test]
+    fn unindexed_columns_join_by_hash() {
+        let (people, companies) = databases();
+
+        let join = PocketDB::join(("companies", &companies), ("people", &people), ("name", "company")).unwrap();
+        assert_eq!(join.explain(), "Hash join on people.company");
+        assert_eq!(join.records().filter_map(|record| record.integer("people.key")).collect::<Vec<_>>(), vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn rows_without_a_match_are_left_out() {
+        let (people, companies) = databases();
+
+        let join = PocketDB::join(("people", &people), ("companies", &companies), ("company", "name")).unwrap();
+        assert!(join.records().all(|record| record.text("people.firstname") != Some("Kim")));
+    }
+
+    #[test]
+    fn joined_records_have_both_tables_columns() {
+        let (people, companies) = databases();
+
+        let join = PocketDB::join(("people", &people), ("companies", &companies), ("company", "name")).unwrap();
+        assert_eq!(join.columns().len(), 7);
+        assert_eq!(join.columns()[0].0, "people.key");
+        assert_eq!(join.records().next().unwrap().integer("companies.key"), Some(20));
+    }
+
+    #[test]
+    fn joining_columns_of_different_types_fails() {
+        let (people, companies) = databases();
+
+        assert_eq!(
+            PocketDB::join(("people", &people), ("companies", &companies), ("company", "key")).unwrap_err(),
+            "Can't join people.company to companies.key, one is text and the other integer"
+        );
+    }
+
+    #[test]
+    fn joining_a_missing_column_fails() {
+        let (people, companies) = databases();
+
+        assert_eq!(PocketDB::join(("people", &people), ("companies", &companies), ("company", "ceo")).unwrap_err(), "No column named companies.ceo");
+    }
+}
@@ -0,0 +1,7 @@
+pub mod aggregate;
+pub mod builder;
+pub mod filter;
+pub mod join;
+pub mod planner;
+pub mod record;
+pub mod statistics;
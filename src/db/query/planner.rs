@@ -0,0 +1,305 @@
+use std::ops::Bound;
+
+use crate::db::{shared::{constants::planner::{MAX_KEY_SEEKS, RANGE_SELECTIVITY, SEEK_COST}, enums::{ColumnType, DataType}}, snapshot::Snapshot};
+
+use super::{filter::Filter, statistics::Statistics};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Access {
+    // Each key is looked up in the primary index
+    KeySeek(Vec<u32>),
+    IndexSeek { column: String, value: DataType },
+    IndexRangeScan { column: String, lower: Bound<DataType>, upper: Bound<DataType> },
+    FullScan,
+}
+
+// How a filter's rows are found: an access path chosen from one of the filter's top-level ANDs, then whatever's left
+// of the filter checked against each row it finds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryPlan {
+    access: Access,
+    // The part of the filter the access path answers by itself
+    access_filter: Option<Filter>,
+    residual: Option<Filter>,
+    estimated_rows: usize,
+    total_rows: usize,
+}
+
+// Costs are counted in rows read, with a tree descent costing `SEEK_COST`. A full scan reads every row; a key seek
+// reads one per key; an index seek reads the rows per distinct value of the index; an index range scan is guessed
+// to read one row in `RANGE_SELECTIVITY`. The cheapest wins, and a full scan wins ties.
+pub struct Planner;
+
+impl Planner {
+    pub fn plan(filter: &Filter, columns: &[(String, ColumnType)], statistics: &Statistics) -> Result<QueryPlan, String> {
+        filter.check(columns)?;
+
+        let total_rows = statistics.rows;
+        let conjuncts = filter.conjuncts();
+        let best = conjuncts
+            .iter()
+            .enumerate()
+            .filter_map(|(i, conjunct)| Planner::access(conjunct, columns, statistics).map(|(access, rows, cost)| (i, access, rows, cost)))
+            .min_by_key(|(_, _, _, cost)| *cost)
+            .filter(|(_, _, _, cost)| *cost < total_rows);
+
+        let plan = match best {
+            Some((i, access, estimated_rows, _)) => QueryPlan {
+                access,
+                access_filter: Some(conjuncts[i].clone()),
+                residual: Planner::and_all(conjuncts.iter().enumerate().filter(|(j, _)| *j != i).map(|(_, conjunct)| *conjunct)),
+                estimated_rows,
+                total_rows,
+            },
+            None => QueryPlan { access: Access::FullScan, access_filter: None, residual: Some(filter.clone()), estimated_rows: total_rows, total_rows },
+        };
+
+        Ok(plan)
+    }
+
+    // The access path a single condition allows, with the rows it's expected to find and what it costs
+    fn access(conjunct: &Filter, columns: &[(String, ColumnType)], statistics: &Statistics) -> Option<(Access, usize, usize)> {
+        let rows = statistics.rows;
+
+        match conjunct {
+            Filter::Eq(column, DataType::Integer(key)) if column == "key" => Some((Access::KeySeek(vec![*key]), 1.min(rows), SEEK_COST)),
+            Filter::Range(column, lower, upper) if column == "key" => {
+                let keys = Planner::key_range(lower, upper)?;
+                // Counted from the bounds, as a range up to u32::MAX is too long to step through
+                let num_keys = if keys.is_empty() { 0 } else { (*keys.end() - *keys.start()) as u64 + 1 };
+                if num_keys > MAX_KEY_SEEKS as u64 {
+                    return None;
+                }
+
+                let num_keys = num_keys as usize;
+                Some((Access::KeySeek(keys.collect()), num_keys.min(rows), num_keys * SEEK_COST))
+            },
+            Filter::Eq(column, value) => {
+                let index = statistics.index(column)?;
+                let estimated_rows = rows.div_ceil(index.distinct_keys.max(1));
+
+                Some((Access::IndexSeek { column: column.clone(), value: value.clone() }, estimated_rows, SEEK_COST + estimated_rows))
+            },
+            // Integers sort by their little-endian bytes in a tree, so only text and bool ranges are contiguous
+            Filter::Range(column, lower, upper) => {
+                statistics.index(column)?;
+                let column_type = columns.iter().find(|(name, _)| name == column).map(|(_, column_type)| *column_type)?;
+                if column_type != ColumnType::Text && column_type != ColumnType::Bool {
+                    return None;
+                }
+
+                let access = Access::IndexRangeScan { column: column.clone(), lower: lower.clone(), upper: upper.clone() };
+                let estimated_rows = rows / RANGE_SELECTIVITY;
+
+                Some((access, estimated_rows, SEEK_COST + estimated_rows))
+            },
+            Filter::And(_, _) | Filter::Or(_, _) => None,
+        }
+    }
+
+    // None when a bound is open, so the keys can't be listed
+    fn key_range(lower: &Bound<DataType>, upper: &Bound<DataType>) -> Option<std::ops::RangeInclusive<u32>> {
+        let first = match lower {
+            Bound::Included(DataType::Integer(key)) => *key,
+            Bound::Excluded(DataType::Integer(key)) => key.checked_add(1)?,
+            _ => return None,
+        };
+        let last = match upper {
+            Bound::Included(DataType::Integer(key)) => *key,
+            // Below zero there's nothing to list, which a full scan finds as quickly
+            Bound::Excluded(DataType::Integer(key)) => key.checked_sub(1)?,
+            _ => return None,
+        };
+
+        Some(first..=last)
+    }
+
+    fn and_all<'a>(filters: impl Iterator<Item = &'a Filter>) -> Option<Filter> {
+        filters.cloned().reduce(Filter::and)
+    }
+}
+
+impl QueryPlan {
+    pub fn access(&self) -> &Access {
+        &self.access
+    }
+
+    pub fn residual(&self) -> Option<&Filter> {
+        self.residual.as_ref()
+    }
+
+    pub fn estimated_rows(&self) -> usize {
+        self.estimated_rows
+    }
+
+    // One line for the access path and, if anything's left to check, one for the filter applied after it
+    pub fn explain(&self) -> String {
+        let mut lines = vec![self.explain_access()];
+        if let Some(residual) = &self.residual {
+            lines.push(format!("Filter {}", residual));
+        }
+
+        lines.join("\n")
+    }
+
+    pub(crate) fn explain_access(&self) -> String {
+        let access = match (&self.access, &self.access_filter) {
+            (Access::KeySeek(keys), Some(filter)) => format!("Key seek on {} ({} key{})", filter, keys.len(), if keys.len() == 1 { "" } else { "s" }),
+            (Access::IndexSeek { .. }, Some(filter)) => format!("Index seek on {}", filter),
+            (Access::IndexRangeScan { .. }, Some(filter)) => format!("Index range scan on {}", filter),
+            _ => String::from("Full scan"),
+        };
+
+        format!("{}, about {} of {} rows", access, self.estimated_rows, self.total_rows)
+    }
+
+    // Rows as the search methods return them, row head first
+    pub fn rows(&self, snapshot: &Snapshot, columns: &[(String, ColumnType)]) -> Vec<Vec<DataType>> {
+        let keep = |row: &Vec<DataType>| self.residual.as_ref().is_none_or(|residual| residual.matches(columns, &row[1..]));
+
+        let rows = match &self.access {
+            Access::KeySeek(keys) => keys
+                .iter()
+                .filter_map(|key| snapshot.search_by_primary_index(DataType::Integer(*key)))
+                .flatten()
+                .collect(),
+            Access::IndexSeek { column, value } => snapshot.search_by_secondary_index(value.clone(), column).unwrap_or_default(),
+            Access::IndexRangeScan { column, lower, upper } => {
+                snapshot.search_range_by_secondary_index(column, lower.as_ref(), upper.as_ref()).unwrap_or_default()
+            },
+            Access::FullScan => return snapshot.rows().filter(keep).collect(),
+        };
+
+        rows.into_iter().filter(keep).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use crate::db::{db::PocketDB, query::filter::Filter, shared::enums::{ColumnType, DataType}};
+
+    use super::Access;
+
+    fn database() -> PocketDB {
+        let db = PocketDB::in_memory();
+        db.add_indexed_column("company", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+        // Scrambled so the companies don't arrive in order
+        let rows = (1..=300).map(|key| (key, PocketDB::row(key, vec![
+            PocketDB::cell(DataType::Text(format!("company {:02}", key * 7919 % 31))),
+            PocketDB::cell(DataType::Integer(key % 60)),
+        ]))).collect();
+        assert!(db.insert_batch(rows).iter().all(|result| result.is_ok()));
+
+        db
+    }
+
+    fn keys(rows: Vec<Vec<DataType>>) -> Vec<u32> {
+        let mut keys: Vec<u32> = rows.iter().map(|row| match row[1] {
+            DataType::Integer(key) => key,
+            ref value => panic!("Expected a key but found {:?}", value),
+        }).collect();
+        keys.sort();
+        keys
+    }
+
+    // What a full scan finds, to check each plan against
+    fn scanned(db: &PocketDB, filter: &Filter) -> Vec<u32> {
+        let snapshot = db.snapshot();
+        let columns = snapshot.columns();
+
+        keys(snapshot.rows().filter(|row| filter.matches(&columns, &row[1..])).collect())
+    }
+
+    #[test]
+    fn key_ranges_seek_the_keys() {
+        let db = database();
+
+        let plan = db.plan(&Filter::range("key", 10..20).and(Filter::range("age", 15..))).unwrap();
+        assert_eq!(plan.access(), &Access::KeySeek((10..20).collect()));
+        assert_eq!(plan.explain(), "Key seek on key >= 10 AND key < 20 (10 keys), about 10 of 300 rows\nFilter age >= 15");
+    }
+
+    #[test]
+    fn unbounded_key_ranges_scan_the_table() {
+        let db = database();
+
+        let unbounded = Filter::Range(String::from("key"), Bound::Excluded(DataType::Integer(250)), Bound::Unbounded);
+        assert_eq!(db.plan(&unbounded).unwrap().access(), &Access::FullScan);
+    }
+
+    #[test]
+    fn key_ranges_too_long_to_seek_scan_the_table() {
+        let db = database();
+
+        let everything = Filter::Range(String::from("key"), Bound::Included(DataType::Integer(0)), Bound::Included(DataType::Integer(u32::MAX)));
+        assert_eq!(db.plan(&everything).unwrap().access(), &Access::FullScan);
+    }
+
+    #[test]
+    fn empty_key_ranges_seek_no_keys() {
+        let db = database();
+
+        let plan = db.plan(&Filter::range("key", 20..10)).unwrap();
+        assert_eq!(plan.access(), &Access::KeySeek(vec![]));
+        assert_eq!(plan.estimated_rows(), 0);
+    }
+
+    #[test]
+    fn equality_on_an_indexed_column_seeks_the_index() {
+        let db = database();
+
+        let plan = db.plan(&Filter::eq("age", 3u32).and(Filter::eq("company", "company 07"))).unwrap();
+        assert_eq!(plan.access(), &Access::IndexSeek { column: String::from("company"), value: DataType::from("company 07") });
+        assert_eq!(plan.residual(), Some(&Filter::eq("age", 3u32)));
+    }
+
+    #[test]
+    fn ranges_on_an_indexed_column_scan_the_index() {
+        let db = database();
+
+        let plan = db.plan(&Filter::range("company", "company 03"..="company 05")).unwrap();
+        assert!(matches!(plan.access(), Access::IndexRangeScan { .. }));
+    }
+
+    #[test]
+    fn filters_without_a_usable_path_scan_the_table() {
+        let db = database();
+
+        let plan = db.plan(&Filter::range("age", 10..20).or(Filter::eq("key", 5u32))).unwrap();
+        assert_eq!(plan.access(), &Access::FullScan);
+        assert_eq!(plan.estimated_rows(), 300);
+    }
+
+    #[test]
+    fn every_plan_finds_what_a_full_scan_finds() {
+        let db = database();
+        let filters = [
+            Filter::range("key", 10..20).and(Filter::range("age", 15..)),
+            Filter::eq("age", 3u32).and(Filter::eq("company", "company 07")),
+            Filter::range("company", "company 03"..="company 05"),
+            Filter::range("company", .."company 02"),
+            Filter::range("age", 10..20).or(Filter::eq("key", 5u32)),
+            Filter::Range(String::from("key"), Bound::Excluded(DataType::Integer(250)), Bound::Unbounded),
+        ];
+
+        for filter in filters {
+            assert_eq!(keys(db.find(&filter).unwrap()), scanned(&db, &filter), "{}", filter);
+        }
+    }
+
+    #[test]
+    fn plans_of_values_of_the_wrong_type_fail() {
+        let db = database();
+
+        assert_eq!(db.plan(&Filter::eq("age", "old")).err(), Some(String::from("Expected integer for column age but found text")));
+    }
+
+    #[test]
+    fn plans_of_missing_columns_fail() {
+        let db = database();
+
+        assert_eq!(db.plan(&Filter::eq("height", 3u32)).err(), Some(String::from("No column named height")));
+    }
+}
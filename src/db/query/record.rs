@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use crate::db::shared::enums::{ColumnType, DataType};
+
+// A row's values by column name. The column names and types are read once from the table's `Columns` and shared by
+// every record a query returns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    columns: Arc<Vec<(String, ColumnType)>>,
+    values: Vec<DataType>,
+}
+
+impl Record {
+    pub(crate) fn new(columns: Arc<Vec<(String, ColumnType)>>, values: Vec<DataType>) -> Record {
+        Record { columns, values }
+    }
+
+    pub fn get(&self, column: &str) -> Option<&DataType> {
+        self.columns.iter().position(|(name, _)| name == column).and_then(|i| self.values.get(i))
+    }
+
+    pub fn integer(&self, column: &str) -> Option<u32> {
+        match self.get(column)? {
+            DataType::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn text(&self, column: &str) -> Option<&str> {
+        match self.get(column)? {
+            DataType::Text(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn bool(&self, column: &str) -> Option<bool> {
+        match self.get(column)? {
+            DataType::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    // The record's key, if the key column wasn't left out of the selection
+    pub fn key(&self) -> Option<u32> {
+        self.integer("key")
+    }
+
+    pub fn columns(&self) -> &[(String, ColumnType)] {
+        &self.columns
+    }
+
+    pub fn values(&self) -> &[DataType] {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Vec<DataType> {
+        self.values
+    }
+}
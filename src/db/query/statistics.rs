@@ -0,0 +1,61 @@
+use crate::db::{b_tree::btree::BTree, meta::vacuum::BlockSource, shared::constants::{node::NODE_SIZE, node_key::NODE_MAX_KEYS}};
+
+// Sizes the planner estimates costs from. The row count is kept exact on every write; the rest isn't maintained on
+// write but recomputed from the trees' nodes every so many writes, outside the write lock, so it lags behind the
+// table in between. See `STATISTICS_REFRESH_RATIO`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Statistics {
+    pub rows: usize,
+    pub primary: IndexStatistics,
+    pub indexes: Vec<IndexStatistics>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct IndexStatistics {
+    pub column: String,
+    pub height: usize,
+    pub nodes: usize,
+    pub keys: usize,
+    pub distinct_keys: usize,
+    pub rows: usize,
+    // Counts of distinct keys by how many rows share them, in powers of two: the first counts keys with one row,
+    // the second keys with two or three, the third four to seven and so on. The last also counts anything longer.
+    pub overflow_lengths: Vec<usize>,
+}
+
+impl Statistics {
+    pub fn index(&self, column: &str) -> Option<&IndexStatistics> {
+        self.indexes.iter().find(|index| index.column == column)
+    }
+
+    // Walks every node of the primary tree and of each secondary index, in list order. A secondary root offset of 0
+    // is an index with no rows yet.
+    pub(crate) fn count_trees(source: &mut dyn BlockSource, primary_root: Option<(u32, usize)>, secondary_index_offsets: &[u32]) -> Vec<IndexStatistics> {
+        let mut primary = IndexStatistics::default();
+        if let Some((primary_root_offset, primary_root_size)) = primary_root {
+            BTree::statistics(source, primary_root_offset, primary_root_size, 0, &mut primary);
+        }
+
+        let mut items = vec![primary];
+        for secondary_index_offset in secondary_index_offsets {
+            let mut secondary = IndexStatistics::default();
+            if *secondary_index_offset > 0 {
+                BTree::statistics(source, *secondary_index_offset, NODE_SIZE, 0, &mut secondary);
+            }
+            items.push(secondary);
+        }
+
+        items
+    }
+}
+
+impl IndexStatistics {
+    // How full the nodes' keys are on average, from 0 to 1
+    pub fn average_fill(&self) -> f64 {
+        if self.nodes == 0 {
+            return 0.0;
+        }
+
+        self.keys as f64 / (self.nodes * NODE_MAX_KEYS) as f64
+    }
+}
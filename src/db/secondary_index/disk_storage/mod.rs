@@ -1 +1,2 @@
-pub mod secondary_index_list;
\ No newline at end of file
+pub mod secondary_index_list;
+pub mod statistics_list;
\ No newline at end of file
@@ -0,0 +1,132 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::db::{query::statistics::IndexStatistics, shared::{constants::statistics::{OVERFLOW_BUCKETS, STATISTICS_ITEM_FIELDS, STATISTICS_ITEM_SIZE, STATISTICS_LIST_META_DATA_SIZE, STATISTICS_LIST_ROWS_OFFSET, STATISTICS_LIST_ROWS_SIZE, STATISTICS_LIST_SIZE_OFFSET, STATISTICS_LIST_SIZE_SIZE, STATISTICS_LIST_WRITES_OFFSET, STATISTICS_LIST_WRITES_SIZE}, utils::bytes_to_u32}};
+
+// Written straight after the `SecondaryIndexList`, in the same block. Holds the table's row count, the writes since
+// the items were last counted and an item per tree: the primary tree first, then each secondary index in list
+// order. Items don't name their columns; those come from the list.
+#[derive(Debug, Clone)]
+pub struct StatisticsList(Vec<u8>);
+
+impl StatisticsList {
+    pub fn new() -> StatisticsList {
+        let bytes = vec![]
+            .into_iter()
+            .chain((STATISTICS_LIST_META_DATA_SIZE as u32).to_le_bytes())
+            .chain(0u32.to_le_bytes())
+            .chain(0u32.to_le_bytes())
+            .collect();
+
+        StatisticsList(bytes)
+    }
+
+    // None for blocks written before statistics were kept
+    pub fn from_bytes(bytes: &[u8]) -> Option<StatisticsList> {
+        if bytes.len() < STATISTICS_LIST_META_DATA_SIZE {
+            return None;
+        }
+
+        let (start, end) = StatisticsList::size_range();
+        let size = bytes_to_u32(&bytes[start..end]) as usize;
+        if size < STATISTICS_LIST_META_DATA_SIZE || size > bytes.len() || !(size - STATISTICS_LIST_META_DATA_SIZE).is_multiple_of(STATISTICS_ITEM_SIZE) {
+            return None;
+        }
+
+        Some(StatisticsList(bytes[..size].to_vec()))
+    }
+
+    pub fn rows(&self) -> usize {
+        let (start, end) = StatisticsList::rows_range();
+
+        bytes_to_u32(&(*self)[start..end]) as usize
+    }
+
+    pub fn set_rows(&mut self, rows: usize) {
+        let (start, end) = StatisticsList::rows_range();
+
+        (*self).splice(start..end, (rows as u32).to_le_bytes());
+    }
+
+    pub fn writes(&self) -> usize {
+        let (start, end) = StatisticsList::writes_range();
+
+        bytes_to_u32(&(*self)[start..end]) as usize
+    }
+
+    pub fn set_writes(&mut self, writes: usize) {
+        let (start, end) = StatisticsList::writes_range();
+
+        (*self).splice(start..end, (writes as u32).to_le_bytes());
+    }
+
+    pub fn num_items(&self) -> usize {
+        (self.len() - STATISTICS_LIST_META_DATA_SIZE) / STATISTICS_ITEM_SIZE
+    }
+
+    pub fn item(&self, i: usize) -> Option<IndexStatistics> {
+        if i >= self.num_items() {
+            return None;
+        }
+
+        let start = STATISTICS_LIST_META_DATA_SIZE + STATISTICS_ITEM_SIZE * i;
+        let fields: Vec<usize> = (*self)[start..start + STATISTICS_ITEM_SIZE]
+            .chunks(4)
+            .map(|field| bytes_to_u32(field) as usize)
+            .collect();
+
+        Some(IndexStatistics {
+            column: String::new(),
+            height: fields[0],
+            nodes: fields[1],
+            keys: fields[2],
+            distinct_keys: fields[3],
+            rows: fields[4],
+            overflow_lengths: fields[5..].to_vec(),
+        })
+    }
+
+    pub fn set_items(&mut self, items: &[IndexStatistics]) {
+        self.truncate(STATISTICS_LIST_META_DATA_SIZE);
+        for item in items {
+            let mut fields = vec![item.height, item.nodes, item.keys, item.distinct_keys, item.rows];
+            fields.extend((0..OVERFLOW_BUCKETS).map(|i| item.overflow_lengths.get(i).copied().unwrap_or(0)));
+            debug_assert_eq!(fields.len(), STATISTICS_ITEM_FIELDS);
+
+            self.extend(fields.into_iter().flat_map(|field| (field as u32).to_le_bytes()));
+        }
+
+        let (start, end) = StatisticsList::size_range();
+        let size = self.len() as u32;
+        (*self).splice(start..end, size.to_le_bytes());
+    }
+
+    pub fn data(&self) -> &[u8] {
+        self
+    }
+
+    fn size_range() -> (usize, usize) {
+        (STATISTICS_LIST_SIZE_OFFSET, STATISTICS_LIST_SIZE_OFFSET + STATISTICS_LIST_SIZE_SIZE)
+    }
+
+    fn rows_range() -> (usize, usize) {
+        (STATISTICS_LIST_ROWS_OFFSET, STATISTICS_LIST_ROWS_OFFSET + STATISTICS_LIST_ROWS_SIZE)
+    }
+
+    fn writes_range() -> (usize, usize) {
+        (STATISTICS_LIST_WRITES_OFFSET, STATISTICS_LIST_WRITES_OFFSET + STATISTICS_LIST_WRITES_SIZE)
+    }
+}
+
+impl Deref for StatisticsList {
+    type Target = Vec<u8>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for StatisticsList {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
@@ -1,30 +1,34 @@
 use crate::db::{b_tree::{btree::BTree, disk_storage::node::NodeIndexType}, file_stystem::pager::Pager};
 
-use super::disk_storage::secondary_index_list::SecondaryIndexList;
+use super::disk_storage::{secondary_index_list::SecondaryIndexList, statistics_list::StatisticsList};
 
 #[derive(Debug)]
 pub struct SecondaryIndex {
     secondary_index_trees: Vec<BTree>,
     secondary_index_list: SecondaryIndexList,
+    statistics_list: StatisticsList,
 }
 
 impl SecondaryIndex {
     pub fn new() -> SecondaryIndex {
-        SecondaryIndex { secondary_index_trees: vec![], secondary_index_list: SecondaryIndexList::new() }
+        SecondaryIndex { secondary_index_trees: vec![], secondary_index_list: SecondaryIndexList::new(), statistics_list: StatisticsList::new() }
     }
 
+    // The statistics follow the list in the same block. Blocks written before they were kept end with the list.
     pub fn from_bytes(bytes: Vec<u8>, pager: &mut Pager) -> SecondaryIndex {
-        let secondary_index_list = SecondaryIndexList::from_bytes(bytes);
+        let secondary_index_list = SecondaryIndexList::from_bytes(bytes.clone());
+        let statistics_list = StatisticsList::from_bytes(&bytes[secondary_index_list.size().min(bytes.len())..]).unwrap_or_else(StatisticsList::new);
         let mut secondary_index_trees = vec![];
         for i in 0..secondary_index_list.num_items() {
             if let Some((secondary_index_offset, indexed_column)) = secondary_index_list.item(i) {
-                let secondary_index_tree = BTree::new(Some(secondary_index_offset), Some(NodeIndexType::Secondary), Some(indexed_column), pager);
+                let secondary_index_offset = if secondary_index_offset > 0 { Some(secondary_index_offset) } else { None };
+                let secondary_index_tree = BTree::new(secondary_index_offset, Some(NodeIndexType::Secondary), Some(indexed_column), pager);
 
                 secondary_index_trees.push(secondary_index_tree);
             }
         }
 
-        SecondaryIndex { secondary_index_trees, secondary_index_list }
+        SecondaryIndex { secondary_index_trees, secondary_index_list, statistics_list }
     }
 
     pub fn add_secondary_index(&mut self, indexed_column: usize, pager: &mut Pager) {
@@ -62,7 +66,16 @@ impl SecondaryIndex {
         self.secondary_index_list.num_items()
     }
 
-    pub fn secondary_index_data(&self) -> &[u8] {
-        &self.secondary_index_list.data()
+    pub fn statistics_list(&self) -> &StatisticsList {
+        &self.statistics_list
+    }
+
+    pub fn statistics_list_mut(&mut self) -> &mut StatisticsList {
+        &mut self.statistics_list
+    }
+
+    // The list followed by the statistics, as they're written
+    pub fn secondary_index_data(&self) -> Vec<u8> {
+        [self.secondary_index_list.data(), self.statistics_list.data()].concat()
     }
 }
\ No newline at end of file
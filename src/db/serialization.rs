@@ -0,0 +1,338 @@
+use std::fmt;
+
+use serde::{de::{self, value::MapDeserializer, DeserializeOwned, IntoDeserializer, Visitor}, ser::{self, Impossible, Serialize, SerializeStruct}, forward_to_deserialize_any};
+
+use super::{db::PocketDB, shared::enums::{ColumnType, DataType}, table::disk_storage::row::Row};
+
+// Rows to and from any type serde can handle, matching its fields to columns by name. Integers of any width fit an
+// integer column as long as the value does, `char`s and unit enum variants are stored as text, and `Option` fields
+// are stored as their value: `None` has no column value to be stored as, so it's an error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SerdeError(String);
+
+impl fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for SerdeError {}
+
+impl ser::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> SerdeError {
+        SerdeError(msg.to_string())
+    }
+}
+
+impl de::Error for SerdeError {
+    fn custom<T: fmt::Display>(msg: T) -> SerdeError {
+        SerdeError(msg.to_string())
+    }
+}
+
+// Builds a row in the order of `columns`, the table's columns without the key. Every column needs a field and every
+// field a column, apart from a `key` field, which has to hold the row's key.
+pub(crate) fn to_row<T: Serialize>(key: u32, value: &T, columns: &[(String, ColumnType)]) -> Result<Row, String> {
+    let mut fields = value.serialize(StructSerializer { fields: vec![] }).map_err(|e| e.to_string())?;
+    if let Some(i) = fields.iter().position(|(field, _)| field == "key") {
+        match fields.remove(i) {
+            (_, DataType::Integer(value)) if value == key => {},
+            (_, DataType::Integer(value)) => return Err(format!("Field key is {} but the row's key is {}", value, key)),
+            _ => return Err(String::from("Field key has to be an integer")),
+        }
+    }
+    if let Some((field, _)) = fields.iter().find(|(field, _)| !columns.iter().any(|(name, _)| name == field)) {
+        return Err(format!("No column named {}", field));
+    }
+
+    let cells = columns
+        .iter()
+        .map(|(name, column_type)| {
+            let (_, value) = fields.iter().find(|(field, _)| field == name).ok_or(format!("No field for column {}", name))?;
+            let value_type = value.column_type();
+            if value_type != *column_type {
+                return Err(format!("Field {} is {} but column {} is {}", name, format!("{:?}", value_type).to_lowercase(), name, format!("{:?}", column_type).to_lowercase()));
+            }
+
+            Ok(PocketDB::cell(value.clone()))
+        })
+        .collect::<Result<_, String>>()?;
+
+    Ok(PocketDB::row(key, cells))
+}
+
+// `values` and `columns` are both key first
+pub(crate) fn from_values<T: DeserializeOwned>(values: Vec<DataType>, columns: &[(String, ColumnType)]) -> Result<T, String> {
+    let fields = columns.iter().map(|(name, _)| name.as_str()).zip(values.into_iter().map(ValueDeserializer));
+
+    T::deserialize(MapDeserializer::<_, SerdeError>::new(fields)).map_err(|e| e.to_string())
+}
+
+struct StructSerializer {
+    fields: Vec<(String, DataType)>,
+}
+
+impl ser::Serializer for StructSerializer {
+    type Ok = Vec<(String, DataType)>;
+    type Error = SerdeError;
+    type SerializeSeq = Impossible<Self::Ok, SerdeError>;
+    type SerializeTuple = Impossible<Self::Ok, SerdeError>;
+    type SerializeTupleStruct = Impossible<Self::Ok, SerdeError>;
+    type SerializeTupleVariant = Impossible<Self::Ok, SerdeError>;
+    type SerializeMap = Impossible<Self::Ok, SerdeError>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<Self::Ok, SerdeError>;
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, SerdeError> {
+        Ok(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_i8(self, _v: i8) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_i16(self, _v: i16) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_i32(self, _v: i32) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_i64(self, _v: i64) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_u8(self, _v: u8) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_u16(self, _v: u16) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_u32(self, _v: u32) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_u64(self, _v: u64) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_char(self, _v: char) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_str(self, _v: &str) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_none(self) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_unit(self) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, _variant: &'static str) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, _value: &T) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _index: u32, _variant: &'static str, _value: &T) -> Result<Self::Ok, SerdeError> { not_a_struct() }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerdeError> { not_a_struct() }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerdeError> { not_a_struct() }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, SerdeError> { not_a_struct() }
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, SerdeError> { not_a_struct() }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerdeError> { not_a_struct() }
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, SerdeError> { not_a_struct() }
+}
+
+impl SerializeStruct for StructSerializer {
+    type Ok = Vec<(String, DataType)>;
+    type Error = SerdeError;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), SerdeError> {
+        let value = value.serialize(ValueSerializer).map_err(|SerdeError(e)| SerdeError(format!("Field {}: {}", key, e)))?;
+        self.fields.push((key.to_string(), value));
+
+        Ok(())
+    }
+
+    fn end(self) -> Result<Self::Ok, SerdeError> {
+        Ok(self.fields)
+    }
+}
+
+fn not_a_struct<T>() -> Result<T, SerdeError> {
+    Err(SerdeError(String::from("Only structs can be stored as rows")))
+}
+
+struct ValueSerializer;
+
+impl ValueSerializer {
+    fn integer<T: TryInto<u32> + fmt::Display + Copy>(v: T) -> Result<DataType, SerdeError> {
+        v.try_into().map(DataType::Integer).map_err(|_| SerdeError(format!("{} doesn't fit an integer column", v)))
+    }
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = DataType;
+    type Error = SerdeError;
+    type SerializeSeq = Impossible<DataType, SerdeError>;
+    type SerializeTuple = Impossible<DataType, SerdeError>;
+    type SerializeTupleStruct = Impossible<DataType, SerdeError>;
+    type SerializeTupleVariant = Impossible<DataType, SerdeError>;
+    type SerializeMap = Impossible<DataType, SerdeError>;
+    type SerializeStruct = Impossible<DataType, SerdeError>;
+    type SerializeStructVariant = Impossible<DataType, SerdeError>;
+
+    fn serialize_bool(self, v: bool) -> Result<DataType, SerdeError> { Ok(DataType::Bool(v)) }
+    fn serialize_i8(self, v: i8) -> Result<DataType, SerdeError> { ValueSerializer::integer(v) }
+    fn serialize_i16(self, v: i16) -> Result<DataType, SerdeError> { ValueSerializer::integer(v) }
+    fn serialize_i32(self, v: i32) -> Result<DataType, SerdeError> { ValueSerializer::integer(v) }
+    fn serialize_i64(self, v: i64) -> Result<DataType, SerdeError> { ValueSerializer::integer(v) }
+    fn serialize_u8(self, v: u8) -> Result<DataType, SerdeError> { Ok(DataType::Integer(v.into())) }
+    fn serialize_u16(self, v: u16) -> Result<DataType, SerdeError> { Ok(DataType::Integer(v.into())) }
+    fn serialize_u32(self, v: u32) -> Result<DataType, SerdeError> { Ok(DataType::Integer(v)) }
+    fn serialize_u64(self, v: u64) -> Result<DataType, SerdeError> { ValueSerializer::integer(v) }
+    fn serialize_char(self, v: char) -> Result<DataType, SerdeError> { Ok(DataType::Text(v.to_string())) }
+    fn serialize_str(self, v: &str) -> Result<DataType, SerdeError> { Ok(DataType::Text(v.to_string())) }
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<DataType, SerdeError> { value.serialize(self) }
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<DataType, SerdeError> { value.serialize(self) }
+    fn serialize_unit_variant(self, _name: &'static str, _index: u32, variant: &'static str) -> Result<DataType, SerdeError> {
+        Ok(DataType::Text(variant.to_string()))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<DataType, SerdeError> { unsupported("a float") }
+    fn serialize_f64(self, _v: f64) -> Result<DataType, SerdeError> { unsupported("a float") }
+    fn serialize_bytes(self, _v: &[u8]) -> Result<DataType, SerdeError> { unsupported("bytes") }
+    fn serialize_none(self) -> Result<DataType, SerdeError> { unsupported("None") }
+    fn serialize_unit(self) -> Result<DataType, SerdeError> { unsupported("()") }
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<DataType, SerdeError> { unsupported("a unit struct") }
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _index: u32, _variant: &'static str, _value: &T) -> Result<DataType, SerdeError> { unsupported("an enum variant with fields") }
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, SerdeError> { unsupported("a sequence") }
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, SerdeError> { unsupported("a tuple") }
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, SerdeError> { unsupported("a tuple struct") }
+    fn serialize_tuple_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, SerdeError> { unsupported("an enum variant with fields") }
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, SerdeError> { unsupported("a map") }
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct, SerdeError> { unsupported("a struct") }
+    fn serialize_struct_variant(self, _name: &'static str, _index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, SerdeError> { unsupported("an enum variant with fields") }
+}
+
+fn unsupported<T>(what: &str) -> Result<T, SerdeError> {
+    Err(SerdeError(format!("Can't store {} in a column", what)))
+}
+
+struct ValueDeserializer(DataType);
+
+impl<'de> IntoDeserializer<'de, SerdeError> for ValueDeserializer {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        match self.0 {
+            DataType::Integer(value) => visitor.visit_u32(value),
+            DataType::Text(value) => visitor.visit_string(value),
+            DataType::Bool(value) => visitor.visit_bool(value),
+            DataType::Meta => visitor.visit_unit(),
+        }
+    }
+
+    // Stored values are never None
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value, SerdeError> {
+        match self.0 {
+            DataType::Text(variant) => visitor.visit_enum(variant.into_deserializer()),
+            _ => Err(SerdeError(String::from("Only text columns can be read as enums"))),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value, SerdeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use crate::db::{db::PocketDB, shared::enums::ColumnType};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Plan {
+        Free,
+        Team,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Person {
+        age: u8,
+        firstname: String,
+        plan: Plan,
+        active: bool,
+        nickname: Option<String>,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Name {
+        key: u32,
+        firstname: String,
+    }
+
+    fn database() -> PocketDB {
+        let db = PocketDB::in_memory();
+        db.add_column("firstname", ColumnType::Text).unwrap().add_indexed_column("plan", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap()
+            .add_column("active", ColumnType::Bool).unwrap().add_column("nickname", ColumnType::Text).unwrap();
+
+        db
+    }
+
+    fn person() -> Person {
+        Person { age: 30, firstname: String::from("Sammie"), plan: Plan::Team, active: true, nickname: Some(String::from("Sam")) }
+    }
+
+    #[test]
+    fn rows_round_trip_through_serde_by_field_name() {
+        let db = database();
+        db.insert_serde(1, &person()).unwrap();
+
+        assert_eq!(db.get_serde::<Person>(1), Ok(Some(person())));
+    }
+
+    #[test]
+    fn missing_keys_get_nothing() {
+        let db = database();
+        db.insert_serde(1, &person()).unwrap();
+
+        assert_eq!(db.get_serde::<Person>(2), Ok(None));
+    }
+
+    #[test]
+    fn structs_can_read_the_key_and_some_of_the_columns() {
+        let db = database();
+        db.insert_serde(1, &person()).unwrap();
+
+        assert_eq!(db.get_serde::<Name>(1), Ok(Some(Name { key: 1, firstname: String::from("Sammie") })));
+    }
+
+    #[test]
+    fn none_fields_cant_be_inserted() {
+        let db = database();
+
+        let person = Person { nickname: None, ..person() };
+        assert_eq!(db.insert_serde(2, &person), Err(String::from("Field nickname: Can't store None in a column")));
+    }
+
+    #[test]
+    fn structs_missing_a_column_cant_be_inserted() {
+        let db = database();
+
+        assert_eq!(db.insert_serde(2, &Name { key: 2, firstname: String::from("Alex") }).unwrap_err(), "No field for column plan");
+    }
+
+    #[test]
+    fn key_fields_must_match_the_rows_key() {
+        let db = database();
+
+        assert_eq!(db.insert_serde(2, &Name { key: 3, firstname: String::from("Alex") }).unwrap_err(), "Field key is 3 but the row's key is 2");
+    }
+
+    #[test]
+    fn only_structs_can_be_inserted() {
+        let db = database();
+
+        assert_eq!(db.insert_serde(2, &(1, 2)).unwrap_err(), "Only structs can be stored as rows");
+    }
+
+    #[test]
+    fn rows_only_read_back_into_structs() {
+        let db = database();
+        db.insert_serde(1, &person()).unwrap();
+
+        assert!(db.get_serde::<Vec<u32>>(1).is_err());
+    }
+}
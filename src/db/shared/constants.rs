@@ -138,4 +138,54 @@ pub mod free_list_item {
     pub const FREE_ITEM_DATA_SIZE: usize = 4;
     pub const FREE_ITEM_DATA_SIZE_OFFSET: usize = FREE_ITEM_DATA_OFFSET_OFFSET + FREE_ITEM_DATA_OFFSET;
     pub const FREE_ITEM_SIZE: usize = FREE_ITEM_DATA_OFFSET + FREE_ITEM_DATA_SIZE;
-}
\ No newline at end of file
+}
+
+pub mod page {
+    pub const PAGE_SIZE: usize = 4096;
+    pub const BUFFER_POOL_SIZE: usize = 256 * PAGE_SIZE;
+}
+
+pub mod durability {
+    pub const CHECKPOINT_INTERVAL: usize = 64;
+}
+
+pub mod lock {
+    use std::time::Duration;
+
+    pub const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(10);
+}
+
+pub mod import {
+    pub const IMPORT_BATCH_SIZE: usize = 500;
+    pub const SCHEMA_SAMPLE_SIZE: usize = 100;
+}
+
+pub mod sql {
+    // A database holds one table, which SQL knows by this name
+    pub const TABLE_NAME: &str = "main";
+}
+
+pub mod statistics {
+    // Index statistics are recomputed once the writes since they last were reach 1 in this many rows
+    pub const STATISTICS_REFRESH_RATIO: usize = 10;
+    pub const OVERFLOW_BUCKETS: usize = 16;
+    pub const STATISTICS_LIST_SIZE_SIZE: usize = 4;
+    pub const STATISTICS_LIST_SIZE_OFFSET: usize = 0;
+    pub const STATISTICS_LIST_ROWS_SIZE: usize = 4;
+    pub const STATISTICS_LIST_ROWS_OFFSET: usize = STATISTICS_LIST_SIZE_OFFSET + STATISTICS_LIST_SIZE_SIZE;
+    pub const STATISTICS_LIST_WRITES_SIZE: usize = 4;
+    pub const STATISTICS_LIST_WRITES_OFFSET: usize = STATISTICS_LIST_ROWS_OFFSET + STATISTICS_LIST_ROWS_SIZE;
+    pub const STATISTICS_LIST_META_DATA_SIZE: usize = STATISTICS_LIST_SIZE_SIZE + STATISTICS_LIST_ROWS_SIZE + STATISTICS_LIST_WRITES_SIZE;
+    // Height, nodes, keys, distinct keys and rows, then the overflow length buckets, all u32
+    pub const STATISTICS_ITEM_FIELDS: usize = 5 + OVERFLOW_BUCKETS;
+    pub const STATISTICS_ITEM_SIZE: usize = STATISTICS_ITEM_FIELDS * 4;
+}
+
+pub mod planner {
+    // What a tree descent costs next to reading one row in a scan
+    pub const SEEK_COST: usize = 4;
+    // Key ranges wider than this are scanned rather than looked up key by key
+    pub const MAX_KEY_SEEKS: usize = 1000;
+    // Share of an index a range is guessed to cover, as 1 in this many rows
+    pub const RANGE_SELECTIVITY: usize = 3;
+}
@@ -1,7 +1,7 @@
 use std::str;
 use super::utils::bytes_to_u32;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ColumnType {
     Meta = 0,
     Integer = 1,
@@ -29,6 +29,33 @@ impl ColumnType {
         }
    }
 
+   // Reads a value typed as text, as it comes from CSV or a command line. Bools are true/false or 1/0.
+   pub fn parse(&self, value: &str) -> Result<DataType, String> {
+        match self {
+            ColumnType::Integer => value.trim()
+                .parse()
+                .map(DataType::Integer)
+                .map_err(|_| format!("Invalid integer {:?}", value)),
+            ColumnType::Bool => match value.trim().to_ascii_lowercase().as_str() {
+                "true" | "1" => Ok(DataType::Bool(true)),
+                "false" | "0" => Ok(DataType::Bool(false)),
+                _ => Err(format!("Invalid bool {:?}", value)),
+            },
+            ColumnType::Text => Ok(DataType::Text(value.to_string())),
+            ColumnType::Meta => Err(String::from("Meta columns hold no values")),
+        }
+   }
+
+   // What a column left unset by a `RowBuilder` holds
+   pub fn default_value(&self) -> DataType {
+        match self {
+            ColumnType::Integer => DataType::Integer(0),
+            ColumnType::Text => DataType::Text(String::new()),
+            ColumnType::Bool => DataType::Bool(false),
+            ColumnType::Meta => DataType::Meta,
+        }
+   }
+
    pub fn from_u32(column_type: u32) -> ColumnType {
         match column_type {
             1 => ColumnType::Integer,
@@ -39,7 +66,7 @@ impl ColumnType {
    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum DataType {
     Meta,
     Integer(u32),
@@ -70,6 +97,15 @@ impl DataType {
         }
     }
 
+    pub fn column_type(&self) -> ColumnType {
+        match self {
+            DataType::Integer(_) => ColumnType::Integer,
+            DataType::Text(_) => ColumnType::Text,
+            DataType::Bool(_) => ColumnType::Bool,
+            DataType::Meta => ColumnType::Meta,
+        }
+    }
+
     pub fn as_bytes(&self) -> Vec<u8> {
         match self {
             DataType::Integer(int) => int.to_le_bytes().to_vec(),
@@ -78,4 +114,169 @@ impl DataType {
             DataType::Meta => vec![0]
         }
     }
-}
\ No newline at end of file
+}
+
+// Values of different types don't compare
+impl PartialOrd for DataType {
+    fn partial_cmp(&self, other: &DataType) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (DataType::Integer(a), DataType::Integer(b)) => Some(a.cmp(b)),
+            (DataType::Text(a), DataType::Text(b)) => Some(a.cmp(b)),
+            (DataType::Bool(a), DataType::Bool(b)) => Some(a.cmp(b)),
+            (DataType::Meta, DataType::Meta) => Some(std::cmp::Ordering::Equal),
+            _ => None,
+        }
+    }
+}
+
+impl From<u32> for DataType {
+    fn from(value: u32) -> DataType {
+        DataType::Integer(value)
+    }
+}
+
+impl From<&str> for DataType {
+    fn from(value: &str) -> DataType {
+        DataType::Text(value.to_string())
+    }
+}
+
+impl From<String> for DataType {
+    fn from(value: String) -> DataType {
+        DataType::Text(value)
+    }
+}
+
+impl From<bool> for DataType {
+    fn from(value: bool) -> DataType {
+        DataType::Bool(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Aggregation {
+    Count,
+    Sum(String),
+    Min(String),
+    Max(String),
+    Avg(String),
+}
+
+impl std::fmt::Display for Aggregation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Aggregation::Count => write!(f, "count"),
+            Aggregation::Sum(column) => write!(f, "sum({})", column),
+            Aggregation::Min(column) => write!(f, "min({})", column),
+            Aggregation::Max(column) => write!(f, "max({})", column),
+            Aggregation::Avg(column) => write!(f, "avg({})", column),
+        }
+    }
+}
+
+// Counts and sums are `Integer`, averages `Float` and minimums and maximums the column's own values. `Null` is the
+// minimum, maximum or average of no rows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AggregateValue {
+    Integer(u64),
+    Float(f64),
+    Value(DataType),
+    Null,
+}
+
+impl std::fmt::Display for AggregateValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AggregateValue::Integer(value) => write!(f, "{}", value),
+            AggregateValue::Float(value) => write!(f, "{}", value),
+            AggregateValue::Value(DataType::Integer(value)) => write!(f, "{}", value),
+            AggregateValue::Value(DataType::Text(value)) => write!(f, "{}", value),
+            AggregateValue::Value(DataType::Bool(value)) => write!(f, "{}", value),
+            AggregateValue::Value(DataType::Meta) | AggregateValue::Null => write!(f, "null"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    Lru,
+    Clock,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadMode {
+    Buffered,
+    Mmap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Durability {
+    Full,
+    Normal,
+    Off,
+}
+
+// Writes and syncs are numbered from 1 in the order they reach the backend
+#[cfg(test)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault {
+    FailWrite(usize),
+    // Writes only the first `usize` bytes of the write before failing
+    TearWrite(usize, usize),
+    FailSync(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpenError {
+    // Another handle holds the file's lock and didn't let go within the lock timeout
+    Locked,
+    Io(String),
+}
+
+impl std::fmt::Display for OpenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OpenError::Locked => write!(f, "Database is locked by another handle"),
+            OpenError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RowBuilderError {
+    NoColumn(String),
+    WrongType { column: String, expected: ColumnType, found: ColumnType },
+    // The key has no default, so every row needs it set
+    NoKey,
+}
+
+impl std::fmt::Display for RowBuilderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RowBuilderError::NoColumn(column) => write!(f, "No column named {}", column),
+            RowBuilderError::WrongType { column, expected, found } => {
+                let (expected, found) = (format!("{:?}", expected).to_lowercase(), format!("{:?}", found).to_lowercase());
+                write!(f, "Expected {} for column {} but found {}", expected, column, found)
+            },
+            RowBuilderError::NoKey => write!(f, "No key set for the row"),
+        }
+    }
+}
+
+impl std::error::Error for RowBuilderError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SqlError {
+    // Lines and columns count from 1 and point at where parsing stopped
+    Parse { message: String, line: usize, column: usize },
+    Execute(String),
+}
+
+impl std::fmt::Display for SqlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SqlError::Parse { message, line, column } => write!(f, "{} at line {}, column {}", message, line, column),
+            SqlError::Execute(e) => write!(f, "{}", e),
+        }
+    }
+}
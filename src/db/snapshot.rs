@@ -0,0 +1,341 @@
+use std::{cmp::Ordering, ops::Bound};
+
+use super::{file_stystem::page, query::statistics::{IndexStatistics, Statistics}, secondary_index::disk_storage::statistics_list::StatisticsList, meta::{disk_storage::master::Master, vacuum::BlockSource}, b_tree::btree::BTree, db::PocketDB, shared::{constants::node::NODE_SIZE, enums::{ColumnType, DataType}, utils::bytes_to_u32}, table::{disk_storage::row::Row, table::Table}};
+
+// A read-only view of the database as of the commit it was taken at. Writers keep committing underneath it; the
+// blocks it can see are kept out of reuse until it's dropped, so its lookups never see a newer or half-written tree.
+#[derive(Debug)]
+pub struct Snapshot<'a> {
+    db: &'a PocketDB,
+    version: u64,
+    master: Master,
+    secondary_index_items: Vec<(u32, usize)>,
+    table_columns: Vec<u8>,
+    statistics_list: StatisticsList,
+}
+
+impl<'a> Snapshot<'a> {
+    pub(crate) fn new(
+        db: &'a PocketDB,
+        version: u64,
+        master: Master,
+        secondary_index_items: Vec<(u32, usize)>,
+        table_columns: Vec<u8>,
+        statistics_list: StatisticsList,
+    ) -> Snapshot<'a> {
+        Snapshot { db, version, master, secondary_index_items, table_columns, statistics_list }
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub(crate) fn master(&self) -> &Master {
+        &self.master
+    }
+
+    pub fn search_by_primary_index(&self, key: DataType) -> Option<Vec<Vec<DataType>>> {
+        let primary_root_offset = Some(self.master.primary_root_offset()).filter(|offset| *offset > 0)?;
+        let mut source = self;
+        let row_pointers = BTree::search(&mut source, primary_root_offset, self.master.primary_root_size() as usize, &key.as_bytes())?;
+
+        Some(self.read_rows(&row_pointers))
+    }
+
+    pub fn search_by_secondary_index(&self, key: DataType, column_name: &str) -> Option<Vec<Vec<DataType>>> {
+        let table = Table::from_bytes(self.table_columns.clone());
+        let (secondary_index_offset, _) = self.secondary_index(&table, column_name)?;
+        let mut source = self;
+        let row_pointers = BTree::search(&mut source, secondary_index_offset, NODE_SIZE, &key.as_bytes())?;
+
+        Some(self.read_rows(&row_pointers))
+    }
+
+    // Rows whose indexed value is within the bounds, for indexes whose byte order is their value order: text and
+    // bools. See `BTree::range_search`.
+    pub fn search_range_by_secondary_index(&self, column_name: &str, lower: Bound<&DataType>, upper: Bound<&DataType>) -> Option<Vec<Vec<DataType>>> {
+        let table = Table::from_bytes(self.table_columns.clone());
+        let (secondary_index_offset, _) = self.secondary_index(&table, column_name)?;
+        let (lower, upper) = (lower.map(|value| value.as_bytes()), upper.map(|value| value.as_bytes()));
+
+        let mut source = self;
+        let mut row_pointers = vec![];
+        BTree::range_search(&mut source, secondary_index_offset, NODE_SIZE, lower.as_ref().map(|key| key.as_slice()), upper.as_ref().map(|key| key.as_slice()), &mut row_pointers);
+
+        Some(self.read_rows(&row_pointers))
+    }
+
+    // The number of rows, as kept on every insert and delete
+    pub fn count(&self) -> usize {
+        self.statistics_list.rows()
+    }
+
+    // The smallest and largest value of the key or an indexed column, or None for any other column. The key's come
+    // from the primary tree's nodes alone. An index's nodes only point at rows, so a text or bool index reads its
+    // first and last rows; an integer index, whose tree order isn't numeric, reads a row per key.
+    pub fn min_max(&self, column_name: &str) -> Option<Option<(DataType, DataType)>> {
+        let mut source = self;
+        let table = Table::from_bytes(self.table_columns.clone());
+
+        if column_name == "key" {
+            let mut keys = vec![];
+            if self.master.primary_root_offset() > 0 {
+                BTree::keys(&mut source, self.master.primary_root_offset(), self.master.primary_root_size() as usize, &mut keys);
+            }
+            let keys = keys.iter().map(|(key, _)| *key);
+
+            return Some(keys.clone().min().zip(keys.max()).map(|(min, max)| (DataType::Integer(min), DataType::Integer(max))));
+        }
+
+        let indexed_column = (0..table.num_columns()).find(|i| table.column(*i).0 == column_name)?;
+        let (secondary_index_offset, _) = *self.secondary_index_items.iter().find(|(_, i_col)| *i_col == indexed_column)?;
+        if secondary_index_offset == 0 {
+            return Some(None);
+        }
+
+        let row_pointers = if table.column(indexed_column).1 == ColumnType::Integer {
+            let mut keys = vec![];
+            BTree::keys(&mut source, secondary_index_offset, NODE_SIZE, &mut keys);
+            keys
+        } else {
+            [false, true]
+                .into_iter()
+                .filter_map(|last| BTree::edge_row(&mut source, secondary_index_offset, NODE_SIZE, last))
+                .collect()
+        };
+        let values: Vec<DataType> = self.read_rows(&row_pointers)
+            .into_iter()
+            .filter_map(|row| row.into_iter().nth(indexed_column))
+            .collect();
+
+        let min = values.iter().min_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+        let max = values.iter().max_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        Some(min.cloned().zip(max.cloned()))
+    }
+
+    // As of the last time the trees were counted, apart from the row count, which is always current
+    pub fn statistics(&self) -> Statistics {
+        let table = Table::from_bytes(self.table_columns.clone());
+        let item = |i: usize, column: String| IndexStatistics { column, ..self.statistics_list.item(i).unwrap_or_default() };
+
+        let indexes = self.secondary_index_items
+            .iter()
+            .enumerate()
+            .map(|(i, (_, indexed_column))| item(i + 1, table.column(*indexed_column).0))
+            .collect();
+
+        Statistics { rows: self.count(), primary: item(0, String::from("key")), indexes }
+    }
+
+    // Counts every tree as of the snapshot, and returns the counts with the writes they take in. See `refresh_statistics`
+    // on `PocketDB`.
+    pub(crate) fn count_statistics(&self) -> (Vec<IndexStatistics>, usize) {
+        let primary_root = Some((self.master.primary_root_offset(), self.master.primary_root_size() as usize)).filter(|(offset, _)| *offset > 0);
+        let secondary_index_offsets: Vec<u32> = self.secondary_index_items.iter().map(|(offset, _)| *offset).collect();
+        let mut source = self;
+
+        (Statistics::count_trees(&mut source, primary_root, &secondary_index_offsets), self.statistics_list.writes())
+    }
+
+    pub fn columns(&self) -> Vec<(String, ColumnType)> {
+        Table::from_bytes(self.table_columns.clone()).columns()
+    }
+
+    // Every row in primary-key order. Each row is read once, in file order, and the rows are sorted by key in
+    // memory.
+    pub fn rows(&self) -> impl Iterator<Item = Vec<DataType>> + '_ {
+        let mut row_pointers = vec![];
+        if self.master.primary_root_offset() > 0 {
+            let mut source = self;
+            BTree::row_pointers(&mut source, self.master.primary_root_offset(), self.master.primary_root_size() as usize, &mut row_pointers);
+        }
+
+        row_pointers.sort_by_key(|(row_offset, _)| *row_offset);
+        let mut keyed_rows: Vec<(u32, Row)> = self.read_blocks(&row_pointers)
+            .into_iter()
+            .flatten()
+            .map(|bytes| {
+                let row = Row::from_bytes(bytes);
+
+                (bytes_to_u32(row.primary_key_bytes()), row)
+            })
+            .collect();
+        // Tree order compares keys as little-endian bytes, not as numbers
+        keyed_rows.sort_by_key(|(key, _)| *key);
+
+        keyed_rows.into_iter().map(|(_, row)| Snapshot::typed_rows(&[&row]).remove(0))
+    }
+
+    // Reads the rows with one pager lock per page they're on
+    fn read_rows(&self, row_pointers: &[(u32, usize)]) -> Vec<Vec<DataType>> {
+        self.read_blocks(row_pointers)
+            .into_iter()
+            .flatten()
+            .map(|bytes| Snapshot::typed_rows(&[&Row::from_bytes(bytes)]).remove(0))
+            .collect()
+    }
+
+    // Blocks next to each other on the same page are read under one pager lock, so writers get a turn between pages
+    fn read_blocks(&self, blocks: &[(u32, usize)]) -> Vec<Option<Vec<u8>>> {
+        Snapshot::page_runs(blocks)
+            .flat_map(|run| {
+                let mut pager = self.db.pager();
+
+                run.iter().map(|(offset, size)| pager.read_from_file(*offset as usize, *size)).collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    fn page_runs(blocks: &[(u32, usize)]) -> impl Iterator<Item = &[(u32, usize)]> {
+        blocks.chunk_by(|(offset, _), (next_offset, _)| page::page_id(*offset as usize) == page::page_id(*next_offset as usize))
+    }
+
+    // Root offset and indexed column of the column's secondary index, if it has one with rows in it
+    fn secondary_index(&self, table: &Table, column_name: &str) -> Option<(u32, usize)> {
+        let indexed_column = (0..table.num_columns()).find(|i| table.column(*i).0 == column_name)?;
+
+        self.secondary_index_items
+            .iter()
+            .find(|(secondary_index_offset, i_col)| *i_col == indexed_column && *secondary_index_offset > 0)
+            .copied()
+    }
+
+    fn typed_rows(rows: &[&Row]) -> Vec<Vec<DataType>> {
+        rows.iter()
+            .map(|r| r.cells()
+                .iter()
+                .map(|c| c.to_typed_data())
+                .collect()
+            ).collect()
+    }
+}
+
+// Each block is read under its own pager lock, so a long walk over a snapshot doesn't hold up writers
+impl BlockSource for &Snapshot<'_> {
+    fn read_block(&mut self, offset: u32, size: usize) -> Option<Vec<u8>> {
+        self.db.pager().read_from_file(offset as usize, size)
+    }
+}
+
+impl Drop for Snapshot<'_> {
+    fn drop(&mut self) {
+        self.db.pager().release_snapshot(self.version);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{db::PocketDB, shared::enums::{ColumnType, DataType}};
+
+    fn insert(db: &PocketDB, key: u32) {
+        db.insert(key, PocketDB::row(key, vec![
+            PocketDB::cell(DataType::Text(format!("name {}", key % 5))),
+            PocketDB::cell(DataType::Integer(key)),
+        ])).unwrap();
+    }
+
+    // Rows 1 to 50, with ten rows to each name
+    fn database() -> PocketDB {
+        let db = PocketDB::in_memory();
+        db.add_indexed_column("name", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+        for key in 1..=50 {
+            insert(&db, key);
+        }
+
+        db
+    }
+
+    #[test]
+    fn snapshots_keep_the_rows_they_were_taken_with() {
+        let db = database();
+        let snapshot = db.snapshot();
+        for key in 51..=150 {
+            insert(&db, key);
+        }
+
+        for key in 1..=50 {
+            assert!(snapshot.search_by_primary_index(DataType::Integer(key)).is_some(), "snapshot lost row {}", key);
+        }
+    }
+
+    #[test]
+    fn snapshots_dont_see_later_inserts() {
+        let db = database();
+        let snapshot = db.snapshot();
+        insert(&db, 51);
+
+        assert!(snapshot.search_by_primary_index(DataType::Integer(51)).is_none());
+        assert_eq!(snapshot.rows().count(), 50);
+        assert!(db.search_by_primary_index(DataType::Integer(51)).is_some());
+    }
+
+    #[test]
+    fn snapshot_index_lookups_ignore_later_commits() {
+        let db = database();
+        let snapshot = db.snapshot();
+        for key in 51..=150 {
+            insert(&db, key);
+        }
+
+        assert_eq!(snapshot.search_by_secondary_index(DataType::Text(String::from("name 1")), "name").map(|rows| rows.len()), Some(10));
+        assert_eq!(db.search_by_secondary_index(DataType::Text(String::from("name 1")), "name").map(|rows| rows.len()), Some(30));
+    }
+
+    #[test]
+    fn snapshot_rows_come_in_key_order() {
+        let db = PocketDB::in_memory();
+        db.add_indexed_column("name", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+        // Keys above 255 put tree order, which compares little-endian bytes, out of numeric order
+        let keys: Vec<u32> = (1..=600).step_by(7).collect();
+        for key in keys.iter().rev() {
+            insert(&db, *key);
+        }
+
+        let row_keys: Vec<DataType> = db.snapshot().rows().map(|row| row[1].clone()).collect();
+        assert_eq!(row_keys, keys.into_iter().map(DataType::Integer).collect::<Vec<DataType>>());
+    }
+
+    // Deletes leave keys in internal nodes whose rows sit at the end of a leaf
+    #[test]
+    fn snapshot_rows_come_in_key_order_after_deletes() {
+        let db = PocketDB::in_memory();
+        db.add_indexed_column("name", ColumnType::Text).unwrap().add_column("age", ColumnType::Integer).unwrap();
+        for key in 1..=400 {
+            insert(&db, key);
+        }
+        for key in (1..=400).filter(|key| key % 3 == 0) {
+            db.delete_by_primary_index(DataType::Integer(key)).unwrap();
+        }
+
+        let row_keys: Vec<DataType> = db.snapshot().rows().map(|row| row[1].clone()).collect();
+        assert_eq!(row_keys, (1..=400).filter(|key| key % 3 != 0).map(DataType::Integer).collect::<Vec<DataType>>());
+    }
+
+    #[test]
+    fn open_snapshots_retain_freed_blocks() {
+        let db = database();
+        let snapshot = db.snapshot();
+        for key in 51..=150 {
+            insert(&db, key);
+        }
+
+        assert!(db.free_space_stats().retained_bytes > 0);
+        drop(snapshot);
+        assert_eq!(db.free_space_stats().retained_bytes, 0);
+    }
+
+    #[test]
+    fn commits_made_under_a_snapshot_survive_it() {
+        let db = database();
+        let snapshot = db.snapshot();
+        for key in 51..=150 {
+            insert(&db, key);
+        }
+        drop(snapshot);
+
+        for key in 1..=150 {
+            assert!(db.search_by_primary_index(DataType::Integer(key)).is_some(), "lost row {}", key);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+
+use crate::db::{query::filter::Filter, shared::enums::{ColumnType, DataType}};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement {
+    CreateTable { table: String, columns: Vec<ColumnDefinition> },
+    CreateIndex { table: String, column: String },
+    // With no column list the values follow the table's columns, key first
+    Insert { table: String, columns: Option<Vec<String>>, rows: Vec<Vec<DataType>> },
+    Select {
+        table: String,
+        projection: Projection,
+        filter: Option<Expression>,
+        order_by: Option<OrderBy>,
+        limit: Option<usize>,
+        offset: Option<usize>,
+    },
+    Update { table: String, assignments: Vec<(String, DataType)>, filter: Option<Expression> },
+    Delete { table: String, filter: Option<Expression> },
+    // How a SELECT, UPDATE or DELETE would find its rows
+    Explain(Box<Statement>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnDefinition {
+    pub name: String,
+    pub column_type: ColumnType,
+    pub primary_key: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Projection {
+    All,
+    Columns(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderBy {
+    pub column: String,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expression {
+    Column(String),
+    Literal(DataType),
+    Compare(Box<Expression>, Operator, Box<Expression>),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expression::Column(name) => write!(f, "{}", name),
+            Expression::Literal(value) => write!(f, "{}", Filter::literal(value)),
+            Expression::Compare(left, operator, right) => {
+                let operator = match operator {
+                    Operator::Equal => "=",
+                    Operator::NotEqual => "!=",
+                    Operator::Less => "<",
+                    Operator::LessOrEqual => "<=",
+                    Operator::Greater => ">",
+                    Operator::GreaterOrEqual => ">=",
+                };
+                write!(f, "{} {} {}", left, operator, right)
+            },
+            Expression::And(left, right) => write!(f, "({} AND {})", left, right),
+            Expression::Or(left, right) => write!(f, "({} OR {})", left, right),
+            Expression::Not(expression) => write!(f, "NOT {}", expression),
+        }
+    }
+}
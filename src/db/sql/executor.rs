@@ -0,0 +1,571 @@
+use std::{cmp::Ordering, collections::HashSet, ops::Bound};
+
+use crate::db::{db::PocketDB, query::{filter::Filter, planner::{Planner, QueryPlan}}, shared::{constants::sql::TABLE_NAME, enums::{ColumnType, DataType}}, snapshot::Snapshot, table::disk_storage::row::Row};
+
+use super::{ast::{ColumnDefinition, Expression, Operator, OrderBy, Projection, Statement}, result_set::ResultSet};
+
+// Runs parsed statements against the database's one table. Rows are found with a primary key lookup when the WHERE
+// clause pins the key, a secondary index lookup when it pins an indexed column, and a full scan otherwise; the whole
+// WHERE clause is then checked against every row found.
+pub struct Executor;
+
+impl Executor {
+    pub fn execute(db: &PocketDB, statement: Statement) -> Result<ResultSet, String> {
+        match statement {
+            Statement::CreateTable { table, columns } => Executor::create_table(db, &table, &columns),
+            Statement::CreateIndex { table, column } => {
+                Executor::check_table(&table)?;
+                db.add_index(&column).map(|_| ResultSet::default())
+            },
+            Statement::Insert { table, columns, rows } => Executor::insert(db, &table, columns, rows),
+            Statement::Select { table, projection, filter, order_by, limit, offset } => {
+                Executor::select(db, &table, projection, filter, order_by, limit, offset)
+            },
+            Statement::Update { table, assignments, filter } => Executor::update(db, &table, &assignments, filter),
+            Statement::Delete { table, filter } => Executor::delete(db, &table, filter),
+            Statement::Explain(statement) => Executor::explain(db, *statement),
+        }
+    }
+
+    // One row per line of the plan, in a single `plan` column
+    fn explain(db: &PocketDB, statement: Statement) -> Result<ResultSet, String> {
+        let (table, filter) = match statement {
+            Statement::Select { table, filter, .. } | Statement::Update { table, filter, .. } | Statement::Delete { table, filter } => (table, filter),
+            _ => return Err(String::from("Only SELECT, UPDATE and DELETE can be explained")),
+        };
+        Executor::check_table(&table)?;
+        let columns = db.columns();
+        if let Some(filter) = &filter {
+            Executor::check_columns(filter, &columns)?;
+        }
+
+        let snapshot = db.snapshot();
+        let mut lines = match Executor::plan(&snapshot, &columns, filter.as_ref())? {
+            Some(plan) => vec![plan.explain_access()],
+            None => {
+                let rows = snapshot.statistics().rows;
+                vec![format!("Full scan, about {} of {} rows", rows, rows)]
+            },
+        };
+        if let Some(filter) = filter {
+            lines.push(format!("Filter {}", filter));
+        }
+
+        let rows = lines.into_iter().map(|line| vec![DataType::Text(line)]).collect();
+
+        Ok(ResultSet::new(vec![(String::from("plan"), ColumnType::Text)], rows))
+    }
+
+    // The key column is always there, so it can be left out, but if it's given it has to be `key INTEGER PRIMARY KEY`
+    fn create_table(db: &PocketDB, table: &str, definitions: &[ColumnDefinition]) -> Result<ResultSet, String> {
+        Executor::check_table(table)?;
+        if db.columns().len() > 1 || db.snapshot().rows().next().is_some() {
+            return Err(format!("Table {} already exists", table));
+        }
+
+        let mut names = HashSet::new();
+        for definition in definitions {
+            if !names.insert(definition.name.as_str()) {
+                return Err(format!("Column {} is defined twice", definition.name));
+            }
+
+            let is_key = definition.name == "key";
+            if is_key != definition.primary_key || (is_key && definition.column_type != ColumnType::Integer) {
+                return Err(String::from("The primary key must be defined as key INTEGER PRIMARY KEY"));
+            }
+        }
+
+        for definition in definitions.iter().filter(|definition| !definition.primary_key) {
+            db.add_column(&definition.name, definition.column_type)?;
+        }
+
+        Ok(ResultSet::default())
+    }
+
+    fn insert(db: &PocketDB, table: &str, names: Option<Vec<String>>, rows: Vec<Vec<DataType>>) -> Result<ResultSet, String> {
+        Executor::check_table(table)?;
+        let columns = db.columns();
+        let names = names.unwrap_or_else(|| columns.iter().map(|(name, _)| name.clone()).collect());
+        if let Some(name) = names.iter().find(|name| !columns.iter().any(|(column, _)| column == *name)) {
+            return Err(format!("No column named {}", name));
+        }
+
+        // Every row is checked before any is inserted
+        let mut keys = HashSet::new();
+        let mut batch = vec![];
+        for values in rows {
+            if values.len() != names.len() {
+                return Err(format!("Expected {} values but found {}", names.len(), values.len()));
+            }
+
+            let mut values = columns
+                .iter()
+                .map(|(column, column_type)| {
+                    let position = names.iter().position(|name| name == column).ok_or(format!("Missing value for column {}", column))?;
+                    Executor::check_type(column, *column_type, &values[position])?;
+
+                    Ok(values[position].clone())
+                })
+                .collect::<Result<Vec<DataType>, String>>()?;
+
+            let key = Executor::key(&values);
+            if !keys.insert(key) || Executor::key_exists(db, key) {
+                return Err(format!("Row with key {} already exists", key));
+            }
+
+            values.remove(0);
+            batch.push((key, PocketDB::row(key, values.into_iter().map(PocketDB::cell).collect())));
+        }
+
+        let inserted = batch.len();
+        db.insert_batch(batch).into_iter().collect::<Result<Vec<()>, String>>()?;
+
+        Ok(ResultSet::affected(inserted))
+    }
+
+    fn select(
+        db: &PocketDB,
+        table: &str,
+        projection: Projection,
+        filter: Option<Expression>,
+        order_by: Option<OrderBy>,
+        limit: Option<usize>,
+        offset: Option<usize>,
+    ) -> Result<ResultSet, String> {
+        Executor::check_table(table)?;
+        let columns = db.columns();
+        let positions = match projection {
+            Projection::All => (0..columns.len()).collect(),
+            Projection::Columns(names) => names
+                .iter()
+                .map(|name| Executor::position(&columns, name))
+                .collect::<Result<Vec<usize>, String>>()?,
+        };
+
+        let mut rows = Executor::matching_rows(db, &columns, filter.as_ref())?;
+        if let Some(order_by) = order_by {
+            let position = Executor::position(&columns, &order_by.column)?;
+            rows.sort_by(|a, b| {
+                let ordering = a[position].partial_cmp(&b[position]).unwrap_or(Ordering::Equal);
+                if order_by.descending { ordering.reverse() } else { ordering }
+            });
+        }
+
+        let rows = rows
+            .into_iter()
+            .skip(offset.unwrap_or(0))
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|values| positions.iter().map(|position| values[*position].clone()).collect())
+            .collect();
+        let columns = positions.iter().map(|position| columns[*position].clone()).collect();
+
+        Ok(ResultSet::new(columns, rows))
+    }
+
+    // Each row is deleted and inserted again with its new values, which may include a new key
+    fn update(db: &PocketDB, table: &str, assignments: &[(String, DataType)], filter: Option<Expression>) -> Result<ResultSet, String> {
+        Executor::check_table(table)?;
+        let columns = db.columns();
+        let assignments = assignments
+            .iter()
+            .map(|(name, value)| {
+                let position = Executor::position(&columns, name)?;
+                Executor::check_type(name, columns[position].1, value)?;
+
+                Ok((position, value.clone()))
+            })
+            .collect::<Result<Vec<(usize, DataType)>, String>>()?;
+
+        let rows = Executor::matching_rows(db, &columns, filter.as_ref())?;
+        if let Some((_, key)) = assignments.iter().find(|(position, _)| *position == 0) {
+            let key = Executor::key(std::slice::from_ref(key));
+            if rows.len() > 1 {
+                return Err(format!("Can't assign key {} to {} rows, keys are unique", key, rows.len()));
+            }
+            if rows.iter().any(|values| Executor::key(values) != key) && Executor::key_exists(db, key) {
+                return Err(format!("Row with key {} already exists", key));
+            }
+        }
+
+        // Every new row is checked before the first one is deleted, so an update either replaces every row or none
+        let updated_rows = rows
+            .iter()
+            .cloned()
+            .map(|mut values| {
+                for (position, value) in &assignments {
+                    values[*position] = value.clone();
+                }
+
+                let key = Executor::key(&values);
+                values.remove(0);
+                let row = PocketDB::row(key, values.into_iter().map(PocketDB::cell).collect());
+                db.check_row(&row)?;
+
+                Ok((key, row))
+            })
+            .collect::<Result<Vec<(u32, Row)>, String>>()?;
+
+        for values in &rows {
+            db.delete_by_primary_index(values[0].clone())?;
+        }
+        for (key, row) in updated_rows {
+            db.insert(key, row)?;
+        }
+
+        Ok(ResultSet::affected(rows.len()))
+    }
+
+    fn delete(db: &PocketDB, table: &str, filter: Option<Expression>) -> Result<ResultSet, String> {
+        Executor::check_table(table)?;
+        let columns = db.columns();
+
+        let rows = Executor::matching_rows(db, &columns, filter.as_ref())?;
+        for values in &rows {
+            db.delete_by_primary_index(values[0].clone())?;
+        }
+
+        Ok(ResultSet::affected(rows.len()))
+    }
+
+    // Values of every row the filter keeps, key first
+    fn matching_rows(db: &PocketDB, columns: &[(String, ColumnType)], filter: Option<&Expression>) -> Result<Vec<Vec<DataType>>, String> {
+        if let Some(filter) = filter {
+            Executor::check_columns(filter, columns)?;
+        }
+
+        // Typed rows start with the row head, and can have padding after the last column
+        let values = |row: Vec<DataType>| -> Vec<DataType> { row.into_iter().skip(1).take(columns.len()).collect() };
+        let snapshot = db.snapshot();
+        let rows: Vec<Vec<DataType>> = match Executor::plan(&snapshot, columns, filter)? {
+            Some(plan) => plan.rows(&snapshot, columns).into_iter().map(values).collect(),
+            None => snapshot.rows().map(values).collect(),
+        };
+
+        let mut matching_rows = vec![];
+        for row in rows {
+            let matches = match filter {
+                Some(filter) => Executor::truth(Executor::evaluate(filter, columns, &row)?)?,
+                None => true,
+            };
+            if matches {
+                matching_rows.push(row);
+            }
+        }
+
+        Ok(matching_rows)
+    }
+
+    // Plans from the comparisons of a column with a value among the WHERE clause's top-level ANDs. None when there
+    // are none, which means a full scan.
+    fn plan(snapshot: &Snapshot, columns: &[(String, ColumnType)], filter: Option<&Expression>) -> Result<Option<QueryPlan>, String> {
+        let mut conjuncts = vec![];
+        if let Some(filter) = filter {
+            Executor::conjuncts(filter, &mut conjuncts);
+        }
+
+        let filter = conjuncts
+            .into_iter()
+            .filter_map(|conjunct| {
+                let (column, operator, value) = match conjunct {
+                    Expression::Compare(left, operator, right) => match (left.as_ref(), right.as_ref()) {
+                        (Expression::Column(column), Expression::Literal(value)) => (column, *operator, value),
+                        (Expression::Literal(value), Expression::Column(column)) => (column, Executor::flip(*operator), value),
+                        _ => return None,
+                    },
+                    _ => return None,
+                };
+                // Mismatched types are left for `evaluate` to report
+                if !columns.iter().any(|(name, column_type)| name == column && column_type.matches(value)) {
+                    return None;
+                }
+
+                match operator {
+                    Operator::Equal => Some(Filter::eq(column, value.clone())),
+                    Operator::NotEqual => None,
+                    Operator::Less => Some(Filter::range(column, ..value.clone())),
+                    Operator::LessOrEqual => Some(Filter::range(column, ..=value.clone())),
+                    Operator::Greater => Some(Filter::Range(column.clone(), Bound::Excluded(value.clone()), Bound::Unbounded)),
+                    Operator::GreaterOrEqual => Some(Filter::range(column, value.clone()..)),
+                }
+            })
+            .reduce(Filter::and);
+
+        match filter {
+            Some(filter) => Planner::plan(&filter, columns, &snapshot.statistics()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    // `5 < age` is `age > 5`
+    fn flip(operator: Operator) -> Operator {
+        match operator {
+            Operator::Less => Operator::Greater,
+            Operator::LessOrEqual => Operator::GreaterOrEqual,
+            Operator::Greater => Operator::Less,
+            Operator::GreaterOrEqual => Operator::LessOrEqual,
+            operator => operator,
+        }
+    }
+
+    fn conjuncts<'a>(expression: &'a Expression, conjuncts: &mut Vec<&'a Expression>) {
+        match expression {
+            Expression::And(left, right) => {
+                Executor::conjuncts(left, conjuncts);
+                Executor::conjuncts(right, conjuncts);
+            },
+            expression => conjuncts.push(expression),
+        }
+    }
+
+    fn check_columns(expression: &Expression, columns: &[(String, ColumnType)]) -> Result<(), String> {
+        match expression {
+            Expression::Column(name) => Executor::position(columns, name).map(|_| ()),
+            Expression::Literal(_) => Ok(()),
+            Expression::Compare(left, _, right) | Expression::And(left, right) | Expression::Or(left, right) => {
+                Executor::check_columns(left, columns).and_then(|_| Executor::check_columns(right, columns))
+            },
+            Expression::Not(expression) => Executor::check_columns(expression, columns),
+        }
+    }
+
+    fn evaluate(expression: &Expression, columns: &[(String, ColumnType)], row: &[DataType]) -> Result<DataType, String> {
+        let value = match expression {
+            Expression::Column(name) => row[Executor::position(columns, name)?].clone(),
+            Expression::Literal(value) => value.clone(),
+            Expression::Compare(left, operator, right) => {
+                let left = Executor::evaluate(left, columns, row)?;
+                let right = Executor::evaluate(right, columns, row)?;
+                let ordering = left.partial_cmp(&right)
+                    .ok_or(format!("Can't compare {} with {}", Executor::type_name(&left), Executor::type_name(&right)))?;
+
+                DataType::Bool(match operator {
+                    Operator::Equal => ordering == Ordering::Equal,
+                    Operator::NotEqual => ordering != Ordering::Equal,
+                    Operator::Less => ordering == Ordering::Less,
+                    Operator::LessOrEqual => ordering != Ordering::Greater,
+                    Operator::Greater => ordering == Ordering::Greater,
+                    Operator::GreaterOrEqual => ordering != Ordering::Less,
+                })
+            },
+            Expression::And(left, right) => DataType::Bool(
+                Executor::truth(Executor::evaluate(left, columns, row)?)? && Executor::truth(Executor::evaluate(right, columns, row)?)?
+            ),
+            Expression::Or(left, right) => DataType::Bool(
+                Executor::truth(Executor::evaluate(left, columns, row)?)? || Executor::truth(Executor::evaluate(right, columns, row)?)?
+            ),
+            Expression::Not(expression) => DataType::Bool(!Executor::truth(Executor::evaluate(expression, columns, row)?)?),
+        };
+
+        Ok(value)
+    }
+
+    fn truth(value: DataType) -> Result<bool, String> {
+        match value {
+            DataType::Bool(value) => Ok(value),
+            value => Err(format!("Expected a bool condition but found {}", Executor::type_name(&value))),
+        }
+    }
+
+    fn check_type(column: &str, column_type: ColumnType, value: &DataType) -> Result<(), String> {
+        if column_type.matches(value) {
+            return Ok(());
+        }
+
+        let expected = format!("{:?}", column_type).to_lowercase();
+
+        Err(format!("Expected {} for column {} but found {}", expected, column, Executor::type_name(value)))
+    }
+
+    fn check_table(table: &str) -> Result<(), String> {
+        if table != TABLE_NAME {
+            return Err(format!("No table named {}", table));
+        }
+
+        Ok(())
+    }
+
+    fn position(columns: &[(String, ColumnType)], name: &str) -> Result<usize, String> {
+        columns.iter().position(|(column, _)| column == name).ok_or(format!("No column named {}", name))
+    }
+
+    // Rows are checked against the schema before they get here, so the key is always an integer
+    fn key(values: &[DataType]) -> u32 {
+        match values.first() {
+            Some(DataType::Integer(key)) => *key,
+            _ => 0,
+        }
+    }
+
+    fn key_exists(db: &PocketDB, key: u32) -> bool {
+        db.search_by_primary_index(DataType::Integer(key)).is_some_and(|rows| !rows.is_empty())
+    }
+
+    fn type_name(value: &DataType) -> &'static str {
+        match value {
+            DataType::Integer(_) => "integer",
+            DataType::Text(_) => "text",
+            DataType::Bool(_) => "bool",
+            DataType::Meta => "meta",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{db::PocketDB, shared::enums::{ColumnType, DataType, SqlError}};
+
+    fn names(db: &PocketDB, sql: &str) -> Vec<String> {
+        db.execute(sql).unwrap().rows().iter().map(|row| match &row[0] {
+            DataType::Text(name) => name.clone(),
+            value => panic!("Expected a name but found {:?}", value),
+        }).collect()
+    }
+
+    // Three people, with `company` indexed
+    fn database() -> PocketDB {
+        let db = PocketDB::in_memory();
+        db.execute("CREATE TABLE main (key INTEGER PRIMARY KEY, name TEXT, company TEXT, age INT, married BOOL)").unwrap();
+        db.execute("INSERT INTO main VALUES (1, 'Sammie', 'Vanguard', 30, false), (2, 'Alex', 'Acme', 41, true)").unwrap();
+        db.execute("INSERT INTO main (name, key, company, age, married) VALUES ('Jo', 3, 'Vanguard', 25, true)").unwrap();
+        db.execute("CREATE INDEX company_index ON main (company)").unwrap();
+
+        db
+    }
+
+    #[test]
+    fn create_table_and_index_set_up_the_table() {
+        let db = database();
+
+        assert_eq!(db.indexed_columns(), vec![String::from("company")]);
+        assert!(db.execute("CREATE TABLE main (name TEXT)").is_err());
+    }
+
+    #[test]
+    fn insert_counts_rows_and_follows_the_column_list() {
+        let db = database();
+
+        assert_eq!(db.execute("INSERT INTO main VALUES (4, 'Sam', 'Acme', 1, true), (5, 'Kim', 'Acme', 2, false);").unwrap().rows_affected(), 2);
+        db.execute("insert into main (married, age, company, name, key) values (false, 3, 'Acme', 'Jo ''JJ''', 6)").unwrap();
+        assert_eq!(names(&db, "SELECT name FROM main WHERE key >= 4"), vec!["Sam", "Kim", "Jo 'JJ'"]);
+    }
+
+    #[test]
+    fn insert_rejects_taken_keys_and_missing_columns() {
+        let db = database();
+
+        assert_eq!(db.execute("INSERT INTO main VALUES (3, 'Sam', 'Acme', 1, true)"), Err(SqlError::Execute(String::from("Row with key 3 already exists"))));
+        assert!(db.execute("INSERT INTO main (key, name) VALUES (4, 'Sam')").is_err());
+    }
+
+    #[test]
+    fn select_projects_the_named_columns() {
+        let db = database();
+
+        let select = db.execute("SELECT name, age FROM main WHERE company = 'Vanguard' AND age > 26").unwrap();
+        assert_eq!(select.columns(), &[(String::from("name"), ColumnType::Text), (String::from("age"), ColumnType::Integer)]);
+        assert_eq!(select.rows(), &[vec![DataType::Text(String::from("Sammie")), DataType::Integer(30)]]);
+    }
+
+    #[test]
+    fn select_filters_orders_and_pages() {
+        let db = database();
+
+        assert_eq!(names(&db, "SELECT name FROM main WHERE NOT married OR age >= 41 ORDER BY age DESC"), vec!["Alex", "Sammie"]);
+        assert_eq!(names(&db, "SELECT name FROM main ORDER BY name LIMIT 2 OFFSET 1"), vec!["Jo", "Sammie"]);
+        assert_eq!(names(&db, "SELECT name FROM main WHERE key = 3"), vec!["Jo"]);
+    }
+
+    #[test]
+    fn select_rejects_unknown_tables_and_mismatched_types() {
+        let db = database();
+
+        assert_eq!(db.execute("SELECT * FROM main WHERE age = 'old'"), Err(SqlError::Execute(String::from("Can't compare integer with text"))));
+        assert!(db.execute("SELECT * FROM other").is_err());
+    }
+
+    #[test]
+    fn parse_errors_carry_their_position() {
+        let db = database();
+
+        assert_eq!(db.execute("SELECT * FROM main WHERE\n  age >"), Err(SqlError::Parse {
+            message: String::from("Expected a value but found end of input"),
+            line: 2,
+            column: 8,
+        }));
+    }
+
+    #[test]
+    fn update_changes_the_matching_rows() {
+        let db = database();
+
+        assert_eq!(db.execute("UPDATE main SET age = 42 WHERE key = 2").unwrap().rows_affected(), 1);
+        assert_eq!(names(&db, "SELECT name FROM main WHERE age = 42"), vec!["Alex"]);
+    }
+
+    // Updating rows that share an indexed value must move each of them in the index, and only them
+    #[test]
+    fn update_keeps_an_indexed_column_with_duplicates_in_step() {
+        let db = database();
+        db.execute("INSERT INTO main VALUES (4, 'Sam', 'Acme', 1, true), (5, 'Kim', 'Vanguard', 2, false), (6, 'Lee', 'Acme', 3, true)").unwrap();
+
+        assert_eq!(db.execute("UPDATE main SET company = 'Acme' WHERE key = 3").unwrap().rows_affected(), 1);
+        assert_eq!(db.execute("UPDATE main SET name = 'moved' WHERE company = 'Acme'").unwrap().rows_affected(), 4);
+        db.execute("INSERT INTO main VALUES (7, 'Ash', 'Acme', 4, false)").unwrap();
+
+        assert_eq!(names(&db, "SELECT name FROM main ORDER BY key"), vec!["Sammie", "moved", "moved", "moved", "Kim", "moved", "Ash"]);
+        assert_eq!(names(&db, "SELECT name FROM main WHERE company = 'Vanguard' ORDER BY key"), vec!["Sammie", "Kim"]);
+        assert_eq!(names(&db, "SELECT name FROM main WHERE company = 'Acme' ORDER BY key"), vec!["moved", "moved", "moved", "moved", "Ash"]);
+    }
+
+    #[test]
+    fn explain_describes_the_plan() {
+        let db = database();
+
+        assert_eq!(names(&db, "EXPLAIN SELECT name FROM main WHERE key = 1 OR age < 30"), vec![
+            "Full scan, about 3 of 3 rows",
+            "Filter (key = 1 OR age < 30)",
+        ]);
+    }
+
+    #[test]
+    fn delete_removes_the_matching_rows() {
+        let db = database();
+
+        assert_eq!(db.execute("DELETE FROM main WHERE key = 2").unwrap().rows_affected(), 1);
+        assert_eq!(names(&db, "SELECT name FROM main"), vec!["Sammie", "Jo"]);
+    }
+
+    #[test]
+    fn update_cant_assign_one_key_to_several_rows() {
+        let db = PocketDB::in_memory();
+        db.execute("CREATE TABLE main (key INTEGER PRIMARY KEY, name TEXT)").unwrap();
+        db.execute("INSERT INTO main VALUES (1, 'Sammie'), (2, 'Alex')").unwrap();
+
+        assert_eq!(db.execute("UPDATE main SET key = 5"), Err(SqlError::Execute(String::from("Can't assign key 5 to 2 rows, keys are unique"))));
+        assert_eq!(names(&db, "SELECT name FROM main"), vec!["Sammie", "Alex"]);
+    }
+
+    #[test]
+    fn update_cant_assign_a_key_another_row_has() {
+        let db = PocketDB::in_memory();
+        db.execute("CREATE TABLE main (key INTEGER PRIMARY KEY, name TEXT)").unwrap();
+        db.execute("INSERT INTO main VALUES (1, 'Sammie'), (2, 'Alex')").unwrap();
+
+        assert_eq!(db.execute("UPDATE main SET key = 2 WHERE key = 1"), Err(SqlError::Execute(String::from("Row with key 2 already exists"))));
+        assert_eq!(names(&db, "SELECT name FROM main WHERE key = 1"), vec!["Sammie"]);
+    }
+
+    // Rows inserted before `age` was added have no age, so none of them can be written back as they are
+    #[test]
+    fn update_that_fails_for_any_row_changes_none() {
+        let db = PocketDB::in_memory();
+        db.add_column("name", ColumnType::Text).unwrap();
+        for key in 1..=3 {
+            db.insert(key, PocketDB::row(key, vec![PocketDB::cell(DataType::Text(format!("name {}", key)))])).unwrap();
+        }
+        db.add_column("age", ColumnType::Integer).unwrap();
+
+        assert!(db.execute("UPDATE main SET name = 'Sam'").is_err());
+        assert_eq!(names(&db, "SELECT name FROM main"), vec!["name 1", "name 2", "name 3"]);
+    }
+}
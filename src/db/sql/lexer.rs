@@ -0,0 +1,242 @@
+use std::{iter::Peekable, str::Chars};
+
+use crate::db::shared::enums::SqlError;
+
+// Keywords come out as words too; the parser decides what a word means from where it is, so columns can share
+// names with keywords like `key`. Double quotes make a word that's never read as a keyword.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Word(String),
+    QuotedWord(String),
+    Integer(u32),
+    String(String),
+    LeftParen,
+    RightParen,
+    Comma,
+    Star,
+    Semicolon,
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    End,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+pub struct Lexer<'a> {
+    chars: Peekable<Chars<'a>>,
+    position: Position,
+}
+
+impl Lexer<'_> {
+    // Every token comes with the position it starts at. The last token is always `Token::End`.
+    pub fn tokenize(sql: &str) -> Result<Vec<(Token, Position)>, SqlError> {
+        let mut lexer = Lexer { chars: sql.chars().peekable(), position: Position { line: 1, column: 1 } };
+        let mut tokens = vec![];
+
+        loop {
+            lexer.skip_whitespace_and_comments();
+            let start = lexer.position;
+            let token = match lexer.next() {
+                Some(c) => lexer.token(c, start)?,
+                None => Token::End,
+            };
+
+            let end = token == Token::End;
+            tokens.push((token, start));
+            if end {
+                return Ok(tokens);
+            }
+        }
+    }
+
+    fn token(&mut self, c: char, start: Position) -> Result<Token, SqlError> {
+        let token = match c {
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ',' => Token::Comma,
+            '*' => Token::Star,
+            ';' => Token::Semicolon,
+            '=' => Token::Equal,
+            '!' if self.next_if_eq('=') => Token::NotEqual,
+            '<' if self.next_if_eq('=') => Token::LessOrEqual,
+            '<' if self.next_if_eq('>') => Token::NotEqual,
+            '<' => Token::Less,
+            '>' if self.next_if_eq('=') => Token::GreaterOrEqual,
+            '>' => Token::Greater,
+            '\'' => Token::String(self.quoted('\'', start)?),
+            '"' => Token::QuotedWord(self.quoted('"', start)?),
+            c if c.is_ascii_digit() => {
+                let digits = self.take_while(c, |c| c.is_ascii_digit());
+                let integer = digits.parse().map_err(|_| Lexer::error(format!("Integer {} is out of range", digits), start))?;
+
+                Token::Integer(integer)
+            },
+            c if c.is_alphabetic() || c == '_' => Token::Word(self.take_while(c, |c| c.is_alphanumeric() || c == '_')),
+            c => return Err(Lexer::error(format!("Unexpected character {:?}", c), start)),
+        };
+
+        Ok(token)
+    }
+
+    // A doubled quote inside the quotes stands for one quote
+    fn quoted(&mut self, quote: char, start: Position) -> Result<String, SqlError> {
+        let mut value = String::new();
+        loop {
+            match self.next() {
+                Some(c) if c == quote && self.next_if_eq(quote) => value.push(quote),
+                Some(c) if c == quote => return Ok(value),
+                Some(c) => value.push(c),
+                None => return Err(Lexer::error(String::from("Unterminated quote"), start)),
+            }
+        }
+    }
+
+    fn take_while(&mut self, first: char, predicate: impl Fn(char) -> bool) -> String {
+        let mut value = String::from(first);
+        while let Some(c) = self.chars.peek().copied().filter(|c| predicate(*c)) {
+            value.push(c);
+            self.next();
+        }
+
+        value
+    }
+
+    // Comments run from `--` to the end of the line
+    fn skip_whitespace_and_comments(&mut self) {
+        loop {
+            let mut ahead = self.chars.clone();
+            match (ahead.next(), ahead.next()) {
+                (Some(c), _) if c.is_whitespace() => {
+                    self.next();
+                },
+                (Some('-'), Some('-')) => {
+                    while self.chars.peek().is_some_and(|c| *c != '\n') {
+                        self.next();
+                    }
+                },
+                _ => return,
+            }
+        }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.position = Position { line: self.position.line + 1, column: 1 };
+        } else {
+            self.position.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn next_if_eq(&mut self, expected: char) -> bool {
+        if self.chars.peek() == Some(&expected) {
+            self.next();
+            return true;
+        }
+
+        false
+    }
+
+    fn error(message: String, position: Position) -> SqlError {
+        SqlError::Parse { message, line: position.line, column: position.column }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::shared::enums::SqlError;
+
+    use super::{Lexer, Position, Token};
+
+    fn tokens(sql: &str) -> Vec<Token> {
+        Lexer::tokenize(sql).unwrap().into_iter().map(|(token, _)| token).collect()
+    }
+
+    fn parse_error(message: &str, line: usize, column: usize) -> SqlError {
+        SqlError::Parse { message: String::from(message), line, column }
+    }
+
+    #[test]
+    fn words_numbers_and_punctuation_become_tokens() {
+        assert_eq!(tokens("SELECT * FROM main WHERE (key, 12);"), vec![
+            Token::Word(String::from("SELECT")),
+            Token::Star,
+            Token::Word(String::from("FROM")),
+            Token::Word(String::from("main")),
+            Token::Word(String::from("WHERE")),
+            Token::LeftParen,
+            Token::Word(String::from("key")),
+            Token::Comma,
+            Token::Integer(12),
+            Token::RightParen,
+            Token::Semicolon,
+            Token::End,
+        ]);
+    }
+
+    #[test]
+    fn comparison_operators_take_one_or_two_characters() {
+        assert_eq!(tokens("= != <> < <= > >="), vec![
+            Token::Equal,
+            Token::NotEqual,
+            Token::NotEqual,
+            Token::Less,
+            Token::LessOrEqual,
+            Token::Greater,
+            Token::GreaterOrEqual,
+            Token::End,
+        ]);
+    }
+
+    #[test]
+    fn doubled_quotes_stand_for_one_quote() {
+        assert_eq!(tokens("'Jo ''JJ''' \"my \"\"column\"\"\""), vec![
+            Token::String(String::from("Jo 'JJ'")),
+            Token::QuotedWord(String::from("my \"column\"")),
+            Token::End,
+        ]);
+    }
+
+    #[test]
+    fn comments_run_to_the_end_of_the_line() {
+        assert_eq!(tokens("-- all of it\nSELECT -- columns\n*"), vec![Token::Word(String::from("SELECT")), Token::Star, Token::End]);
+    }
+
+    #[test]
+    fn tokens_start_at_their_line_and_column() {
+        let positions: Vec<Position> = Lexer::tokenize("SELECT name\n  FROM main").unwrap().into_iter().map(|(_, position)| position).collect();
+
+        assert_eq!(positions, vec![
+            Position { line: 1, column: 1 },
+            Position { line: 1, column: 8 },
+            Position { line: 2, column: 3 },
+            Position { line: 2, column: 8 },
+            Position { line: 2, column: 12 },
+        ]);
+    }
+
+    #[test]
+    fn unterminated_quote_is_reported_where_it_opens() {
+        assert_eq!(Lexer::tokenize("SELECT\n  'open"), Err(parse_error("Unterminated quote", 2, 3)));
+    }
+
+    #[test]
+    fn unexpected_character_is_reported_where_it_is() {
+        assert_eq!(Lexer::tokenize("SELECT name + 1"), Err(parse_error("Unexpected character '+'", 1, 13)));
+    }
+
+    #[test]
+    fn integer_past_u32_is_out_of_range() {
+        assert_eq!(Lexer::tokenize("LIMIT 4294967296"), Err(parse_error("Integer 4294967296 is out of range", 1, 7)));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod ast;
+pub mod executor;
+pub mod lexer;
+pub mod parser;
+pub mod result_set;
@@ -0,0 +1,524 @@
+use crate::db::shared::enums::{ColumnType, DataType, SqlError};
+
+use super::{ast::{ColumnDefinition, Expression, Operator, OrderBy, Projection, Statement}, lexer::{Lexer, Position, Token}};
+
+// A recursive descent parser over the lexer's tokens. Keywords are matched without regard to case.
+pub struct Parser {
+    tokens: Vec<(Token, Position)>,
+    i: usize,
+}
+
+impl Parser {
+    // Parses one statement, optionally ending in a semicolon
+    pub fn parse(sql: &str) -> Result<Statement, SqlError> {
+        let mut parser = Parser { tokens: Lexer::tokenize(sql)?, i: 0 };
+        let statement = parser.statement()?;
+
+        parser.next_if(&Token::Semicolon);
+        if parser.peek() != &Token::End {
+            return Err(parser.error(format!("Expected end of statement but found {}", parser.describe())));
+        }
+
+        Ok(statement)
+    }
+
+    fn statement(&mut self) -> Result<Statement, SqlError> {
+        if self.keyword("explain") {
+            return Ok(Statement::Explain(Box::new(self.statement()?)));
+        }
+        if self.keyword("create") {
+            if self.keyword("table") {
+                return self.create_table();
+            }
+            if self.keyword("index") {
+                return self.create_index();
+            }
+
+            return Err(self.error(format!("Expected TABLE or INDEX but found {}", self.describe())));
+        }
+        if self.keyword("insert") {
+            return self.insert();
+        }
+        if self.keyword("select") {
+            return self.select();
+        }
+        if self.keyword("update") {
+            return self.update();
+        }
+        if self.keyword("delete") {
+            return self.delete();
+        }
+
+        Err(self.error(format!("Expected a statement but found {}", self.describe())))
+    }
+
+    // CREATE TABLE table (column type [PRIMARY KEY], ...)
+    fn create_table(&mut self) -> Result<Statement, SqlError> {
+        let table = self.identifier()?;
+        let columns = self.list(|parser| {
+            let name = parser.identifier()?;
+            let column_type = parser.column_type()?;
+            let primary_key = parser.keyword("primary");
+            if primary_key {
+                parser.expect_keyword("key")?;
+            }
+
+            Ok(ColumnDefinition { name, column_type, primary_key })
+        })?;
+
+        Ok(Statement::CreateTable { table, columns })
+    }
+
+    // CREATE INDEX [name] ON table (column). Indexes are known by their column, so the name is only checked.
+    fn create_index(&mut self) -> Result<Statement, SqlError> {
+        if !self.keyword("on") {
+            self.identifier()?;
+            self.expect_keyword("on")?;
+        }
+        let table = self.identifier()?;
+
+        self.expect(Token::LeftParen)?;
+        let column = self.identifier()?;
+        self.expect(Token::RightParen)?;
+
+        Ok(Statement::CreateIndex { table, column })
+    }
+
+    // INSERT INTO table [(column, ...)] VALUES (value, ...), ...
+    fn insert(&mut self) -> Result<Statement, SqlError> {
+        self.expect_keyword("into")?;
+        let table = self.identifier()?;
+        let columns = if self.peek() == &Token::LeftParen {
+            Some(self.list(Parser::identifier)?)
+        } else {
+            None
+        };
+
+        self.expect_keyword("values")?;
+        let mut rows = vec![self.list(Parser::literal)?];
+        while self.next_if(&Token::Comma) {
+            rows.push(self.list(Parser::literal)?);
+        }
+
+        Ok(Statement::Insert { table, columns, rows })
+    }
+
+    // SELECT * | column, ... FROM table [WHERE expression] [ORDER BY column [ASC | DESC]] [LIMIT n [OFFSET n]]
+    fn select(&mut self) -> Result<Statement, SqlError> {
+        let projection = if self.next_if(&Token::Star) {
+            Projection::All
+        } else {
+            let mut columns = vec![self.identifier()?];
+            while self.next_if(&Token::Comma) {
+                columns.push(self.identifier()?);
+            }
+            Projection::Columns(columns)
+        };
+
+        self.expect_keyword("from")?;
+        let table = self.identifier()?;
+        let filter = self.filter()?;
+
+        let order_by = if self.keyword("order") {
+            self.expect_keyword("by")?;
+            let column = self.identifier()?;
+            let descending = self.keyword("desc");
+            if !descending {
+                self.keyword("asc");
+            }
+
+            Some(OrderBy { column, descending })
+        } else {
+            None
+        };
+
+        let (limit, offset) = if self.keyword("limit") {
+            let limit = self.integer()?;
+            let offset = if self.keyword("offset") { Some(self.integer()?) } else { None };
+
+            (Some(limit), offset)
+        } else {
+            (None, None)
+        };
+
+        Ok(Statement::Select { table, projection, filter, order_by, limit, offset })
+    }
+
+    // UPDATE table SET column = value, ... [WHERE expression]
+    fn update(&mut self) -> Result<Statement, SqlError> {
+        let table = self.identifier()?;
+        self.expect_keyword("set")?;
+
+        let mut assignments = vec![];
+        loop {
+            let column = self.identifier()?;
+            self.expect(Token::Equal)?;
+            assignments.push((column, self.literal()?));
+
+            if !self.next_if(&Token::Comma) {
+                break;
+            }
+        }
+        let filter = self.filter()?;
+
+        Ok(Statement::Update { table, assignments, filter })
+    }
+
+    // DELETE FROM table [WHERE expression]
+    fn delete(&mut self) -> Result<Statement, SqlError> {
+        self.expect_keyword("from")?;
+        let table = self.identifier()?;
+        let filter = self.filter()?;
+
+        Ok(Statement::Delete { table, filter })
+    }
+
+    fn filter(&mut self) -> Result<Option<Expression>, SqlError> {
+        if self.keyword("where") {
+            return self.expression().map(Some);
+        }
+
+        Ok(None)
+    }
+
+    // OR binds loosest, then AND, then NOT, then comparisons
+    fn expression(&mut self) -> Result<Expression, SqlError> {
+        let mut expression = self.and_expression()?;
+        while self.keyword("or") {
+            expression = Expression::Or(Box::new(expression), Box::new(self.and_expression()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn and_expression(&mut self) -> Result<Expression, SqlError> {
+        let mut expression = self.not_expression()?;
+        while self.keyword("and") {
+            expression = Expression::And(Box::new(expression), Box::new(self.not_expression()?));
+        }
+
+        Ok(expression)
+    }
+
+    fn not_expression(&mut self) -> Result<Expression, SqlError> {
+        if self.keyword("not") {
+            return Ok(Expression::Not(Box::new(self.not_expression()?)));
+        }
+
+        let left = self.operand()?;
+        let operator = match self.peek() {
+            Token::Equal => Operator::Equal,
+            Token::NotEqual => Operator::NotEqual,
+            Token::Less => Operator::Less,
+            Token::LessOrEqual => Operator::LessOrEqual,
+            Token::Greater => Operator::Greater,
+            Token::GreaterOrEqual => Operator::GreaterOrEqual,
+            _ => return Ok(left),
+        };
+        self.i += 1;
+
+        Ok(Expression::Compare(Box::new(left), operator, Box::new(self.operand()?)))
+    }
+
+    fn operand(&mut self) -> Result<Expression, SqlError> {
+        if self.next_if(&Token::LeftParen) {
+            let expression = self.expression()?;
+            self.expect(Token::RightParen)?;
+
+            return Ok(expression);
+        }
+
+        match self.peek() {
+            Token::Word(word) if !Parser::is_boolean(word) => self.identifier().map(Expression::Column),
+            Token::QuotedWord(_) => self.identifier().map(Expression::Column),
+            _ => self.literal().map(Expression::Literal),
+        }
+    }
+
+    fn literal(&mut self) -> Result<DataType, SqlError> {
+        let literal = match self.peek() {
+            Token::Integer(value) => DataType::Integer(*value),
+            Token::String(value) => DataType::Text(value.clone()),
+            Token::Word(word) if Parser::is_boolean(word) => DataType::Bool(word.eq_ignore_ascii_case("true")),
+            _ => return Err(self.error(format!("Expected a value but found {}", self.describe()))),
+        };
+        self.i += 1;
+
+        Ok(literal)
+    }
+
+    fn integer(&mut self) -> Result<usize, SqlError> {
+        match self.peek() {
+            Token::Integer(value) => {
+                let value = *value as usize;
+                self.i += 1;
+
+                Ok(value)
+            },
+            _ => Err(self.error(format!("Expected an integer but found {}", self.describe()))),
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, SqlError> {
+        match self.peek() {
+            Token::Word(word) | Token::QuotedWord(word) => {
+                let word = word.clone();
+                self.i += 1;
+
+                Ok(word)
+            },
+            _ => Err(self.error(format!("Expected a name but found {}", self.describe()))),
+        }
+    }
+
+    fn column_type(&mut self) -> Result<ColumnType, SqlError> {
+        let column_type = match self.peek() {
+            Token::Word(word) => match word.to_ascii_lowercase().as_str() {
+                "integer" | "int" => Some(ColumnType::Integer),
+                "text" => Some(ColumnType::Text),
+                "bool" | "boolean" => Some(ColumnType::Bool),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        match column_type {
+            Some(column_type) => {
+                self.i += 1;
+                Ok(column_type)
+            },
+            None => Err(self.error(format!("Expected INTEGER, TEXT or BOOL but found {}", self.describe()))),
+        }
+    }
+
+    // A parenthesised, comma separated list with at least one item
+    fn list<T>(&mut self, item: impl Fn(&mut Parser) -> Result<T, SqlError>) -> Result<Vec<T>, SqlError> {
+        self.expect(Token::LeftParen)?;
+
+        let mut items = vec![item(self)?];
+        while self.next_if(&Token::Comma) {
+            items.push(item(self)?);
+        }
+        self.expect(Token::RightParen)?;
+
+        Ok(items)
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Token::Word(word) if word.eq_ignore_ascii_case(keyword) => {
+                self.i += 1;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), SqlError> {
+        if self.keyword(keyword) {
+            return Ok(());
+        }
+
+        Err(self.error(format!("Expected {} but found {}", keyword.to_uppercase(), self.describe())))
+    }
+
+    fn expect(&mut self, token: Token) -> Result<(), SqlError> {
+        if self.next_if(&token) {
+            return Ok(());
+        }
+
+        Err(self.error(format!("Expected {} but found {}", Parser::describe_token(&token), self.describe())))
+    }
+
+    fn next_if(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.i += 1;
+            return true;
+        }
+
+        false
+    }
+
+    // The lexer always ends with `Token::End`, which is never stepped past
+    fn peek(&self) -> &Token {
+        &self.tokens[self.i.min(self.tokens.len() - 1)].0
+    }
+
+    fn is_boolean(word: &str) -> bool {
+        word.eq_ignore_ascii_case("true") || word.eq_ignore_ascii_case("false")
+    }
+
+    fn error(&self, message: String) -> SqlError {
+        let (_, position) = self.tokens[self.i.min(self.tokens.len() - 1)];
+
+        SqlError::Parse { message, line: position.line, column: position.column }
+    }
+
+    fn describe(&self) -> String {
+        Parser::describe_token(self.peek())
+    }
+
+    fn describe_token(token: &Token) -> String {
+        match token {
+            Token::Word(word) => word.clone(),
+            Token::QuotedWord(word) => format!("\"{}\"", word),
+            Token::Integer(value) => value.to_string(),
+            Token::String(value) => format!("'{}'", value),
+            Token::LeftParen => String::from("("),
+            Token::RightParen => String::from(")"),
+            Token::Comma => String::from(","),
+            Token::Star => String::from("*"),
+            Token::Semicolon => String::from(";"),
+            Token::Equal => String::from("="),
+            Token::NotEqual => String::from("!="),
+            Token::Less => String::from("<"),
+            Token::LessOrEqual => String::from("<="),
+            Token::Greater => String::from(">"),
+            Token::GreaterOrEqual => String::from(">="),
+            Token::End => String::from("end of input"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::shared::enums::{ColumnType, DataType, SqlError};
+
+    use super::{ColumnDefinition, Expression, Operator, OrderBy, Parser, Projection, Statement};
+
+    fn filter(sql: &str) -> String {
+        match Parser::parse(sql).unwrap() {
+            Statement::Select { filter, .. } => filter.unwrap().to_string(),
+            statement => panic!("Expected a SELECT but parsed {:?}", statement),
+        }
+    }
+
+    fn parse_error(message: &str, line: usize, column: usize) -> SqlError {
+        SqlError::Parse { message: String::from(message), line, column }
+    }
+
+    #[test]
+    fn create_table_lists_columns_and_the_primary_key() {
+        assert_eq!(Parser::parse("CREATE TABLE main (key INTEGER PRIMARY KEY, name TEXT, active BOOLEAN)"), Ok(Statement::CreateTable {
+            table: String::from("main"),
+            columns: vec![
+                ColumnDefinition { name: String::from("key"), column_type: ColumnType::Integer, primary_key: true },
+                ColumnDefinition { name: String::from("name"), column_type: ColumnType::Text, primary_key: false },
+                ColumnDefinition { name: String::from("active"), column_type: ColumnType::Bool, primary_key: false },
+            ],
+        }));
+    }
+
+    #[test]
+    fn create_index_name_is_optional() {
+        let expected = Ok(Statement::CreateIndex { table: String::from("main"), column: String::from("name") });
+
+        assert_eq!(Parser::parse("CREATE INDEX by_name ON main (name)"), expected);
+        assert_eq!(Parser::parse("CREATE INDEX ON main (name)"), expected);
+    }
+
+    #[test]
+    fn insert_takes_several_rows_and_an_optional_column_list() {
+        assert_eq!(Parser::parse("INSERT INTO main (key, name) VALUES (1, 'a'), (2, 'b')"), Ok(Statement::Insert {
+            table: String::from("main"),
+            columns: Some(vec![String::from("key"), String::from("name")]),
+            rows: vec![
+                vec![DataType::Integer(1), DataType::Text(String::from("a"))],
+                vec![DataType::Integer(2), DataType::Text(String::from("b"))],
+            ],
+        }));
+        assert_eq!(Parser::parse("INSERT INTO main VALUES (1, TRUE)"), Ok(Statement::Insert {
+            table: String::from("main"),
+            columns: None,
+            rows: vec![vec![DataType::Integer(1), DataType::Bool(true)]],
+        }));
+    }
+
+    #[test]
+    fn select_reads_every_clause() {
+        assert_eq!(Parser::parse("SELECT name, key FROM main WHERE key > 2 ORDER BY name DESC LIMIT 5 OFFSET 1"), Ok(Statement::Select {
+            table: String::from("main"),
+            projection: Projection::Columns(vec![String::from("name"), String::from("key")]),
+            filter: Some(Expression::Compare(
+                Box::new(Expression::Column(String::from("key"))),
+                Operator::Greater,
+                Box::new(Expression::Literal(DataType::Integer(2))),
+            )),
+            order_by: Some(OrderBy { column: String::from("name"), descending: true }),
+            limit: Some(5),
+            offset: Some(1),
+        }));
+    }
+
+    #[test]
+    fn keywords_ignore_case_and_a_semicolon_may_end_the_statement() {
+        assert_eq!(Parser::parse("select * from main;"), Parser::parse("SELECT * FROM main"));
+    }
+
+    #[test]
+    fn update_and_delete_take_a_filter() {
+        assert_eq!(Parser::parse("UPDATE main SET name = 'b', active = false WHERE key = 1"), Ok(Statement::Update {
+            table: String::from("main"),
+            assignments: vec![
+                (String::from("name"), DataType::Text(String::from("b"))),
+                (String::from("active"), DataType::Bool(false)),
+            ],
+            filter: Some(Expression::Compare(
+                Box::new(Expression::Column(String::from("key"))),
+                Operator::Equal,
+                Box::new(Expression::Literal(DataType::Integer(1))),
+            )),
+        }));
+        assert_eq!(Parser::parse("DELETE FROM main"), Ok(Statement::Delete { table: String::from("main"), filter: None }));
+    }
+
+    #[test]
+    fn explain_wraps_a_statement() {
+        assert_eq!(
+            Parser::parse("EXPLAIN DELETE FROM main"),
+            Ok(Statement::Explain(Box::new(Statement::Delete { table: String::from("main"), filter: None }))),
+        );
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or_and_not_tighter_than_and() {
+        assert_eq!(filter("SELECT * FROM main WHERE a = 1 OR b = 2 AND c = 3"), "(a = 1 OR (b = 2 AND c = 3))");
+        assert_eq!(filter("SELECT * FROM main WHERE NOT a = 1 AND b = 2"), "(NOT a = 1 AND b = 2)");
+        assert_eq!(filter("SELECT * FROM main WHERE (a = 1 OR b = 2) AND c = 3"), "((a = 1 OR b = 2) AND c = 3)");
+    }
+
+    #[test]
+    fn trailing_tokens_are_reported_where_they_start() {
+        assert_eq!(Parser::parse("SELECT * FROM main extra"), Err(parse_error("Expected end of statement but found extra", 1, 20)));
+    }
+
+    #[test]
+    fn unknown_statement_is_reported_at_its_first_word() {
+        assert_eq!(Parser::parse("  DROP TABLE main"), Err(parse_error("Expected a statement but found DROP", 1, 3)));
+        assert_eq!(Parser::parse("CREATE VIEW main"), Err(parse_error("Expected TABLE or INDEX but found VIEW", 1, 8)));
+    }
+
+    #[test]
+    fn missing_pieces_are_reported_on_the_line_they_are_missing_from() {
+        assert_eq!(
+            Parser::parse("SELECT *\nFROM main\nWHERE key ="),
+            Err(parse_error("Expected a value but found end of input", 3, 12)),
+        );
+        assert_eq!(
+            Parser::parse("CREATE TABLE main (key FLOAT)"),
+            Err(parse_error("Expected INTEGER, TEXT or BOOL but found FLOAT", 1, 24)),
+        );
+        assert_eq!(
+            Parser::parse("INSERT main VALUES (1)"),
+            Err(parse_error("Expected INTO but found main", 1, 8)),
+        );
+        assert_eq!(
+            Parser::parse("SELECT * FROM main LIMIT 'five'"),
+            Err(parse_error("Expected an integer but found 'five'", 1, 26)),
+        );
+        assert_eq!(
+            Parser::parse("CREATE INDEX ON main (name"),
+            Err(parse_error("Expected ) but found end of input", 1, 27)),
+        );
+    }
+}
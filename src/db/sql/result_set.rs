@@ -0,0 +1,33 @@
+use crate::db::shared::enums::{ColumnType, DataType};
+
+// What a statement returns. SELECT fills in columns and rows; the other statements report how many rows they
+// inserted, updated or deleted.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ResultSet {
+    columns: Vec<(String, ColumnType)>,
+    rows: Vec<Vec<DataType>>,
+    rows_affected: usize,
+}
+
+impl ResultSet {
+    pub(crate) fn new(columns: Vec<(String, ColumnType)>, rows: Vec<Vec<DataType>>) -> ResultSet {
+        ResultSet { columns, rows, rows_affected: 0 }
+    }
+
+    pub(crate) fn affected(rows_affected: usize) -> ResultSet {
+        ResultSet { rows_affected, ..ResultSet::default() }
+    }
+
+    pub fn columns(&self) -> &[(String, ColumnType)] {
+        &self.columns
+    }
+
+    // Values are in the same order as `columns`
+    pub fn rows(&self) -> &[Vec<DataType>] {
+        &self.rows
+    }
+
+    pub fn rows_affected(&self) -> usize {
+        self.rows_affected
+    }
+}
@@ -0,0 +1,198 @@
+use crate::db::{db::PocketDB, shared::enums::{ColumnType, DataType}};
+
+use super::disk_storage::row::Row;
+
+// A Rust type that can be stored in a column. The derive below only accepts fields of these types.
+pub trait ColumnValue: Sized {
+    const COLUMN_TYPE: ColumnType;
+
+    fn into_data_type(self) -> DataType;
+
+    fn from_data_type(value: &DataType) -> Option<Self>;
+}
+
+impl ColumnValue for u32 {
+    const COLUMN_TYPE: ColumnType = ColumnType::Integer;
+
+    fn into_data_type(self) -> DataType {
+        DataType::Integer(self)
+    }
+
+    fn from_data_type(value: &DataType) -> Option<u32> {
+        match value {
+            DataType::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl ColumnValue for String {
+    const COLUMN_TYPE: ColumnType = ColumnType::Text;
+
+    fn into_data_type(self) -> DataType {
+        DataType::Text(self)
+    }
+
+    fn from_data_type(value: &DataType) -> Option<String> {
+        match value {
+            DataType::Text(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl ColumnValue for bool {
+    const COLUMN_TYPE: ColumnType = ColumnType::Bool;
+
+    fn into_data_type(self) -> DataType {
+        DataType::Bool(self)
+    }
+
+    fn from_data_type(value: &DataType) -> Option<bool> {
+        match value {
+            DataType::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+// A struct stored as a row, one column per field in the order they're declared. Usually derived:
+// `#[derive(PocketRecord)] struct Person { #[key] id: u32, firstname: String, #[index] company: String }`
+// The `#[key]` field is the row's key; every other field is a column named after it, indexed if marked `#[index]`.
+pub trait PocketRecord: Sized {
+    // Name, type and whether it's indexed, for each column but the key
+    fn columns() -> Vec<(&'static str, ColumnType, bool)>;
+
+    fn key(&self) -> u32;
+
+    fn into_row(self) -> Row;
+
+    // From a row's values without its head, key first, as `Record::values` has them
+    fn from_values(values: &[DataType]) -> Option<Self>;
+
+    // From a row as the lookups return it. None if a value is missing or of the wrong type.
+    fn from_row(row: &[DataType]) -> Option<Self> {
+        Self::from_values(row.get(1..)?)
+    }
+
+    // Adds the columns the table doesn't have yet, so it can be called every time the database is opened. Fails if
+    // the table's columns aren't the first of the record's, in the same order and of the same types.
+    fn register(db: &PocketDB) -> Result<(), String> {
+        let existing = db.columns();
+        let columns = Self::columns();
+        for (i, (name, column_type)) in existing.iter().skip(1).enumerate() {
+            match columns.get(i) {
+                Some((expected, expected_type, _)) if expected == name && expected_type == column_type => {},
+                Some((expected, expected_type, _)) if expected == name => {
+                    let (column_type, expected_type) = (format!("{:?}", column_type).to_lowercase(), format!("{:?}", expected_type).to_lowercase());
+                    return Err(format!("Column {} is {} in the table but {} in the record", name, column_type, expected_type));
+                },
+                Some((expected, _, _)) => return Err(format!("Column {} of the table is {} in the record", name, expected)),
+                None => return Err(format!("Column {} of the table isn't in the record", name)),
+            }
+        }
+
+        for (name, column_type, indexed) in columns.into_iter().skip(existing.len().saturating_sub(1)) {
+            if indexed {
+                db.add_indexed_column(name, column_type)?;
+            } else {
+                db.add_column(name, column_type)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{db::PocketDB, shared::enums::{ColumnType, DataType}};
+
+    use super::PocketRecord;
+
+    #[derive(Debug, Clone, PartialEq, pocket_db_derive::PocketRecord)]
+    struct Person {
+        #[key]
+        id: u32,
+        firstname: String,
+        #[index]
+        company: String,
+        age: u32,
+        active: bool,
+    }
+
+    fn person(id: u32, company: &str) -> Person {
+        Person { id, firstname: format!("name {}", id), company: company.to_string(), age: 20 + id, active: id.is_multiple_of(2) }
+    }
+
+    // The three people registered and inserted
+    fn database() -> PocketDB {
+        let db = PocketDB::in_memory();
+        Person::register(&db).unwrap();
+        for (id, company) in [(1, "Acme"), (2, "Vanguard"), (3, "Acme")] {
+            let person = person(id, company);
+            db.insert(person.key(), person.into_row()).unwrap();
+        }
+
+        db
+    }
+
+    #[test]
+    fn derived_columns_follow_the_fields_after_the_key() {
+        assert_eq!(Person::columns(), vec![
+            ("firstname", ColumnType::Text, false),
+            ("company", ColumnType::Text, true),
+            ("age", ColumnType::Integer, false),
+            ("active", ColumnType::Bool, false),
+        ]);
+    }
+
+    #[test]
+    fn registering_adds_the_columns_and_indexes() {
+        let db = PocketDB::in_memory();
+        Person::register(&db).unwrap();
+
+        assert_eq!(db.columns().len(), 5);
+        assert_eq!(db.indexed_columns(), vec![String::from("company")]);
+    }
+
+    #[test]
+    fn registering_twice_changes_nothing() {
+        let db = PocketDB::in_memory();
+        Person::register(&db).unwrap();
+        Person::register(&db).unwrap();
+
+        assert_eq!(db.columns().len(), 5);
+        assert_eq!(db.indexed_columns(), vec![String::from("company")]);
+    }
+
+    #[test]
+    fn registering_against_a_different_table_fails() {
+        let db = PocketDB::in_memory();
+        db.add_column("lastname", ColumnType::Text).unwrap();
+
+        assert_eq!(Person::register(&db), Err(String::from("Column lastname of the table is firstname in the record")));
+    }
+
+    #[test]
+    fn records_round_trip_through_rows() {
+        let db = database();
+
+        let rows = db.search_by_primary_index(DataType::Integer(2)).unwrap();
+        assert_eq!(Person::from_row(&rows[0]), Some(person(2, "Vanguard")));
+    }
+
+    #[test]
+    fn records_round_trip_through_query_values() {
+        let db = database();
+
+        let records = db.query().order_by("key").fetch().unwrap();
+        let people: Vec<Person> = records.iter().filter_map(|record| Person::from_values(record.values())).collect();
+        assert_eq!(people, vec![person(1, "Acme"), person(2, "Vanguard"), person(3, "Acme")]);
+    }
+
+    #[test]
+    fn values_missing_fields_map_to_no_record() {
+        assert_eq!(Person::from_values(&[DataType::Integer(1), DataType::from("Sammie")]), None);
+    }
+}
@@ -1,2 +1,4 @@
 pub mod table;
-pub mod disk_storage;
\ No newline at end of file
+pub mod disk_storage;
+pub mod mapping;
+pub mod row_builder;
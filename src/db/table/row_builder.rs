@@ -0,0 +1,101 @@
+use crate::db::{db::PocketDB, shared::enums::{ColumnType, DataType, RowBuilderError}};
+
+use super::disk_storage::row::Row;
+
+// A row put together by column name and checked against the table's columns when it's built:
+// `db.row_builder().key(1).set("firstname", "Sammie").set("ismarried", false).build()?`
+// Columns can be set in any order, and setting one twice keeps the last value. Columns left unset hold their type's
+// default, see `ColumnType::default_value`; the key has none and has to be set, with `key` or as the "key" column.
+#[derive(Debug, Clone)]
+pub struct RowBuilder {
+    columns: Vec<(String, ColumnType)>,
+    values: Vec<(String, DataType)>,
+}
+
+impl RowBuilder {
+    pub(crate) fn new(columns: Vec<(String, ColumnType)>) -> RowBuilder {
+        RowBuilder { columns, values: vec![] }
+    }
+
+    pub fn key(&mut self, key: u32) -> &mut Self {
+        self.set("key", key)
+    }
+
+    pub fn set(&mut self, column: &str, value: impl Into<DataType>) -> &mut Self {
+        let value = value.into();
+        match self.values.iter_mut().find(|(name, _)| name == column) {
+            Some((_, existing)) => *existing = value,
+            None => self.values.push((column.to_string(), value)),
+        }
+
+        self
+    }
+
+    // Fails on the first column, in the order they were set, that the table doesn't have or whose value isn't of its
+    // type, then if the key wasn't set
+    pub fn build(&self) -> Result<Row, RowBuilderError> {
+        for (column, value) in &self.values {
+            let (_, column_type) = self.columns
+                .iter()
+                .find(|(name, _)| name == column)
+                .ok_or(RowBuilderError::NoColumn(column.clone()))?;
+            if !column_type.matches(value) {
+                return Err(RowBuilderError::WrongType { column: column.clone(), expected: *column_type, found: value.column_type() });
+            }
+        }
+
+        let Some(DataType::Integer(key)) = self.value("key") else {
+            return Err(RowBuilderError::NoKey);
+        };
+        // Column 0 is the key, which the row keeps apart from its cells
+        let cells = self.columns
+            .iter()
+            .skip(1)
+            .map(|(name, column_type)| PocketDB::cell(self.value(name).cloned().unwrap_or(column_type.default_value())))
+            .collect();
+
+        Ok(PocketDB::row(*key, cells))
+    }
+
+    fn value(&self, column: &str) -> Option<&DataType> {
+        self.values.iter().find(|(name, _)| name == column).map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{db::PocketDB, shared::enums::{ColumnType, DataType, RowBuilderError}};
+
+    #[test]
+    fn builds_rows_in_column_order_with_defaults() {
+        let db = PocketDB::in_memory();
+        db.add_column("firstname", ColumnType::Text).unwrap().add_indexed_column("company", ColumnType::Text).unwrap()
+            .add_column("age", ColumnType::Integer).unwrap().add_column("ismarried", ColumnType::Bool).unwrap();
+
+        let row = db.row_builder().set("ismarried", true).set("firstname", "Sammie").key(1).set("age", 31u32).set("age", 32u32).build().unwrap();
+        db.insert_row(row).unwrap();
+        db.insert_row(db.row_builder().key(2).set("company", "Acme").build().unwrap()).unwrap();
+
+        let values: Vec<Vec<DataType>> = db.query().order_by("key").fetch().unwrap().into_iter().map(|record| record.into_values()).collect();
+        assert_eq!(values, vec![
+            vec![DataType::Integer(1), DataType::from("Sammie"), DataType::from(""), DataType::Integer(32), DataType::Bool(true)],
+            vec![DataType::Integer(2), DataType::from(""), DataType::from("Acme"), DataType::Integer(0), DataType::Bool(false)],
+        ]);
+        assert_eq!(db.search_by_secondary_index(DataType::from("Acme"), "company").map(|rows| rows.len()), Some(1));
+
+        assert_eq!(db.row_builder().key(3).set("lastname", "Smith").build().unwrap_err(), RowBuilderError::NoColumn(String::from("lastname")));
+        let error = db.row_builder().key(3).set("ismarried", "no").build().unwrap_err();
+        assert_eq!(error, RowBuilderError::WrongType { column: String::from("ismarried"), expected: ColumnType::Bool, found: ColumnType::Text });
+        assert_eq!(error.to_string(), "Expected bool for column ismarried but found text");
+        assert_eq!(db.row_builder().set("firstname", "Alex").build().unwrap_err(), RowBuilderError::NoKey);
+        assert_eq!(db.row_builder().set("key", "3").build().unwrap_err().to_string(), "Expected integer for column key but found text");
+
+        let row = PocketDB::row(3, vec![
+            PocketDB::cell(DataType::from("Alex")),
+            PocketDB::cell(DataType::from("Acme")),
+            PocketDB::cell(DataType::from("forty")),
+            PocketDB::cell(DataType::Bool(false)),
+        ]);
+        assert_eq!(db.insert(3, row), Err(String::from("Expected integer for column age but found text")));
+    }
+}
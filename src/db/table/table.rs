@@ -77,10 +77,19 @@ impl Table {
         self.table.get_mut(&row_offset)
     }
 
+    pub fn release_rows(&mut self) {
+        self.table.clear();
+    }
+
     pub fn column(&self, i: usize) -> (String, ColumnType) {
         self.columns.column(i)
     }
 
+    // Every column but the row head, so the key column comes first
+    pub fn columns(&self) -> Vec<(String, ColumnType)> {
+        (1..self.num_columns()).map(|i| self.column(i)).collect()
+    }
+
     pub fn num_columns(&self) -> usize {
         self.columns.num_columns()
     }
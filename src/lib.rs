@@ -0,0 +1,24 @@
+// An embedded database of a single table, kept in one file or in memory. What callers need is re-exported here; the
+// trees, pages and on-disk layouts under `db` stay private.
+mod db;
+
+// What `#[derive(PocketRecord)]` generates names everything through the crate's name, including in its own tests
+extern crate self as pocket_db;
+
+pub use db::{
+    db::PocketDB,
+    file_stystem::{buffer_pool::BufferPoolStats, memory_backend::MemoryBackend, storage::StorageBackend},
+    import::{ImportReport, RowError},
+    meta::disk_storage::free_list::FreeListStats,
+    options::{CsvOptions, JsonOptions, OpenOptions},
+    shared::{constants::{page::PAGE_SIZE, sql::TABLE_NAME}, enums::{AggregateValue, Aggregation, ColumnType, DataType, Durability, EvictionPolicy, OpenError, ReadMode, RowBuilderError, SqlError}},
+    snapshot::Snapshot,
+    sql::result_set::ResultSet,
+    table::{disk_storage::{cell::Cell, row::Row}, mapping::{ColumnValue, PocketRecord}, row_builder::RowBuilder},
+};
+pub use pocket_db_derive::PocketRecord;
+
+// Building up reads beyond single lookups, and what they return
+pub mod query {
+    pub use crate::db::query::{aggregate::{Aggregate, Group}, builder::Query, filter::Filter, join::Join, planner::QueryPlan, record::Record, statistics::{IndexStatistics, Statistics}};
+}